@@ -3,16 +3,22 @@ use std::process;
 
 use anyhow::Result;
 
-use recurse::run;
+use recurse::{run, ExitError, ExitStatus};
 
 fn main() -> Result<()> {
     match run() {
         Ok(_) => {
             process::exit(0);
         }
-        Err(error) => {
-            let _ = writeln!(io::stderr(), "Error: {}", error);
-            process::exit(1);
-        }
+        Err(error) => match error.downcast_ref::<ExitStatus>() {
+            Some(status) => process::exit(status.code),
+            None => {
+                let code = error
+                    .downcast_ref::<ExitError>()
+                    .map_or(1, |exit_error| exit_error.code);
+                let _ = writeln!(io::stderr(), "Error: {}", error);
+                process::exit(code);
+            }
+        },
     }
 }
@@ -0,0 +1,333 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use crate::command::Command;
+use crate::ops::backup::{Backup, BackupOptions};
+use crate::ops::io::{atomic_write, file_hash};
+use crate::ops::journal::{format_hash, journal_dirpath, replaced_hash};
+use crate::{ExitStatus, Recurse};
+
+/// The `backups` sub-command action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BackupAction {
+    /// List the backups with the original file paths
+    List,
+    /// Restore the most recent backup of each file
+    Restore,
+    /// Remove the backups
+    Clean,
+}
+
+impl FromStr for BackupAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "list" => Ok(BackupAction::List),
+            "restore" => Ok(BackupAction::Restore),
+            "clean" => Ok(BackupAction::Clean),
+            _ => Err(anyhow!("invalid backup action '{}'", s)),
+        }
+    }
+}
+
+pub(crate) struct BackupsCommand {}
+
+impl Command for BackupsCommand {
+    fn execute(subcmd: Recurse, mut writer: impl Write) -> Result<()> {
+        if let Recurse::Backups {
+            action,
+            hidden,
+            force,
+            backup_dir,
+            backup_suffix,
+            backup_naming,
+            journal_dir,
+            keep,
+            inpath,
+        } = subcmd
+        {
+            // ------------
+            // Validations
+            // ------------
+            // 1) inpath exists, if not bail with error
+            if !inpath.exists() {
+                return Err(anyhow!(format!(
+                    "no such file or directory '{}'",
+                    inpath.display()
+                )));
+            }
+            let options = BackupOptions::new(&inpath, backup_dir, backup_suffix, backup_naming)?;
+            let backups = options.backups(hidden);
+            match action {
+                BackupAction::List => {
+                    for backup in &backups {
+                        writeln!(
+                            writer,
+                            "{} -> {}",
+                            backup.path.display(),
+                            backup.original.display()
+                        )?;
+                    }
+                }
+                BackupAction::Restore => {
+                    // files are only restored when the contents are
+                    // unchanged since the journaled replace run so that
+                    // later edits are not lost
+                    let journal_dir = match force {
+                        true => None,
+                        false => Some(journal_dirpath(journal_dir)?),
+                    };
+                    let mut refused = 0;
+                    for backup in most_recent(&backups) {
+                        let refusal = match &journal_dir {
+                            Some(journal_dir) => restore_refusal(backup, journal_dir)?,
+                            None => None,
+                        };
+                        if let Some(reason) = refusal {
+                            refused += 1;
+                            writeln!(
+                                writer,
+                                "{} {}, not restored",
+                                backup.original.display(),
+                                reason
+                            )?;
+                            continue;
+                        }
+                        BackupsCommand::restore(backup)?;
+                        writeln!(
+                            writer,
+                            "{} restored from {}",
+                            backup.original.display(),
+                            backup.path.display()
+                        )?;
+                    }
+                    if refused > 0 {
+                        writeln!(writer, "{} file(s) not restored", refused)?;
+                        writer.flush()?;
+                        return Err(ExitStatus { code: 1 }.into());
+                    }
+                }
+                BackupAction::Clean => {
+                    let mut removed = 0;
+                    for (index, backup) in backups.iter().enumerate() {
+                        // backups are in version order for each original
+                        // file, keep the last `keep` backups
+                        let newer = backups[index + 1..]
+                            .iter()
+                            .take_while(|newer| newer.original == backup.original)
+                            .count();
+                        if newer < keep {
+                            continue;
+                        }
+                        fs::remove_file(&backup.path)?;
+                        removed += 1;
+                        writeln!(writer, "{} removed", backup.path.display())?;
+                    }
+                    writeln!(writer, "{} backup(s) removed", removed)?;
+                }
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("failure to parse backups subcommand."))
+        }
+    }
+}
+
+impl BackupsCommand {
+    /// Restores the original file contents from the `backup` file.
+    fn restore(backup: &Backup) -> Result<()> {
+        let contents = fs::read(&backup.path)?;
+        if backup.original.exists() {
+            atomic_write(&backup.original, &contents, false)
+        } else {
+            if let Some(parent) = backup.original.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&backup.original, contents)?;
+            Ok(())
+        }
+    }
+}
+
+/// Returns the reason to not restore the original file of the `backup`
+/// when the file changed after the replace run that wrote the backup.
+/// The replaced file contents are recorded in the `journal_dir` undo
+/// journal.  Files without a journaled replace run are not restored.
+fn restore_refusal(backup: &Backup, journal_dir: &Path) -> Result<Option<&'static str>> {
+    if !backup.original.exists() {
+        return Ok(None);
+    }
+    let current_hash = format_hash(file_hash(&backup.original)?);
+    let backup_hash = format_hash(file_hash(&backup.path)?);
+    if current_hash == backup_hash {
+        return Ok(None);
+    }
+    match replaced_hash(journal_dir, &backup.original, &backup_hash)? {
+        Some(hash) if hash == current_hash => Ok(None),
+        Some(_) => Ok(Some("modified after the replace run")),
+        None => Ok(Some("replace run not found in the journal")),
+    }
+}
+
+/// Returns the most recent backup of each original file in the
+/// `backups` backups that are in version order.
+fn most_recent(backups: &[Backup]) -> Vec<&Backup> {
+    backups
+        .iter()
+        .enumerate()
+        .filter(|(index, backup)| {
+            backups
+                .get(index + 1)
+                .is_none_or(|next| next.original != backup.original)
+        })
+        .map(|(_, backup)| backup)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::backup::BackupNaming;
+    use crate::ops::journal::JournalRun;
+    use std::path::PathBuf;
+
+    fn backups_subcmd(action: BackupAction, inpath: &Path, keep: usize) -> Recurse {
+        Recurse::Backups {
+            action,
+            hidden: false,
+            force: false,
+            backup_dir: Some(inpath.join("backups")),
+            backup_suffix: ".bu".to_string(),
+            backup_naming: BackupNaming::Numbered,
+            journal_dir: Some(inpath.join("journal")),
+            keep,
+            inpath: inpath.join("src"),
+        }
+    }
+
+    fn backups_test() -> (tempfile::TempDir, PathBuf) {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let filepath = tmpdir.path().join("src").join("test.txt");
+        fs::create_dir_all(filepath.parent().unwrap()).unwrap();
+        fs::create_dir_all(tmpdir.path().join("backups")).unwrap();
+        fs::write(&filepath, "a check\n").unwrap();
+        for (number, contents) in [(1, "a first\n"), (2, "a test\n")] {
+            fs::write(
+                tmpdir
+                    .path()
+                    .join("backups")
+                    .join(format!("test.txt.{}.bu", number)),
+                contents,
+            )
+            .unwrap();
+        }
+        (tmpdir, filepath)
+    }
+
+    /// Records the replace of the "a test" contents of the `filepath` file
+    /// with the "a check" contents in the journal of the `tmpdir` test.
+    fn journal_replace(tmpdir: &Path, filepath: &Path) {
+        let replaced = tmpdir.join("replaced.txt");
+        fs::write(filepath, "a test\n").unwrap();
+        fs::write(&replaced, "a check\n").unwrap();
+        let run = JournalRun::create(&tmpdir.join("journal")).unwrap();
+        run.record_file(filepath, &replaced).unwrap();
+        run.finish(10).unwrap();
+        fs::rename(&replaced, filepath).unwrap();
+    }
+
+    #[test]
+    fn test_backups_list() {
+        let (tmpdir, _) = backups_test();
+        let mut output = Vec::new();
+        let res = BackupsCommand::execute(
+            backups_subcmd(BackupAction::List, tmpdir.path(), 0),
+            &mut output,
+        );
+        assert!(res.is_ok());
+        let output_string = std::str::from_utf8(&output).unwrap().replace(r"\", "/");
+        let output_vec: Vec<&str> = output_string.lines().collect();
+        assert_eq!(output_vec.len(), 2);
+        assert!(output_vec[0].contains("backups/test.txt.1.bu -> "));
+        assert!(output_vec[1].contains("backups/test.txt.2.bu -> "));
+        assert!(output_vec
+            .iter()
+            .all(|line| line.ends_with("/src/test.txt")));
+    }
+
+    #[test]
+    fn test_backups_restore_most_recent() {
+        let (tmpdir, filepath) = backups_test();
+        journal_replace(tmpdir.path(), &filepath);
+        let mut output = Vec::new();
+        let res = BackupsCommand::execute(
+            backups_subcmd(BackupAction::Restore, tmpdir.path(), 0),
+            &mut output,
+        );
+        assert!(res.is_ok());
+        assert_eq!(fs::read_to_string(&filepath).unwrap(), "a test\n");
+        assert_eq!(std::str::from_utf8(&output).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_backups_restore_refuses_modified_files() {
+        let (tmpdir, filepath) = backups_test();
+        journal_replace(tmpdir.path(), &filepath);
+        fs::write(&filepath, "a check\nan edit\n").unwrap();
+        let mut output = Vec::new();
+        let res = BackupsCommand::execute(
+            backups_subcmd(BackupAction::Restore, tmpdir.path(), 0),
+            &mut output,
+        );
+        assert_eq!(
+            res.unwrap_err().downcast::<ExitStatus>().unwrap(),
+            ExitStatus { code: 1 }
+        );
+        assert_eq!(fs::read_to_string(&filepath).unwrap(), "a check\nan edit\n");
+        let output = std::str::from_utf8(&output).unwrap();
+        assert!(output.contains("test.txt modified after the replace run, not restored\n"));
+        assert!(output.ends_with("1 file(s) not restored\n"));
+
+        // files without a journaled replace run are not restored
+        fs::remove_dir_all(tmpdir.path().join("journal")).unwrap();
+        let mut output = Vec::new();
+        let res = BackupsCommand::execute(
+            backups_subcmd(BackupAction::Restore, tmpdir.path(), 0),
+            &mut output,
+        );
+        assert!(res.is_err());
+        assert!(std::str::from_utf8(&output)
+            .unwrap()
+            .contains("replace run not found in the journal, not restored"));
+
+        // --force restores changed files
+        let mut subcmd = backups_subcmd(BackupAction::Restore, tmpdir.path(), 0);
+        if let Recurse::Backups { force, .. } = &mut subcmd {
+            *force = true;
+        }
+        assert!(BackupsCommand::execute(subcmd, &mut Vec::new()).is_ok());
+        assert_eq!(fs::read_to_string(&filepath).unwrap(), "a test\n");
+    }
+
+    #[test]
+    fn test_backups_clean_keep() {
+        let (tmpdir, _) = backups_test();
+        let mut output = Vec::new();
+        let res = BackupsCommand::execute(
+            backups_subcmd(BackupAction::Clean, tmpdir.path(), 1),
+            &mut output,
+        );
+        assert!(res.is_ok());
+        let backup_dir = tmpdir.path().join("backups");
+        assert!(!backup_dir.join("test.txt.1.bu").exists());
+        assert!(backup_dir.join("test.txt.2.bu").exists());
+        assert!(std::str::from_utf8(&output)
+            .unwrap()
+            .ends_with("1 backup(s) removed\n"));
+    }
+}
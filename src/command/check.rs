@@ -0,0 +1,308 @@
+use std::fs::read_to_string;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::command::Command;
+use crate::ops::backup::{BackupNaming, BackupOptions};
+use crate::ops::io::walk;
+use crate::ops::path::{path_has_extension, path_is_hidden, path_relative_to};
+use crate::ops::rules::{RuleSet, Severity};
+use crate::report::check::CheckReporter;
+use crate::report::checkstyle::CheckstyleReporter;
+use crate::report::junit::JunitReporter;
+use crate::report::sarif::SarifReporter;
+use crate::report::{FileStats, MatchRecord, Reporter, Summary};
+use crate::{ExitStatus, Recurse};
+
+pub(crate) struct CheckCommand {}
+
+impl Command for CheckCommand {
+    fn execute(subcmd: Recurse, mut writer: impl Write) -> Result<()> {
+        if let Recurse::Check {
+            extension,
+            hidden,
+            backup_dir,
+            backup_suffix,
+            mindepth,
+            maxdepth,
+            symlinks,
+            rules,
+            sarif,
+            junit,
+            checkstyle,
+            inpath,
+        } = subcmd
+        {
+            // ------------
+            // Validations
+            // ------------
+            // 1) inpath exists, if not bail with error
+            if !inpath.exists() {
+                return Err(anyhow!(format!(
+                    "no such file or directory '{}'",
+                    inpath.display()
+                )));
+            }
+            // 2) rules file is a valid rules definition file
+            let ruleset = RuleSet::from_file(&rules)?;
+            // the rules file includes all rule patterns, skip it when it
+            // is in the traversal
+            let rules_filepath = rules.canonicalize()?;
+
+            let mut reporter: Box<dyn Reporter> = if sarif {
+                Box::new(SarifReporter::new(&mut writer, ruleset.report_rules()))
+            } else if junit {
+                Box::new(JunitReporter::new(&mut writer, ruleset.report_rules()))
+            } else if checkstyle {
+                Box::new(CheckstyleReporter::new(&mut writer, ruleset.report_rules()))
+            } else {
+                Box::new(CheckReporter::new(&mut writer, ruleset.rules()))
+            };
+            let mut summary = Summary::default();
+            let mut errors = 0;
+
+            let has_extension_filter = extension.is_some();
+            // replace sub-command backups are excluded, the naming scheme
+            // does not change the excluded files
+            let backup =
+                BackupOptions::new(&inpath, backup_dir, backup_suffix, BackupNaming::Simple)?;
+            for entry in walk(&inpath, &mindepth, &maxdepth, &symlinks).filter_map(|f| f.ok()) {
+                if entry.metadata().unwrap().is_file() {
+                    let filepath = entry.path();
+                    if backup.excludes(filepath) {
+                        // skip replace sub-command backup files
+                        continue;
+                    } else if !hidden && path_is_hidden(filepath) {
+                        // if file is in a hidden path, skip it
+                        continue;
+                    } else if has_extension_filter
+                        && !path_has_extension(filepath, extension.as_ref().unwrap())
+                    {
+                        // if user requested extension filter, filter on it
+                        continue;
+                    } else if filepath
+                        .canonicalize()
+                        .is_ok_and(|path| path == rules_filepath)
+                    {
+                        // skip the rules file, files that can not be
+                        // resolved are not the rules file
+                        continue;
+                    }
+                    // rule globs match paths relative to the start path
+                    let relpath = path_relative_to(filepath, &inpath);
+                    errors += CheckCommand::check_filepath(
+                        filepath,
+                        relpath,
+                        &ruleset,
+                        reporter.as_mut(),
+                        &mut summary,
+                    )?;
+                }
+            }
+            reporter.finish(&summary)?;
+            drop(reporter);
+            writer.flush()?;
+            if errors > 0 {
+                // error severity rule violations fail the check
+                return Err(ExitStatus { code: 1 }.into());
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("failure to parse check subcommand."))
+        }
+    }
+}
+
+impl CheckCommand {
+    /// Reports the rule violations in the `filepath` file and returns
+    /// the number of error severity violations.  Violations are not
+    /// reported when the line is an allowed exception to the rule or
+    /// when the line or the preceding line includes an inline
+    /// `recurse:allow(rule-id)` suppression comment.
+    pub(crate) fn check_filepath(
+        filepath: &Path,
+        relpath: &Path,
+        ruleset: &RuleSet,
+        reporter: &mut dyn Reporter,
+        summary: &mut Summary,
+    ) -> Result<usize> {
+        let active: Vec<bool> = ruleset
+            .rules()
+            .iter()
+            .map(|rule| rule.applies_to(relpath))
+            .collect();
+        if !active.contains(&true) {
+            return Ok(0);
+        }
+        let pattern_set = ruleset.pattern_set();
+        let mut errors = 0;
+        match read_to_string(filepath) {
+            Ok(filestr) => {
+                let mut stats = FileStats::default();
+                if pattern_set.is_match(&filestr) {
+                    let lines: Vec<&str> = filestr.lines().collect();
+                    for (line_index, line) in lines.iter().enumerate() {
+                        let previous_line = match line_index {
+                            0 => "",
+                            _ => lines[line_index - 1],
+                        };
+                        let matches: Vec<_> = pattern_set
+                            .find_all(line)
+                            .into_iter()
+                            .filter(|mat| {
+                                let rule = ruleset.rule(mat.pattern);
+                                active[mat.pattern]
+                                    && !rule.is_allowed(line)
+                                    && !ruleset.is_suppressed(rule, line)
+                                    && !ruleset.is_suppressed(rule, previous_line)
+                            })
+                            .collect();
+                        if matches.is_empty() {
+                            continue;
+                        }
+                        if stats.matches == 0 {
+                            reporter.begin(filepath)?;
+                        }
+                        stats.matched_lines += 1;
+                        for mat in &matches {
+                            stats.matches += 1;
+                            if ruleset.rule(mat.pattern).severity == Severity::Error {
+                                errors += 1;
+                            }
+                            reporter.matched(&MatchRecord {
+                                path: filepath,
+                                line_number: line_index + 1,
+                                line,
+                                start: mat.start,
+                                end: mat.end,
+                                pattern_set,
+                                pattern_match: mat,
+                            })?;
+                        }
+                    }
+                    if stats.matches > 0 {
+                        reporter.end(filepath, &stats)?;
+                    }
+                }
+                summary.add(&stats);
+            }
+            Err(error) => match error.kind() {
+                // If this was due to invalid UTF-8 conversion
+                // on file read, then skip the file.
+                // The intent is to test files with valid
+                // UTF-8 encodings only in this subcommand
+                ErrorKind::InvalidData => {}
+                _ => return Err(anyhow!(error)),
+            },
+        }
+        Ok(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn check_subcmd(rules: &str, sarif: bool) -> Recurse {
+        Recurse::Check {
+            extension: None,
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            rules: PathBuf::from(rules),
+            sarif,
+            junit: false,
+            checkstyle: false,
+            inpath: PathBuf::from("tests/testfiles/check/src"),
+        }
+    }
+
+    #[test]
+    fn test_check_subcmd_invalid_inpath_validation() {
+        let rw = Recurse::Check {
+            extension: None,
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            rules: PathBuf::from("tests/testfiles/check/recurse.toml"),
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            inpath: PathBuf::from("path/to/bogus"),
+        };
+        let mut output = Vec::new();
+        let res = CheckCommand::execute(rw, &mut output);
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("no such file or directory"));
+    }
+
+    #[test]
+    fn test_check_missing_rules_file() {
+        let rw = check_subcmd("tests/testfiles/check/bogus.toml", false);
+        let mut output = Vec::new();
+        let res = CheckCommand::execute(rw, &mut output);
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("unable to read rules file"));
+    }
+
+    #[test]
+    fn test_check_error_violations() {
+        let rw = check_subcmd("tests/testfiles/check/recurse.toml", false);
+        let mut output = Vec::new();
+        let res = CheckCommand::execute(rw, &mut output);
+        // error severity violations are reported with the exit status
+        assert_eq!(
+            res.unwrap_err().downcast_ref::<ExitStatus>(),
+            Some(&ExitStatus { code: 1 })
+        );
+        let output_string = std::str::from_utf8(&output).unwrap().replace(r"\", "/");
+        let output_vec: Vec<&str> = output_string.lines().collect();
+        assert_eq!(
+            output_vec,
+            vec![
+                "tests/testfiles/check/src/lib.rs:2:8: warning[no-todo]: Resolve TODO notes",
+                "tests/testfiles/check/src/lib.rs:7:5: error[no-dbg]: Remove dbg! macros",
+                "1 error(s), 1 warning(s), 0 note(s) in 1 of 2 file(s)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_warning_violations_only() {
+        let rw = check_subcmd("tests/testfiles/check/warnings.toml", false);
+        let mut output = Vec::new();
+        let res = CheckCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        assert!(output_slice.contains("warning[no-todo]"));
+    }
+
+    #[test]
+    fn test_check_sarif() {
+        let rw = check_subcmd("tests/testfiles/check/recurse.toml", true);
+        let mut output = Vec::new();
+        let res = CheckCommand::execute(rw, &mut output);
+        assert!(res.is_err());
+        let log: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1]["ruleId"], "no-dbg");
+        assert_eq!(results[1]["level"], "error");
+        assert_eq!(results[1]["message"]["text"], "Remove dbg! macros");
+    }
+}
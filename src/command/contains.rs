@@ -3,11 +3,14 @@ use std::io::{ErrorKind, Write};
 use std::path::Path;
 
 use anyhow::{anyhow, Result};
-use regex::Regex;
+use serde_json::json;
 
-use crate::command::Command;
+use crate::command::{resolve_positional_args, Command};
+use crate::ops::backup::{BackupNaming, BackupOptions};
 use crate::ops::io::walk;
 use crate::ops::path::{path_has_extension, path_is_hidden};
+use crate::ops::query::Query;
+use crate::report::json::{path_value, write_record};
 use crate::Recurse;
 
 pub(crate) struct ContainsCommand {}
@@ -17,13 +20,23 @@ impl Command for ContainsCommand {
         if let Recurse::Contains {
             extension,
             hidden,
+            backup_dir,
+            backup_suffix,
             mindepth,
             maxdepth,
             symlinks,
+            all_of,
+            any_of,
+            none_of,
+            query,
+            json,
             find,
             inpath,
         } = subcmd
         {
+            let has_pattern_options =
+                !all_of.is_empty() || !any_of.is_empty() || !none_of.is_empty() || query.is_some();
+            let (find, inpath) = resolve_positional_args(find, inpath, has_pattern_options)?;
             // ------------
             // Validations
             // ------------
@@ -35,27 +48,39 @@ impl Command for ContainsCommand {
                 )));
             }
 
+            // the positional pattern is a required match
+            let all_of: Vec<String> = find.into_iter().chain(all_of).collect();
+            let query = Query::new(&all_of, &any_of, &none_of, query.as_deref())?;
+
             let has_extension_filter = extension.is_some();
-            let regex = Regex::new(&find)?;
+            // replace sub-command backups are excluded, the naming scheme
+            // does not change the excluded files
+            let backup =
+                BackupOptions::new(&inpath, backup_dir, backup_suffix, BackupNaming::Simple)?;
             for entry in walk(inpath, &mindepth, &maxdepth, &symlinks).filter_map(|f| f.ok()) {
                 if entry.metadata().unwrap().is_file() {
                     let filepath = entry.path();
-                    if !hidden && path_is_hidden(filepath) {
+                    if backup.excludes(filepath) {
+                        // skip replace sub-command backup files
+                        continue;
+                    } else if !hidden && path_is_hidden(filepath) {
                         // if file is in a hidden path, skip it
                         continue;
                     } else if has_extension_filter {
                         // if user requested extension filter, filter on it
                         if path_has_extension(filepath, extension.as_ref().unwrap()) {
-                            ContainsCommand::print_filepath_regex_match(
-                                &filepath,
-                                &regex,
+                            ContainsCommand::print_filepath_query_match(
+                                filepath,
+                                &query,
+                                &json,
                                 &mut writer,
                             )?;
                         }
                     } else {
-                        ContainsCommand::print_filepath_regex_match(
-                            &filepath,
-                            &regex,
+                        ContainsCommand::print_filepath_query_match(
+                            filepath,
+                            &query,
+                            &json,
                             &mut writer,
                         )?;
                     }
@@ -69,15 +94,20 @@ impl Command for ContainsCommand {
 }
 
 impl ContainsCommand {
-    pub(crate) fn print_filepath_regex_match(
+    pub(crate) fn print_filepath_query_match(
         filepath: &Path,
-        regex: &Regex,
+        query: &Query,
+        json: &bool,
         writer: &mut impl Write,
     ) -> Result<()> {
-        match read_to_string(&filepath) {
+        match read_to_string(filepath) {
             Ok(filestr) => {
-                if regex.is_match(&filestr) {
-                    writeln!(writer, "{}", &filepath.display())?;
+                if query.is_match(&filestr) {
+                    if *json {
+                        write_record(writer, "match", json!({ "path": path_value(filepath) }))?;
+                    } else {
+                        writeln!(writer, "{}", &filepath.display())?;
+                    }
                 }
             }
             Err(error) => match error.kind() {
@@ -102,12 +132,19 @@ mod tests {
     fn test_contains_subcmd_invalid_inpath_validation() {
         let rw = Recurse::Contains {
             extension: None,
-            find: "test".to_string(),
+            find: Some("test".to_string()),
             hidden: false,
-            inpath: PathBuf::from("path/to/bogus"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("path/to/bogus")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: false,
         };
         let mut output = Vec::new();
         let res = ContainsCommand::execute(rw, &mut output);
@@ -125,10 +162,13 @@ mod tests {
             extension: None,
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("path/to/bogus"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = ContainsCommand::execute(rw, &mut output);
@@ -143,12 +183,19 @@ mod tests {
     fn test_contains_invalid_filetype_non_utf8_binary_is_not_logged() {
         let rw = Recurse::Contains {
             extension: None,
-            find: ".*".to_string(),
+            find: Some(".*".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/contains/librecurse.rlib"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/librecurse.rlib")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: false,
         };
         let mut output = Vec::new();
         let res = ContainsCommand::execute(rw, &mut output);
@@ -156,19 +203,26 @@ mod tests {
         let output_slice = std::str::from_utf8(&output).unwrap();
         let output_vec: Vec<&str> = output_slice.split("\n").collect();
         assert!(output_vec.len() == 1);
-        assert!(output_vec[0] == "");
+        assert!(output_vec[0].is_empty());
     }
 
     #[test]
     fn test_contains_default_match() {
         let rw = Recurse::Contains {
             extension: None,
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/contains/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/dir1")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: false,
         };
         let mut output = Vec::new();
         let res = ContainsCommand::execute(rw, &mut output);
@@ -180,19 +234,26 @@ mod tests {
         assert!(output_string.contains("tests_testfiles_contains_dir1_test1.md"));
         assert!(output_string.contains("tests_testfiles_contains_dir1_dir2_test2.txt"));
         assert!(output_vec.len() == 3);
-        assert!(output_vec[2] == "");
+        assert!(output_vec[2].is_empty());
     }
 
     #[test]
     fn test_contains_hidden_match() {
         let rw = Recurse::Contains {
             extension: None,
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: true,
-            inpath: PathBuf::from("tests/testfiles/contains/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/dir1")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: false,
         };
         let mut output = Vec::new();
         let res = ContainsCommand::execute(rw, &mut output);
@@ -205,19 +266,26 @@ mod tests {
         assert!(output_string.contains("tests_testfiles_contains_dir1_.test-hidden.txt"));
         assert!(output_string.contains("tests_testfiles_contains_dir1_dir2_test2.txt"));
         assert!(output_vec.len() == 4);
-        assert!(output_vec[3] == "");
+        assert!(output_vec[3].is_empty());
     }
 
     #[test]
     fn test_contains_filter_match() {
         let rw = Recurse::Contains {
             extension: Some("txt".to_string()),
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/contains/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/dir1")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: false,
         };
         let mut output = Vec::new();
         let res = ContainsCommand::execute(rw, &mut output);
@@ -228,19 +296,26 @@ mod tests {
         output_string = output_string.replace(r"\", "_");
         assert!(output_string.contains("tests_testfiles_contains_dir1_dir2_test2.txt"));
         assert!(output_vec.len() == 2);
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
     fn test_contains_filter_hidden_match() {
         let rw = Recurse::Contains {
             extension: Some("txt".to_string()),
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: true,
-            inpath: PathBuf::from("tests/testfiles/contains/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/dir1")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: false,
         };
         let mut output = Vec::new();
         let res = ContainsCommand::execute(rw, &mut output);
@@ -252,19 +327,26 @@ mod tests {
         assert!(output_string.contains("tests_testfiles_contains_dir1_dir2_test2.txt"));
         assert!(output_string.contains("tests_testfiles_contains_dir1_.test-hidden.txt"));
         assert!(output_vec.len() == 3);
-        assert!(output_vec[2] == "");
+        assert!(output_vec[2].is_empty());
     }
 
     #[test]
     fn test_contains_maxdepth_match() {
         let rw = Recurse::Contains {
             extension: None,
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/contains/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/dir1")),
             mindepth: None,
             maxdepth: Some(1),
             symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: false,
         };
         let mut output = Vec::new();
         let res = ContainsCommand::execute(rw, &mut output);
@@ -275,19 +357,26 @@ mod tests {
         output_string = output_string.replace(r"\", "_");
         assert!(output_string.contains("tests_testfiles_contains_dir1_test1.md"));
         assert!(output_vec.len() == 2);
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
     fn test_contains_mindepth_match() {
         let rw = Recurse::Contains {
             extension: None,
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/contains/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/dir1")),
             mindepth: Some(2),
             maxdepth: None,
             symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: false,
         };
         let mut output = Vec::new();
         let res = ContainsCommand::execute(rw, &mut output);
@@ -298,19 +387,26 @@ mod tests {
         output_string = output_string.replace(r"\", "_");
         assert!(output_string.contains("tests_testfiles_contains_dir1_dir2_test2.txt"));
         assert!(output_vec.len() == 2);
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
     fn test_contains_unicode_devanagari() {
         let rw = Recurse::Contains {
             extension: None,
-            find: r"ऄ".to_string(),
+            find: Some(r"ऄ".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/contains/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/dir1")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: false,
         };
         let mut output = Vec::new();
         let res = ContainsCommand::execute(rw, &mut output);
@@ -321,6 +417,127 @@ mod tests {
         output_string = output_string.replace(r"\", "_");
         assert!(output_string.contains("tests_testfiles_contains_dir1_test1.txt"));
         assert!(output_vec.len() == 2);
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
+    }
+
+    #[test]
+    fn test_contains_all_of_none_of_query() {
+        let rw = Recurse::Contains {
+            extension: None,
+            find: Some("tests/testfiles/contains/dir1".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: None,
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            all_of: vec!["test".to_string(), "^10".to_string()],
+            any_of: vec![],
+            none_of: vec!["1010".to_string()],
+            query: None,
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = ContainsCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let output_vec: Vec<&str> = output_slice.split("\n").collect();
+        let mut output_string = output_slice.replace("/", "_");
+        output_string = output_string.replace(r"\", "_");
+        assert!(output_string.contains("tests_testfiles_contains_dir1_test1.txt"));
+        assert!(output_string.contains("tests_testfiles_contains_dir1_dir2_test2.md"));
+        assert!(output_vec.len() == 3);
+        assert!(output_vec[2].is_empty());
+    }
+
+    #[test]
+    fn test_contains_query_expression() {
+        let rw = Recurse::Contains {
+            extension: None,
+            find: Some("test".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/dir1")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: Some("!'^10' | ऄ".to_string()),
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = ContainsCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let output_vec: Vec<&str> = output_slice.split("\n").collect();
+        let mut output_string = output_slice.replace("/", "_");
+        output_string = output_string.replace(r"\", "_");
+        assert!(output_string.contains("tests_testfiles_contains_dir1_test-nomatch.txt"));
+        assert!(output_string.contains("tests_testfiles_contains_dir1_test1.txt"));
+        assert!(output_vec.len() == 3);
+        assert!(output_vec[2].is_empty());
+    }
+
+    #[test]
+    fn test_contains_invalid_query_expression() {
+        let rw = Recurse::Contains {
+            extension: None,
+            find: None,
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/dir1")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: Some("A & (B".to_string()),
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = ContainsCommand::execute(rw, &mut output);
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("invalid query expression"));
+    }
+
+    #[test]
+    fn test_contains_json() {
+        let rw = Recurse::Contains {
+            extension: Some("txt".to_string()),
+            find: Some("1010".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/contains/dir1/dir2")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: true,
+        };
+        let mut output = Vec::new();
+        let res = ContainsCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let records: Vec<serde_json::Value> = output_slice
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["type"], "match");
+        let path = records[0]["data"]["path"]["text"].as_str().unwrap();
+        assert!(path.replace(r"\", "/").ends_with("dir2/test2.txt"));
     }
 }
@@ -3,12 +3,23 @@ use std::io::{ErrorKind, Write};
 use std::path::Path;
 
 use anyhow::{anyhow, Result};
-use colored::*;
-use regex::Regex;
 
-use crate::command::Command;
+use crate::command::{resolve_positional_args, Command};
+use crate::ops::backup::{BackupNaming, BackupOptions};
+use crate::ops::color::ColorStyles;
 use crate::ops::io::walk;
 use crate::ops::path::{path_has_extension, path_is_hidden};
+use crate::ops::pattern::{read_patterns_file, GroupRef, PatternMatch, PatternSet};
+use crate::ops::scope::LineScope;
+use crate::ops::template::Template;
+use crate::report::checkstyle::CheckstyleReporter;
+use crate::report::editor::{EditorFormat, EditorReporter};
+use crate::report::json::JsonReporter;
+use crate::report::junit::JunitReporter;
+use crate::report::sarif::SarifReporter;
+use crate::report::standard::StandardReporter;
+use crate::report::template::TemplateReporter;
+use crate::report::{pattern_rules, ContextRecord, FileStats, MatchRecord, Reporter, Summary};
 use crate::Recurse;
 
 pub(crate) struct FindCommand {}
@@ -18,13 +29,35 @@ impl Command for FindCommand {
         if let Recurse::Find {
             extension,
             hidden,
+            backup_dir,
+            backup_suffix,
             mindepth,
             maxdepth,
             symlinks,
+            patterns,
+            patterns_file,
+            fixed_strings,
+            only_group,
+            format,
+            color,
+            colors,
+            vimgrep,
+            emacs,
+            vscode,
+            sarif,
+            junit,
+            checkstyle,
+            context,
+            in_lines,
+            line_range,
+            between,
+            json,
             find,
             inpath,
         } = subcmd
         {
+            let has_pattern_options = !patterns.is_empty() || patterns_file.is_some();
+            let (find, inpath) = resolve_positional_args(find, inpath, has_pattern_options)?;
             // ------------
             // Validations
             // ------------
@@ -36,24 +69,103 @@ impl Command for FindCommand {
                 )));
             }
 
+            // positional pattern first, then option patterns, then file patterns
+            let mut pattern_list: Vec<String> = find.into_iter().chain(patterns).collect();
+            if let Some(patterns_path) = patterns_file {
+                pattern_list.extend(read_patterns_file(patterns_path)?);
+            }
+            let pattern_set = PatternSet::new(pattern_list, fixed_strings)?;
+            let scope = LineScope::new(in_lines.as_deref(), line_range, &between)?;
+
+            // 2) capture groups in the --only-group and --format options are
+            // defined in at least one of the patterns
+            let template = match format {
+                Some(format) => Some(Template::parse(&format)?),
+                None => None,
+            };
+            for group in only_group
+                .iter()
+                .chain(template.iter().flat_map(|t| t.groups()))
+            {
+                if !pattern_set.has_group(group) {
+                    return Err(anyhow!(
+                        "capture group '{}' is not defined in the find patterns",
+                        group
+                    ));
+                }
+            }
+
+            let editor_format = if vimgrep {
+                Some(EditorFormat::Vimgrep)
+            } else if emacs {
+                Some(EditorFormat::Emacs)
+            } else if vscode {
+                Some(EditorFormat::Vscode)
+            } else {
+                None
+            };
+            let mut reporter: Box<dyn Reporter> = if json {
+                Box::new(JsonReporter::new(&mut writer))
+            } else if sarif {
+                Box::new(SarifReporter::new(&mut writer, pattern_rules(&pattern_set)))
+            } else if junit {
+                Box::new(JunitReporter::new(&mut writer, pattern_rules(&pattern_set)))
+            } else if checkstyle {
+                Box::new(CheckstyleReporter::new(
+                    &mut writer,
+                    pattern_rules(&pattern_set),
+                ))
+            } else if let Some(editor_format) = editor_format {
+                Box::new(EditorReporter::new(&mut writer, editor_format))
+            } else if let Some(template) = template {
+                Box::new(TemplateReporter::new(&mut writer, template))
+            } else {
+                let styles = ColorStyles::new(color.use_color(), &colors)?;
+                Box::new(StandardReporter::new(&mut writer, styles))
+            };
+            let mut summary = Summary::default();
+
             let has_extension_filter = extension.is_some();
-            let re = Regex::new(&find)?;
+            // replace sub-command backups are excluded, the naming scheme
+            // does not change the excluded files
+            let backup =
+                BackupOptions::new(&inpath, backup_dir, backup_suffix, BackupNaming::Simple)?;
             for entry in walk(inpath, &mindepth, &maxdepth, &symlinks).filter_map(|f| f.ok()) {
                 if entry.metadata().unwrap().is_file() {
                     let filepath = entry.path();
-                    if !hidden && path_is_hidden(filepath) {
+                    if backup.excludes(filepath) {
+                        // skip replace sub-command backup files
+                        continue;
+                    } else if !hidden && path_is_hidden(filepath) {
                         // if file is in a hidden path, skip it
                         continue;
                     } else if has_extension_filter {
                         // if user requested extension filter, filter on it
                         if path_has_extension(filepath, extension.as_ref().unwrap()) {
-                            FindCommand::print_filepath_regex_matches(&filepath, &re, &mut writer)?;
+                            FindCommand::print_filepath_regex_matches(
+                                filepath,
+                                &pattern_set,
+                                &only_group,
+                                &scope,
+                                context,
+                                reporter.as_mut(),
+                                &mut summary,
+                            )?;
                         }
                     } else {
-                        FindCommand::print_filepath_regex_matches(&filepath, &re, &mut writer)?;
+                        FindCommand::print_filepath_regex_matches(
+                            filepath,
+                            &pattern_set,
+                            &only_group,
+                            &scope,
+                            context,
+                            reporter.as_mut(),
+                            &mut summary,
+                        )?;
                     }
                 }
             }
+            reporter.finish(&summary)?;
             Ok(())
         } else {
             Err(anyhow!("failure to parse find subcommand."))
@@ -64,31 +176,95 @@ impl Command for FindCommand {
 impl FindCommand {
     pub(crate) fn print_filepath_regex_matches(
         filepath: &Path,
-        re: &Regex,
-        writer: &mut impl Write,
+        pattern_set: &PatternSet,
+        only_group: &Option<GroupRef>,
+        scope: &LineScope,
+        context: usize,
+        reporter: &mut dyn Reporter,
+        summary: &mut Summary,
     ) -> Result<()> {
-        match read_to_string(&filepath) {
+        match read_to_string(filepath) {
             Ok(filestr) => {
+                let mut stats = FileStats::default();
                 // short circuit the individual line checks if overall match does not
                 // indicate the presence of a match
-                if re.is_match(&filestr) {
-                    // iterate through lines and print matches
-                    let mut line_number = 0;
-                    for line in filestr.lines() {
-                        line_number += 1;
-                        for mat in re.find_iter(line) {
-                            writeln!(
-                                writer,
-                                "{} {} {} {} {}",
-                                &filepath.display(),
-                                format!("{}:{}-{}", &line_number, &mat.start(), &mat.end()).green(),
-                                "[".dimmed().bold(),
-                                &mat.as_str().red(),
-                                "]".dimmed().bold(),
-                            )?;
+                if pattern_set.is_match(&filestr) {
+                    let lines: Vec<&str> = filestr.lines().collect();
+                    let included = scope.included_lines(&lines);
+                    // the reported offsets are the capture group offsets when a
+                    // group is requested.  Matches where the group did not
+                    // participate are not reported.  Matches are not reported
+                    // in lines that are out of scope.
+                    let line_matches: Vec<Vec<(PatternMatch, usize, usize)>> = lines
+                        .iter()
+                        .zip(included)
+                        .map(|(line, included)| {
+                            if !included {
+                                return Vec::new();
+                            }
+                            pattern_set
+                                .find_all(line)
+                                .into_iter()
+                                .filter_map(|mat| {
+                                    let (start, end) = match only_group {
+                                        Some(group) => {
+                                            pattern_set.group_offsets(line, &mat, group)?
+                                        }
+                                        None => (mat.start, mat.end),
+                                    };
+                                    Some((mat, start, end))
+                                })
+                                .collect()
+                        })
+                        .collect();
+                    if line_matches.iter().all(|matches| matches.is_empty()) {
+                        summary.add(&stats);
+                        return Ok(());
+                    }
+
+                    // lines that are reported as context of a match
+                    let mut is_context = vec![false; lines.len()];
+                    for (line_index, matches) in line_matches.iter().enumerate() {
+                        if !matches.is_empty() && context > 0 {
+                            let first = line_index.saturating_sub(context);
+                            let last = (line_index + context).min(lines.len() - 1);
+                            for flag in &mut is_context[first..=last] {
+                                *flag = true;
+                            }
+                        }
+                    }
+
+                    // iterate through lines and report matches
+                    reporter.begin(filepath)?;
+                    for (line_index, line) in lines.iter().enumerate() {
+                        let matches = &line_matches[line_index];
+                        if matches.is_empty() {
+                            if is_context[line_index] {
+                                reporter.context(&ContextRecord {
+                                    path: filepath,
+                                    line_number: line_index + 1,
+                                    line,
+                                })?;
+                            }
+                            continue;
+                        }
+                        stats.matched_lines += 1;
+                        for (mat, start, end) in matches {
+                            stats.matches += 1;
+                            reporter.matched(&MatchRecord {
+                                path: filepath,
+                                line_number: line_index + 1,
+                                line,
+                                start: *start,
+                                end: *end,
+                                pattern_set,
+                                pattern_match: mat,
+                            })?;
                         }
                     }
+                    reporter.end(filepath, &stats)?;
                 }
+                summary.add(&stats);
             }
             Err(error) => match error.kind() {
                 // If this was due to invalid UTF-8 conversion
@@ -106,6 +282,7 @@ impl FindCommand {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ops::color::ColorChoice;
     use std::env;
     use std::path::PathBuf;
 
@@ -113,12 +290,32 @@ mod tests {
     fn test_find_subcmd_invalid_inpath_validation() {
         let rw = Recurse::Find {
             extension: None,
-            find: "test".to_string(),
+            find: Some("test".to_string()),
             hidden: false,
-            inpath: PathBuf::from("path/to/bogus"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("path/to/bogus")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
         };
         let mut output = Vec::new();
         let res = FindCommand::execute(rw, &mut output);
@@ -136,10 +333,13 @@ mod tests {
             extension: None,
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("path/to/bogus"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = FindCommand::execute(rw, &mut output);
@@ -154,12 +354,32 @@ mod tests {
     fn test_find_invalid_filetype_non_utf8_binary_is_not_logged() {
         let rw = Recurse::Find {
             extension: None,
-            find: ".*".to_string(),
+            find: Some(".*".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/find/librecurse.rlib"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/librecurse.rlib")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
         };
         let mut output = Vec::new();
         let res = FindCommand::execute(rw, &mut output);
@@ -167,7 +387,7 @@ mod tests {
         let output_slice = std::str::from_utf8(&output).unwrap();
         let output_vec: Vec<&str> = output_slice.split("\n").collect();
         assert!(output_vec.len() == 1);
-        assert!(output_vec[0] == "");
+        assert!(output_vec[0].is_empty());
     }
 
     #[test]
@@ -177,12 +397,32 @@ mod tests {
 
         let rw = Recurse::Find {
             extension: None,
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/find/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
         };
         let mut output = Vec::new();
         let res = FindCommand::execute(rw, &mut output);
@@ -194,7 +434,7 @@ mod tests {
         assert!(output_string.contains("tests_testfiles_find_dir1_test1.md 2:0-4 [ 1010 ]"));
         assert!(output_string.contains("tests_testfiles_find_dir1_dir2_test2.txt 2:0-4 [ 1010 ]"));
         assert!(output_vec.len() == 3);
-        assert!(output_vec[2] == "");
+        assert!(output_vec[2].is_empty());
     }
 
     #[test]
@@ -204,12 +444,32 @@ mod tests {
 
         let rw = Recurse::Find {
             extension: None,
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: true,
-            inpath: PathBuf::from("tests/testfiles/find/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
         };
         let mut output = Vec::new();
         let res = FindCommand::execute(rw, &mut output);
@@ -222,7 +482,7 @@ mod tests {
         assert!(output_string.contains("tests_testfiles_find_dir1_.test-hidden.txt 2:0-4 [ 1010 ]"));
         assert!(output_string.contains("tests_testfiles_find_dir1_dir2_test2.txt 2:0-4 [ 1010 ]"));
         assert!(output_vec.len() == 4);
-        assert!(output_vec[3] == "");
+        assert!(output_vec[3].is_empty());
     }
 
     #[test]
@@ -232,12 +492,32 @@ mod tests {
 
         let rw = Recurse::Find {
             extension: Some("txt".to_string()),
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/find/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
         };
         let mut output = Vec::new();
         let res = FindCommand::execute(rw, &mut output);
@@ -248,7 +528,7 @@ mod tests {
         output_string = output_string.replace(r"\", "_");
         assert!(output_string.contains("tests_testfiles_find_dir1_dir2_test2.txt 2:0-4 [ 1010 ]"));
         assert!(output_vec.len() == 2);
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
@@ -258,12 +538,32 @@ mod tests {
 
         let rw = Recurse::Find {
             extension: Some("txt".to_string()),
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: true,
-            inpath: PathBuf::from("tests/testfiles/find/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
         };
         let mut output = Vec::new();
         let res = FindCommand::execute(rw, &mut output);
@@ -275,7 +575,7 @@ mod tests {
         assert!(output_string.contains("tests_testfiles_find_dir1_dir2_test2.txt 2:0-4 [ 1010 ]"));
         assert!(output_string.contains("tests_testfiles_find_dir1_.test-hidden.txt 2:0-4 [ 1010 ]"));
         assert!(output_vec.len() == 3);
-        assert!(output_vec[2] == "");
+        assert!(output_vec[2].is_empty());
     }
 
     #[test]
@@ -285,12 +585,32 @@ mod tests {
 
         let rw = Recurse::Find {
             extension: None,
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/find/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
             mindepth: None,
             maxdepth: Some(1),
             symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
         };
         let mut output = Vec::new();
         let res = FindCommand::execute(rw, &mut output);
@@ -301,7 +621,7 @@ mod tests {
         output_string = output_string.replace(r"\", "_");
         assert!(output_string.contains("tests_testfiles_find_dir1_test1.md 2:0-4 [ 1010 ]"));
         assert!(output_vec.len() == 2);
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
@@ -311,12 +631,32 @@ mod tests {
 
         let rw = Recurse::Find {
             extension: None,
-            find: r"\d\d\d\d".to_string(),
+            find: Some(r"\d\d\d\d".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/find/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
             mindepth: Some(2),
             maxdepth: None,
             symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
         };
         let mut output = Vec::new();
         let res = FindCommand::execute(rw, &mut output);
@@ -327,19 +667,39 @@ mod tests {
         output_string = output_string.replace(r"\", "_");
         assert!(output_string.contains("tests_testfiles_find_dir1_dir2_test2.txt 2:0-4 [ 1010 ]"));
         assert!(output_vec.len() == 2);
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
     fn test_find_unicode_devanagari() {
         let rw = Recurse::Find {
             extension: None,
-            find: r"ऄ".to_string(),
+            find: Some(r"ऄ".to_string()),
             hidden: false,
-            inpath: PathBuf::from("tests/testfiles/find/dir1"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
         };
         let mut output = Vec::new();
         let res = FindCommand::execute(rw, &mut output);
@@ -350,6 +710,582 @@ mod tests {
         output_string = output_string.replace(r"\", "_");
         assert!(output_string.contains("tests_testfiles_find_dir1_test1.txt 4:0-3 [ ऄ ]"));
         assert!(output_vec.len() == 2);
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
+    }
+
+    #[test]
+    fn test_find_multiple_patterns_tagged_match() {
+        // setup
+        env::set_var("NO_COLOR", "1");
+
+        let rw = Recurse::Find {
+            extension: Some("md".to_string()),
+            find: None,
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec!["1010".to_string(), "test".to_string()],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let output_vec: Vec<&str> = output_slice.split("\n").collect();
+        let mut output_string = output_slice.replace("/", "_");
+        output_string = output_string.replace(r"\", "_");
+        assert!(
+            output_string.contains("tests_testfiles_find_dir1_test1.md 1:10-14 [ test ] (test)")
+        );
+        assert!(output_string.contains("tests_testfiles_find_dir1_test1.md 2:0-4 [ 1010 ] (1010)"));
+        assert!(
+            output_string.contains("tests_testfiles_find_dir1_test1.md 3:10-14 [ test ] (test)")
+        );
+        // 1 test2.md match on each of two lines + 3 test1.md matches
+        assert!(output_vec.len() == 6);
+        assert!(output_vec[5].is_empty());
+    }
+
+    #[test]
+    fn test_find_positional_pattern_with_option_patterns() {
+        // setup
+        env::set_var("NO_COLOR", "1");
+
+        let rw = Recurse::Find {
+            extension: None,
+            find: Some("1010".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
+            mindepth: Some(2),
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec!["10$".to_string()],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let mut output_string = output_slice.replace("/", "_");
+        output_string = output_string.replace(r"\", "_");
+        assert!(output_string
+            .contains("tests_testfiles_find_dir1_dir2_test2.txt 2:0-4 [ 1010 ] (1010)"));
+        assert!(
+            output_string.contains("tests_testfiles_find_dir1_dir2_test2.txt 2:2-4 [ 10 ] (10$)")
+        );
+        assert!(
+            output_string.contains("tests_testfiles_find_dir1_dir2_test2.md 2:0-2 [ 10 ] (10$)")
+        );
+    }
+
+    #[test]
+    fn test_find_patterns_file_single_positional_is_inpath() {
+        // setup
+        env::set_var("NO_COLOR", "1");
+
+        let rw = Recurse::Find {
+            extension: Some("txt".to_string()),
+            find: Some("tests/testfiles/find/dir1".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: None,
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: Some(PathBuf::from("tests/testfiles/find/patterns.txt")),
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let output_vec: Vec<&str> = output_slice.split("\n").collect();
+        let mut output_string = output_slice.replace("/", "_");
+        output_string = output_string.replace(r"\", "_");
+        assert!(output_string
+            .contains("tests_testfiles_find_dir1_dir2_test2.txt 2:0-4 [ 1010 ] (1010)"));
+        assert!(output_string
+            .contains(r"tests_testfiles_find_dir1_dir2_test2.txt 2:0-4 [ 1010 ] (_d_d_d_d)"));
+        assert!(output_vec.len() == 3);
+        assert!(output_vec[2].is_empty());
+    }
+
+    #[test]
+    fn test_find_missing_inpath() {
+        let rw = Recurse::Find {
+            extension: None,
+            find: Some("test".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: None,
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("missing traversal start path"));
+    }
+
+    #[test]
+    fn test_find_only_group_match() {
+        // setup
+        env::set_var("NO_COLOR", "1");
+
+        let rw = Recurse::Find {
+            extension: Some("txt".to_string()),
+            find: Some(r"10(?P<tail>\d+)".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: Some(GroupRef::Name("tail".to_string())),
+            format: None,
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let output_vec: Vec<&str> = output_slice.split("\n").collect();
+        let mut output_string = output_slice.replace("/", "_");
+        output_string = output_string.replace(r"\", "_");
+        assert!(output_string.contains("tests_testfiles_find_dir1_dir2_test2.txt 2:2-4 [ 10 ]"));
+        assert!(output_vec.len() == 2);
+        assert!(output_vec[1].is_empty());
+    }
+
+    #[test]
+    fn test_find_format_template() {
+        let rw = Recurse::Find {
+            extension: None,
+            find: Some(r"(\d\d)(?P<name>\d\d)|ऄ".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1/test1.txt")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: Some(r"{line}:{col}:{start}-{end}\t{match}|{name}={1}".to_string()),
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        assert_eq!(output_slice, "4:1:0-3\tऄ|=\n");
+
+        let rw = Recurse::Find {
+            extension: None,
+            find: Some(r"(\d\d)(?P<name>\d\d)".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1/dir2/test2.txt")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: Some(r"{path}:{line}:{col}\t{name}={1} {pattern}".to_string()),
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let output_string = output_slice.replace("/", "_").replace(r"\", "_");
+        assert!(output_string.ends_with(":2:1\t10=10 (_d_d)(?P<name>_d_d)\n"));
+    }
+
+    #[test]
+    fn test_find_undefined_capture_group() {
+        let rw = Recurse::Find {
+            extension: None,
+            find: Some(r"(\d\d)".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: Some("{path} {version}".to_string()),
+            color: ColorChoice::Auto,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("capture group 'version' is not defined"));
+    }
+
+    #[test]
+    fn test_find_color_always() {
+        let rw = Recurse::Find {
+            extension: Some("txt".to_string()),
+            find: Some("1010".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Always,
+            colors: vec!["path:blue".to_string(), "separator:none".to_string()],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        assert!(output_slice.starts_with("\x1B[34mtests"));
+        assert!(output_slice
+            .ends_with("test2.txt\x1B[0m \x1B[32m2:0-4\x1B[0m [ \x1B[31m1010\x1B[0m ]\n"));
+    }
+
+    #[test]
+    fn test_find_color_never() {
+        let rw = Recurse::Find {
+            extension: Some("txt".to_string()),
+            find: Some("1010".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Never,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        assert!(!output_slice.contains('\x1B'));
+        assert!(output_slice.ends_with("test2.txt 2:0-4 [ 1010 ]\n"));
+    }
+
+    #[test]
+    fn test_find_json_with_context() {
+        let rw = Recurse::Find {
+            extension: None,
+            find: Some("1010".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1/dir2/test2.txt")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Never,
+            colors: vec![],
+            vimgrep: false,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 1,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: true,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let records: Vec<serde_json::Value> = output_slice
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let types: Vec<&str> = records
+            .iter()
+            .map(|record| record["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            vec!["begin", "context", "match", "context", "end", "summary"]
+        );
+        assert!(records.iter().all(|record| record["version"] == 1));
+        let data = &records[2]["data"];
+        assert_eq!(data["line_number"], 2);
+        assert_eq!(data["text"], "1010");
+        assert_eq!(data["start"], 0);
+        assert_eq!(data["end"], 4);
+        assert_eq!(data["column"], 1);
+        assert_eq!(records[1]["data"]["line_number"], 1);
+        assert_eq!(records[4]["data"]["stats"]["matches"], 1);
+        assert_eq!(records[5]["data"]["files_matched"], 1);
+    }
+
+    #[test]
+    fn test_find_vimgrep() {
+        let rw = Recurse::Find {
+            extension: None,
+            find: Some("ऄ".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1/test1.txt")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Always,
+            colors: vec![],
+            vimgrep: true,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 1,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let output_string = output_slice.replace(r"\", "/");
+        // no color or context lines in the vimgrep format
+        assert_eq!(output_string, "tests/testfiles/find/dir1/test1.txt:4:1:ऄ\n");
+    }
+
+    #[test]
+    fn test_find_line_scope() {
+        let find_subcmd = |in_lines: Option<&str>, line_range: Option<&str>| Recurse::Find {
+            extension: None,
+            find: Some("test".to_string()),
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("tests/testfiles/find/dir1/dir2/test2.txt")),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            patterns: vec![],
+            patterns_file: None,
+            fixed_strings: false,
+            only_group: None,
+            format: None,
+            color: ColorChoice::Never,
+            colors: vec![],
+            vimgrep: true,
+            emacs: false,
+            vscode: false,
+            sarif: false,
+            junit: false,
+            checkstyle: false,
+            context: 0,
+            in_lines: in_lines.map(String::from),
+            line_range: line_range.map(|range| range.parse().unwrap()),
+            between: vec![],
+            json: false,
+        };
+        let mut output = Vec::new();
+        let res = FindCommand::execute(find_subcmd(None, Some("2:")), &mut output);
+        assert!(res.is_ok());
+        let output_string = std::str::from_utf8(&output).unwrap().replace(r"\", "/");
+        assert_eq!(
+            output_string,
+            "tests/testfiles/find/dir1/dir2/test2.txt:3:11:This is a test\n"
+        );
+
+        let mut output = Vec::new();
+        let res = FindCommand::execute(find_subcmd(Some("^1010$"), None), &mut output);
+        assert!(res.is_ok());
+        assert!(output.is_empty());
     }
 }
@@ -0,0 +1,70 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+
+use crate::command::Command;
+use crate::ops::journal::{journal_dirpath, read_runs, RunRecord};
+use crate::Recurse;
+
+pub(crate) struct HistoryCommand {}
+
+impl Command for HistoryCommand {
+    fn execute(subcmd: Recurse, mut writer: impl Write) -> Result<()> {
+        if let Recurse::History { journal_dir } = subcmd {
+            let journal_dir = journal_dirpath(journal_dir)?;
+            for run in read_runs(&journal_dir)? {
+                writeln!(writer, "{}", HistoryCommand::run_summary(&run))?;
+            }
+            Ok(())
+        } else {
+            Err(anyhow!("failure to parse history subcommand."))
+        }
+    }
+}
+
+impl HistoryCommand {
+    /// Returns the single line summary of the `run` run with the run id,
+    /// the number of edited files, the undo state, and the command line.
+    fn run_summary(run: &RunRecord) -> String {
+        let state = match run.restored_count() {
+            0 => String::new(),
+            _ if run.is_undone() => ", undone".to_string(),
+            restored => format!(", {} restored", restored),
+        };
+        format!(
+            "{}  {} file(s){}  {}",
+            run.id(),
+            run.files.len(),
+            state,
+            run.manifest.command_line()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::journal::JournalRun;
+
+    #[test]
+    fn test_history_lists_runs() {
+        let journal_dir = tempfile::tempdir().unwrap();
+        let workdir = tempfile::tempdir().unwrap();
+        let filepath = workdir.path().join("test.txt");
+        std::fs::write(&filepath, "a check\n").unwrap();
+        let run = JournalRun::create(journal_dir.path()).unwrap();
+        let id = run.id().to_string();
+        run.record_file(&filepath, &filepath).unwrap();
+        run.finish(10).unwrap();
+
+        let rw = Recurse::History {
+            journal_dir: Some(journal_dir.path().to_path_buf()),
+        };
+        let mut output = Vec::new();
+        let res = HistoryCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        assert!(output_slice.starts_with(&format!("{}  1 file(s)  recurse", id)));
+        assert_eq!(output_slice.lines().count(), 1);
+    }
+}
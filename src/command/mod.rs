@@ -1,10 +1,15 @@
 use std::io::Write;
+use std::path::PathBuf;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
+pub(crate) mod backups;
+pub(crate) mod check;
 pub(crate) mod contains;
 pub(crate) mod find;
+pub(crate) mod history;
 pub(crate) mod replace;
+pub(crate) mod undo;
 pub(crate) mod walk;
 
 use crate::Recurse;
@@ -12,3 +17,18 @@ use crate::Recurse;
 pub(crate) trait Command {
     fn execute(subcmd: Recurse, writer: impl Write) -> Result<()>;
 }
+
+/// Returns the find pattern and traversal start path positional
+/// arguments.  When patterns are defined with options, a single
+/// positional argument is the traversal start path.
+pub(crate) fn resolve_positional_args(
+    find: Option<String>,
+    inpath: Option<PathBuf>,
+    has_pattern_options: bool,
+) -> Result<(Option<String>, PathBuf)> {
+    match (find, inpath) {
+        (Some(path), None) if has_pattern_options => Ok((None, PathBuf::from(path))),
+        (find, Some(inpath)) => Ok((find, inpath)),
+        (_, None) => Err(anyhow!("missing traversal start path")),
+    }
+}
@@ -1,16 +1,30 @@
-use std::fs::{read_to_string, OpenOptions};
-use std::io::{BufWriter, ErrorKind, Write};
+use std::cell::Cell;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
-use regex::Regex;
+use serde_json::{json, Value};
 
 use crate::command::Command;
-use crate::ops::io::walk;
-use crate::ops::path::{path_has_extension, path_is_hidden};
-use crate::Recurse;
-
-const BACKUP_FILEPATH_EXTENSION: &str = "bu";
+use crate::ops::backup::BackupOptions;
+use crate::ops::color::ColorStyles;
+use crate::ops::diff::unified_diff;
+use crate::ops::external::ExternalCommand;
+use crate::ops::io::{walk, AtomicFile, FileSnapshot};
+use crate::ops::journal::{journal_dirpath, JournalRun};
+use crate::ops::newline::{FinalNewline, LineEndingChoice, TextFormat};
+use crate::ops::path::{path_has_extension, path_is_hidden, path_relative_to};
+use crate::ops::pattern::GroupRef;
+use crate::ops::prompt::{read_answer, Answer};
+use crate::ops::replace_rules::substitutions_from_file;
+use crate::ops::scope::{LineScope, Occurrences};
+use crate::ops::stream::{stream_substitutions, StreamError, StreamOptions, MAX_LINE_LEN};
+use crate::ops::substitution::{apply_all, Substitution, SubstitutionOptions};
+use crate::ops::transform::{CounterScope, TemplateContext};
+use crate::report::json::{path_value, write_record};
+use crate::{ExitStatus, Recurse};
 
 pub(crate) struct ReplaceCommand {}
 
@@ -20,9 +34,41 @@ impl Command for ReplaceCommand {
             extension,
             hidden,
             nobu,
+            backup_dir,
+            backup_suffix,
+            backup_naming,
             mindepth,
             maxdepth,
             symlinks,
+            json,
+            preserve_mtime,
+            verify_hash,
+            no_journal,
+            journal_dir,
+            journal_keep,
+            dry_run,
+            interactive,
+            expect,
+            expect_any,
+            diff_context,
+            color,
+            in_lines,
+            line_range,
+            between,
+            max_per_file,
+            nth,
+            first_file_only,
+            literal_replacement,
+            preserve_case,
+            template,
+            counter_scope,
+            with_cmd,
+            cmd_group,
+            cmd_timeout,
+            line_endings,
+            final_newline,
+            stream_threshold,
+            rules,
             find,
             inpath,
             replace,
@@ -46,40 +92,246 @@ impl Command for ReplaceCommand {
                     inpath.display()
                 ));
             }
+            // 2) the command timeout is positive
+            if cmd_timeout == 0 {
+                return Err(anyhow!("--cmd-timeout requires a value of at least 1"));
+            }
+            // 3) the find patterns are valid and the replacement capture
+            // group references are defined before any file is read.  The
+            // find and replace pairs are applied in order.
+            let substitution_options = SubstitutionOptions {
+                preserve_case,
+                literal: literal_replacement,
+                template,
+            };
+            let (substitutions, rules_filepath) = match (rules, find, replace, with_cmd) {
+                (Some(rules), _, _, _) => (
+                    substitutions_from_file(&rules, substitution_options)?,
+                    rules.canonicalize().ok(),
+                ),
+                (None, Some(find), _, Some(command)) => (
+                    vec![
+                        Substitution::new(&find, String::new(), substitution_options)?
+                            .with_command(ExternalCommand::new(
+                                command,
+                                cmd_group.unwrap_or(GroupRef::Index(0)),
+                                Duration::from_secs(cmd_timeout),
+                            ))?,
+                    ],
+                    None,
+                ),
+                (None, Some(find), Some(replace), None) => (
+                    vec![Substitution::new(&find, replace, substitution_options)?],
+                    None,
+                ),
+                _ => {
+                    return Err(anyhow!(
+                        "find and replace strings, a find string and a command, or a rules file are required"
+                    ))
+                }
+            };
+            // 4) occurrence limits are positive
+            if max_per_file == Some(0) {
+                return Err(anyhow!("--max-per-file requires a value of at least 1"));
+            }
+            if nth == Some(0) {
+                return Err(anyhow!("--nth requires a value of at least 1"));
+            }
+            // 5) the journal keeps at least one run
+            if journal_keep == 0 {
+                return Err(anyhow!("--journal-keep requires a value of at least 1"));
+            }
+            let scope = LineScope::new(in_lines.as_deref(), line_range, &between)?;
+            let occurrences = Occurrences {
+                max: max_per_file,
+                nth,
+            };
+            let backup = BackupOptions::new(&inpath, backup_dir, backup_suffix, backup_naming)?;
+            let journal_dir = match dry_run || no_journal {
+                true => None,
+                false => Some(journal_dirpath(journal_dir)?),
+            };
+            // journal directory files are excluded when the journal
+            // directory is in the traversal
+            let journal_filter = journal_dir
+                .as_ref()
+                .and_then(|journal_dir| journal_dir.canonicalize().ok());
             let has_extension_filter = extension.is_some();
-            let re = Regex::new(&find)?;
-            for entry in walk(inpath, &mindepth, &maxdepth, &symlinks).filter_map(|f| f.ok()) {
+            let mut filepaths = Vec::new();
+            for entry in walk(&inpath, &mindepth, &maxdepth, &symlinks).filter_map(|f| f.ok()) {
                 if entry.metadata().unwrap().is_file() {
                     let filepath = entry.path();
-                    if has_backup_extension(&filepath) {
-                        // If file has the backup extension that is used by
-                        // this application, do not perform string replacement
-                        // in that file.
+                    if backup.excludes(filepath) {
+                        // If file has the backup suffix that is used by
+                        // this application or is in the backup directory,
+                        // do not perform string replacement in that file.
                         continue;
                     } else if !hidden && path_is_hidden(filepath) {
                         // if file is in a hidden path, skip it
                         continue;
-                    } else if has_extension_filter {
+                    } else if has_extension_filter
+                        && !path_has_extension(filepath, extension.as_ref().unwrap())
+                    {
                         // if user requested extension filter, filter on it
-                        if path_has_extension(filepath, extension.as_ref().unwrap()) {
-                            ReplaceCommand::regex_replace(
-                                &filepath,
-                                &re,
-                                &replace,
-                                &nobu,
-                                &mut writer,
-                            )?;
-                        } // otherwise skip
-                    } else {
-                        ReplaceCommand::regex_replace(
-                            &filepath,
-                            &re,
-                            &replace,
-                            &nobu,
-                            &mut writer,
+                        continue;
+                    } else if journal_filter.as_ref().is_some_and(|journal_dir| {
+                        filepath
+                            .canonicalize()
+                            .is_ok_and(|filepath| filepath.starts_with(journal_dir))
+                    }) {
+                        // if file is in the journal directory, skip it
+                        continue;
+                    } else if rules_filepath.is_some()
+                        && filepath.canonicalize().ok() == rules_filepath
+                    {
+                        // if file is the rules file, skip it
+                        continue;
+                    }
+                    // rule globs match paths relative to the start path
+                    let relpath = path_relative_to(filepath, &inpath);
+                    let applicable: Vec<&Substitution> = substitutions
+                        .iter()
+                        .filter(|substitution| substitution.applies_to(relpath))
+                        .collect();
+                    if !applicable.is_empty() {
+                        filepaths.push((filepath.to_path_buf(), applicable));
+                    }
+                }
+            }
+            // replacement count assertions are checked before any file
+            // is written.  The replaced files wait in temporary files for
+            // the check so that the replacements are computed once.
+            let expectation = Expectation {
+                count: expect,
+                any: expect_any,
+            };
+            let checks_expectation = expectation.is_defined() && !dry_run;
+            let mut options = ReplaceOptions {
+                no_backup: nobu,
+                backup,
+                preserve_mtime,
+                verify_hash,
+                json,
+                dry_run,
+                diff_context,
+                styles: ColorStyles::new(color.use_color(), &[])?,
+                scope,
+                occurrences,
+                counter: Cell::new(1),
+                counter_scope,
+                line_endings,
+                final_newline,
+                stream_threshold: stream_threshold.saturating_mul(1024 * 1024),
+                journal: None,
+            };
+            if !checks_expectation {
+                options.journal = journal_dir.as_deref().map(JournalRun::create).transpose()?;
+            }
+            let mut stats = ReplaceStats::default();
+            let mut pending = Vec::new();
+            let stdin = io::stdin();
+            let mut input = stdin.lock();
+            for (filepath, applicable) in &filepaths {
+                if interactive {
+                    // interactive mode is limited to a single find and
+                    // replace pair
+                    let (edit, quit) = ReplaceCommand::interactive_replace(
+                        filepath,
+                        applicable[0],
+                        &options,
+                        &mut input,
+                        &mut writer,
+                    )?;
+                    stats.record(filepath, edit);
+                    if quit {
+                        break;
+                    }
+                } else {
+                    match ReplaceCommand::stage_replace(
+                        filepath,
+                        applicable,
+                        &options,
+                        &mut writer,
+                    )? {
+                        StagedEdit::Pending(edit) if checks_expectation => pending.push(edit),
+                        StagedEdit::Pending(edit) => stats.record(
+                            filepath,
+                            ReplaceCommand::write_replacement(*edit, &options, &mut writer)?,
+                        ),
+                        StagedEdit::Done(edit) => stats.record(filepath, edit),
+                    }
+                }
+                if first_file_only && (stats.files > 0 || !pending.is_empty()) {
+                    break;
+                }
+            }
+            if checks_expectation {
+                expectation.check(pending.iter().map(|edit| edit.replacements).sum())?;
+                options.journal = journal_dir.as_deref().map(JournalRun::create).transpose()?;
+                for edit in pending {
+                    let filepath = edit.filepath.clone();
+                    let edit = ReplaceCommand::write_replacement(*edit, &options, &mut writer)?;
+                    stats.record(&filepath, edit);
+                }
+            }
+            if let Some(journal) = options.journal {
+                journal.finish(journal_keep)?;
+            }
+            if dry_run {
+                writeln!(
+                    writer,
+                    "{} file(s) would change with {} replacement(s)",
+                    stats.files, stats.replacements
+                )?;
+            } else if json {
+                write_record(
+                    &mut writer,
+                    "summary",
+                    json!({
+                        "files": stats.files,
+                        "replacements": stats.replacements,
+                        "conflicts": stats.conflicts.len(),
+                        "failures": stats.failures.len(),
+                    }),
+                )?;
+            } else {
+                writeln!(
+                    writer,
+                    "{} file(s) updated with {} replacement(s)",
+                    stats.files, stats.replacements
+                )?;
+            }
+            if !stats.conflicts.is_empty() || !stats.failures.is_empty() {
+                if !json {
+                    for (filepaths, reason) in [
+                        (&stats.conflicts, "concurrent modification"),
+                        (&stats.failures, "replacement command failures"),
+                    ] {
+                        if filepaths.is_empty() {
+                            continue;
+                        }
+                        writeln!(
+                            writer,
+                            "{} file(s) not updated due to {}:",
+                            filepaths.len(),
+                            reason
                         )?;
+                        for filepath in filepaths {
+                            writeln!(writer, "  {}", filepath.display())?;
+                        }
                     }
                 }
+                writer.flush()?;
+                // files with lost edits fail the replace
+                return Err(ExitStatus { code: 2 }.into());
+            }
+            writer.flush()?;
+            if dry_run {
+                expectation.check(stats.replacements)?;
+            }
+            if stats.replacements == 0 {
+                // grep-like exit status when nothing matched
+                return Err(ExitStatus { code: 1 }.into());
             }
             Ok(())
         } else {
@@ -88,79 +340,578 @@ impl Command for ReplaceCommand {
     }
 }
 
+/// The options that modify the replace sub-command file edits and
+/// reports.
+pub(crate) struct ReplaceOptions {
+    pub(crate) no_backup: bool,
+    pub(crate) backup: BackupOptions,
+    pub(crate) preserve_mtime: bool,
+    pub(crate) verify_hash: bool,
+    pub(crate) json: bool,
+    pub(crate) dry_run: bool,
+    pub(crate) diff_context: usize,
+    pub(crate) styles: ColorStyles,
+    pub(crate) scope: LineScope,
+    pub(crate) occurrences: Occurrences,
+    /// The template `${n}` counter value of the next replacement
+    pub(crate) counter: Cell<usize>,
+    pub(crate) counter_scope: CounterScope,
+    pub(crate) line_endings: LineEndingChoice,
+    pub(crate) final_newline: FinalNewline,
+    /// The minimum size in bytes of files with streaming replacements
+    pub(crate) stream_threshold: u64,
+    pub(crate) journal: Option<JournalRun>,
+}
+
+impl ReplaceOptions {
+    /// Returns the template context of the `filepath` file.  The
+    /// counter restarts in each file with the file counter scope.
+    fn template_context<'a>(&'a self, filepath: &'a Path) -> TemplateContext<'a> {
+        if self.counter_scope == CounterScope::File {
+            self.counter.set(1);
+        }
+        TemplateContext {
+            path: filepath,
+            counter: &self.counter,
+        }
+    }
+
+    /// Returns a boolean that indicates whether the `substitutions` are
+    /// streamed through a file of `len` bytes one line at a time.  Files
+    /// are streamed when they are at least the stream threshold size,
+    /// the matches and line scope are limited to single lines, and the
+    /// final newline state is preserved.  Line scopes that are evaluated
+    /// on the result of a previous substitution are limited to a single
+    /// substitution.
+    fn streams(&self, substitutions: &[&Substitution], len: u64) -> bool {
+        !self.dry_run
+            && len >= self.stream_threshold
+            && self.final_newline == FinalNewline::Preserve
+            && self.scope.is_line_local()
+            && (substitutions.len() == 1 || !self.scope.is_defined())
+            && substitutions
+                .iter()
+                .all(|substitution| substitution.is_line_local())
+    }
+
+    fn stream_options(&self) -> StreamOptions<'_> {
+        StreamOptions {
+            scope: &self.scope,
+            occurrences: &self.occurrences,
+            line_endings: self.line_endings,
+            max_line_len: MAX_LINE_LEN,
+        }
+    }
+}
+
+/// The replacement count assertions of a run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Expectation {
+    /// The exact number of replacements
+    pub(crate) count: Option<usize>,
+    /// At least one replacement
+    pub(crate) any: bool,
+}
+
+impl Expectation {
+    pub(crate) fn is_defined(&self) -> bool {
+        self.count.is_some() || self.any
+    }
+
+    /// Returns an error when the `replacements` count does not meet the
+    /// expectation.
+    pub(crate) fn check(&self, replacements: usize) -> Result<()> {
+        match self.count {
+            Some(count) if count != replacements => Err(anyhow!(
+                "expected {} replacement(s), found {}",
+                count,
+                replacements
+            )),
+            _ if self.any && replacements == 0 => {
+                Err(anyhow!("expected at least one replacement, found 0"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Streams the `filepath` file through the line local `substitutions`
+/// to the `writer` stream in the detected format of the file and
+/// returns the number of replacements.
+fn stream_file(
+    filepath: &Path,
+    writer: impl Write,
+    substitutions: &[&Substitution],
+    options: &ReplaceOptions,
+    context: &TemplateContext,
+) -> Result<usize, StreamError> {
+    let format = TextFormat::detect_reader(File::open(filepath)?)?;
+    stream_substitutions(
+        BufReader::new(File::open(filepath)?),
+        writer,
+        format,
+        substitutions,
+        &options.stream_options(),
+        context,
+    )
+}
+
+/// The result of the replacements in a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FileEdit {
+    /// The file was edited with the number of replacements.  Files
+    /// without matches have zero replacements.
+    Replaced(usize),
+    /// The file was modified after it was read and was not edited.
+    Conflict,
+    /// A replacement command failed and the file was not edited.
+    Failed,
+}
+
+/// The staged replacements in a file.
+pub(crate) enum StagedEdit {
+    /// The replaced file contents wait for a write
+    Pending(Box<PendingEdit>),
+    /// The edit result of a file that is not written
+    Done(FileEdit),
+}
+
+/// The replaced contents of a file in a temporary file that replaces the
+/// file when it is written.
+pub(crate) struct PendingEdit {
+    filepath: PathBuf,
+    /// The state of the file when it was read
+    snapshot: FileSnapshot,
+    tmpfile: AtomicFile,
+    replacements: usize,
+}
+
+impl PendingEdit {
+    /// Returns the pending edit of the `filepath` file with the `replaced`
+    /// file contents.
+    fn new(
+        filepath: &Path,
+        snapshot: FileSnapshot,
+        replaced: &str,
+        replacements: usize,
+    ) -> Result<Self> {
+        let mut tmpfile = AtomicFile::create(filepath)?;
+        tmpfile.file()?.write_all(replaced.as_bytes())?;
+        tmpfile.close();
+        Ok(Self {
+            filepath: filepath.to_path_buf(),
+            snapshot,
+            tmpfile,
+            replacements,
+        })
+    }
+}
+
+/// Replacement statistics for a complete traversal.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ReplaceStats {
+    pub(crate) files: usize,
+    pub(crate) replacements: usize,
+    pub(crate) conflicts: Vec<PathBuf>,
+    pub(crate) failures: Vec<PathBuf>,
+}
+
+impl ReplaceStats {
+    /// Adds the `edit` result of the `filepath` file to the statistics.
+    pub(crate) fn record(&mut self, filepath: &Path, edit: FileEdit) {
+        match edit {
+            FileEdit::Replaced(0) => {}
+            FileEdit::Replaced(replacements) => {
+                self.files += 1;
+                self.replacements += replacements;
+            }
+            FileEdit::Conflict => self.conflicts.push(filepath.to_path_buf()),
+            FileEdit::Failed => self.failures.push(filepath.to_path_buf()),
+        }
+    }
+}
+
 impl ReplaceCommand {
-    pub(crate) fn regex_replace(
+    /// Replaces the matches of the `substitutions` in order in the
+    /// `filepath` file and returns the staged edit.  The replaced file
+    /// contents are written to a temporary file with the result of all
+    /// substitutions.  In dry run mode, a unified diff of the changes is
+    /// written and the file edit is done.  Large files are streamed when
+    /// the replacements are limited to single lines.
+    pub(crate) fn stage_replace(
         filepath: &Path,
-        re: &Regex,
-        replace: &str,
-        no_backup: &bool,
+        substitutions: &[&Substitution],
+        options: &ReplaceOptions,
         writer: &mut impl Write,
-    ) -> Result<()> {
-        match read_to_string(&filepath) {
-            Ok(filestr) => {
+    ) -> Result<StagedEdit> {
+        let len = fs::metadata(filepath).map_or(0, |metadata| metadata.len());
+        if options.streams(substitutions, len) {
+            if let Some(edit) =
+                ReplaceCommand::stream_replace(filepath, substitutions, options, writer)?
+            {
+                return Ok(edit);
+            }
+        }
+        match FileSnapshot::read(filepath, options.verify_hash) {
+            Ok((filestr, snapshot)) => {
+                let format = TextFormat::detect(&filestr);
+                let (replaced, replacements) = match apply_all(
+                    substitutions,
+                    &options.scope,
+                    &options.occurrences,
+                    &options.template_context(filepath),
+                    &format.normalize(&filestr),
+                ) {
+                    Ok(replaced) => replaced,
+                    Err(error) => {
+                        let edit =
+                            ReplaceCommand::write_failure(filepath, &error, options, writer)?;
+                        return Ok(StagedEdit::Done(edit));
+                    }
+                };
                 // bail if no matches so that we don't
                 // write files that are not changed
-                if re.is_match(&filestr) {
-                    let post_replace_string = re.replace_all(&filestr, replace);
-
-                    if *no_backup == false {
-                        // Write backup of original file
-                        // This is the default behavior when user
-                        // does not use an explicit flag on the
-                        // command line
-                        let backup_file = OpenOptions::new()
-                            .write(true)
-                            .create(true)
-                            .truncate(true)
-                            .open(get_backup_filepath(filepath))?;
-                        let mut backup_buffer = BufWriter::new(backup_file);
-                        backup_buffer.write_all(&filestr.as_bytes())?;
-                        backup_buffer.flush()?;
-                    }
+                if replacements == 0 {
+                    return Ok(StagedEdit::Done(FileEdit::Replaced(0)));
+                }
+                let post_replace_string =
+                    format.restore(&replaced, options.line_endings, options.final_newline);
 
-                    // write replacement string inplace
-                    let replace_file = OpenOptions::new()
-                        .write(true)
-                        .create(true)
-                        .truncate(true)
-                        .open(filepath)?;
-                    let mut buffer = BufWriter::new(replace_file);
-
-                    buffer.write_all(post_replace_string.as_bytes())?;
-                    buffer.flush()?;
-                    writeln!(writer, "{} updated", filepath.display())?;
+                if options.dry_run {
+                    write!(
+                        writer,
+                        "{}",
+                        unified_diff(
+                            filepath,
+                            &filestr,
+                            &post_replace_string,
+                            options.diff_context,
+                            &options.styles,
+                        )
+                    )?;
+                    return Ok(StagedEdit::Done(FileEdit::Replaced(replacements)));
                 }
+
+                Ok(StagedEdit::Pending(Box::new(PendingEdit::new(
+                    filepath,
+                    snapshot,
+                    &post_replace_string,
+                    replacements,
+                )?)))
             }
             Err(error) => match error.kind() {
                 // If this was due to invalid UTF-8 conversion
                 // on file read, then skip the file.
                 // The intent is to test files with valid
                 // UTF-8 encodings only in this subcommand
-                ErrorKind::InvalidData => {}
+                ErrorKind::InvalidData => Ok(StagedEdit::Done(FileEdit::Replaced(0))),
+                _ => Err(anyhow!(error)),
+            },
+        }
+    }
+
+    /// Streams the `filepath` file through the line local `substitutions`
+    /// to a temporary file and returns the staged edit.  The file
+    /// contents are not read into memory.  Files with lines that are
+    /// longer than the maximum line length are not streamed and `None`
+    /// is returned.
+    fn stream_replace(
+        filepath: &Path,
+        substitutions: &[&Substitution],
+        options: &ReplaceOptions,
+        writer: &mut impl Write,
+    ) -> Result<Option<StagedEdit>> {
+        let snapshot = FileSnapshot::stat(filepath, options.verify_hash)?;
+        let mut tmpfile = AtomicFile::create(filepath)?;
+        let next = options.counter.get();
+        let result = stream_file(
+            filepath,
+            BufWriter::new(tmpfile.file()?),
+            substitutions,
+            options,
+            &options.template_context(filepath),
+        );
+        // the temporary file is removed when the file is not replaced
+        let edit = match result {
+            Ok(0) => StagedEdit::Done(FileEdit::Replaced(0)),
+            Ok(replacements) => {
+                tmpfile.close();
+                StagedEdit::Pending(Box::new(PendingEdit {
+                    filepath: filepath.to_path_buf(),
+                    snapshot,
+                    tmpfile,
+                    replacements,
+                }))
+            }
+            // files that are not valid UTF-8 are skipped
+            Err(StreamError::Io(error)) if error.kind() == ErrorKind::InvalidData => {
+                StagedEdit::Done(FileEdit::Replaced(0))
+            }
+            Err(StreamError::Io(error)) => return Err(anyhow!(error)),
+            Err(StreamError::LineTooLong) => {
+                options.counter.set(next);
+                return Ok(None);
+            }
+            Err(StreamError::Replacement(error)) => StagedEdit::Done(
+                ReplaceCommand::write_failure(filepath, &error, options, writer)?,
+            ),
+        };
+        Ok(Some(edit))
+    }
+
+    /// Prompts for confirmation of each `substitution` match in the
+    /// `filepath` file and replaces the accepted matches.  Returns the file edit
+    /// result and a boolean that indicates whether the user quit.
+    /// Accepted replacements are written before a quit.
+    pub(crate) fn interactive_replace(
+        filepath: &Path,
+        substitution: &Substitution,
+        options: &ReplaceOptions,
+        input: &mut impl BufRead,
+        writer: &mut impl Write,
+    ) -> Result<(FileEdit, bool)> {
+        let (filestr, snapshot) = match FileSnapshot::read(filepath, options.verify_hash) {
+            Ok(read) => read,
+            Err(error) => match error.kind() {
+                ErrorKind::InvalidData => return Ok((FileEdit::Replaced(0), false)),
                 _ => return Err(anyhow!(error)),
             },
+        };
+        let format = TextFormat::detect(&filestr);
+        let text = format.normalize(&filestr);
+        let mut replaced = String::with_capacity(text.len());
+        let mut last_end = 0;
+        let mut replacements = 0;
+        let mut accept_all = false;
+        let mut quit = false;
+        let ranges = options.scope.ranges(&text);
+        let context = options.template_context(filepath);
+        let mut occurrence = 0;
+        for caps in substitution.re.captures_iter(&text) {
+            let mat = caps.get(0).unwrap();
+            if !ranges.contains(mat.start(), mat.end()) {
+                // matches in lines that are out of scope are not replaced
+                continue;
+            }
+            occurrence += 1;
+            if !options.occurrences.is_selected(occurrence) {
+                continue;
+            }
+            let proposed = match substitution.replacement(&caps, &context) {
+                Ok(proposed) => proposed,
+                Err(error) => {
+                    // accepted replacements are discarded
+                    let edit = ReplaceCommand::write_failure(filepath, &error, options, writer)?;
+                    return Ok((edit, quit));
+                }
+            };
+            let replacement = if accept_all {
+                Some(proposed)
+            } else {
+                write!(
+                    writer,
+                    "{}",
+                    match_preview(filepath, &text, mat.start(), mat.end(), &proposed, options)
+                )?;
+                match read_answer("Replace?", input, writer)? {
+                    Answer::Yes => Some(proposed),
+                    Answer::No => None,
+                    Answer::All => {
+                        accept_all = true;
+                        Some(proposed)
+                    }
+                    Answer::Edit(text) => Some(text),
+                    Answer::Quit => {
+                        quit = true;
+                        break;
+                    }
+                }
+            };
+            replaced.push_str(&text[last_end..mat.start()]);
+            match replacement {
+                Some(text) => {
+                    replaced.push_str(&text);
+                    replacements += 1;
+                    // the counter only advances for accepted replacements
+                    context.counter.set(context.counter.get() + 1);
+                }
+                None => replaced.push_str(mat.as_str()),
+            }
+            last_end = mat.end();
         }
-        Ok(())
+        replaced.push_str(&text[last_end..]);
+        if replacements == 0 {
+            return Ok((FileEdit::Replaced(0), quit));
+        }
+        let replaced = format.restore(&replaced, options.line_endings, options.final_newline);
+        let edit = ReplaceCommand::write_replacement(
+            PendingEdit::new(filepath, snapshot, &replaced, replacements)?,
+            options,
+            writer,
+        )?;
+        Ok((edit, quit))
     }
-}
 
-fn get_backup_filepath(inpath: &Path) -> PathBuf {
-    match inpath.extension() {
-        Some(pre_ext) => {
-            let post_ext = pre_ext.to_string_lossy() + "." + BACKUP_FILEPATH_EXTENSION;
-            return inpath.with_extension(post_ext.to_string());
+    /// Reports the replacement `error` of the `filepath` file that is not
+    /// edited.
+    fn write_failure(
+        filepath: &Path,
+        error: &anyhow::Error,
+        options: &ReplaceOptions,
+        writer: &mut impl Write,
+    ) -> Result<FileEdit> {
+        if options.json {
+            write_record(
+                writer,
+                "failure",
+                json!({ "path": path_value(filepath), "error": error.to_string() }),
+            )?;
+        } else {
+            writeln!(
+                writer,
+                "{} failed: {}, not updated",
+                filepath.display(),
+                error
+            )?;
         }
-        None => {
-            return inpath.with_extension(BACKUP_FILEPATH_EXTENSION);
+        Ok(FileEdit::Failed)
+    }
+
+    /// Writes the replaced file contents of the pending `edit` to the
+    /// file with a backup of the original file and reports the edit.
+    /// The file is not written and a conflict is reported when the file
+    /// changed after it was read.
+    pub(crate) fn write_replacement(
+        edit: PendingEdit,
+        options: &ReplaceOptions,
+        writer: &mut impl Write,
+    ) -> Result<FileEdit> {
+        let PendingEdit {
+            filepath,
+            snapshot,
+            tmpfile,
+            replacements,
+        } = edit;
+        let filepath = filepath.as_path();
+        if snapshot.is_changed(filepath)? {
+            if options.json {
+                write_record(writer, "conflict", json!({ "path": path_value(filepath) }))?;
+            } else {
+                writeln!(
+                    writer,
+                    "{} conflict: file changed after it was read, not updated",
+                    filepath.display()
+                )?;
+            }
+            return Ok(FileEdit::Conflict);
+        }
+        let backup_filepath = if options.no_backup {
+            None
+        } else {
+            // Write backup of original file
+            // This is the default behavior when user
+            // does not use an explicit flag on the
+            // command line
+            let backup_filepath = options.backup.backup_filepath(filepath);
+            if let Some(parent) = backup_filepath.parent() {
+                if !parent.as_os_str().is_empty() {
+                    fs::create_dir_all(parent)?;
+                }
+            }
+            // the original file is copied without a read into memory
+            fs::copy(filepath, &backup_filepath)?;
+            Some(backup_filepath)
+        };
+
+        // atomic replacement of the original file
+        if let Some(journal) = &options.journal {
+            journal.record_file(filepath, tmpfile.path())?;
+        }
+        tmpfile.persist(options.preserve_mtime)?;
+        if options.json {
+            write_record(
+                writer,
+                "edit",
+                json!({
+                    "path": path_value(filepath),
+                    "replacements": replacements,
+                    "backup": match &backup_filepath {
+                        Some(backup_filepath) => path_value(backup_filepath),
+                        None => Value::Null,
+                    },
+                    "run": options.journal.as_ref().map(|journal| journal.id()),
+                }),
+            )?;
+        } else {
+            writeln!(
+                writer,
+                "{} updated with {} replacement(s)",
+                filepath.display(),
+                replacements
+            )?;
         }
+        Ok(FileEdit::Replaced(replacements))
     }
 }
 
-fn has_backup_extension(inpath: &Path) -> bool {
-    match inpath.extension() {
-        Some(ext) => return ext.to_string_lossy() == BACKUP_FILEPATH_EXTENSION,
-        None => return false,
+/// Returns the interactive mode preview of the `start` to `end` byte
+/// offset match in the `filestr` file contents with the `proposed`
+/// replacement.  The lines of the match are shown as removed lines,
+/// the replaced lines are shown as added lines, and the surrounding
+/// lines are shown as context.
+fn match_preview(
+    filepath: &Path,
+    filestr: &str,
+    start: usize,
+    end: usize,
+    proposed: &str,
+    options: &ReplaceOptions,
+) -> String {
+    let styles = &options.styles;
+    let line_start = filestr[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = filestr[end..].find('\n').map_or(filestr.len(), |i| end + i);
+    let line_number = filestr[..line_start].matches('\n').count() + 1;
+    let before: Vec<&str> = filestr[..line_start].lines().collect();
+    let after: Vec<&str> = filestr[line_end..]
+        .strip_prefix('\n')
+        .unwrap_or("")
+        .lines()
+        .collect();
+    let mut preview = format!(
+        "{} {}\n",
+        styles.path(&filepath.display().to_string()),
+        styles.line(&format!(
+            "{}:{}-{}",
+            line_number,
+            start - line_start,
+            end - line_start
+        ))
+    );
+    for line in &before[before.len().saturating_sub(options.diff_context)..] {
+        preview.push_str(&format!(" {}\n", line));
     }
+    let original = format!(
+        "{}{}{}",
+        &filestr[line_start..start],
+        styles.matched(&filestr[start..end]),
+        &filestr[end..line_end]
+    );
+    let replaced = format!(
+        "{}{}{}",
+        &filestr[line_start..start],
+        proposed,
+        &filestr[end..line_end]
+    );
+    for line in original.lines() {
+        preview.push_str(&format!("{}\n", styles.removed(&format!("-{}", line))));
+    }
+    for line in replaced.lines() {
+        preview.push_str(&format!("{}\n", styles.added(&format!("+{}", line))));
+    }
+    for line in after.iter().take(options.diff_context) {
+        preview.push_str(&format!(" {}\n", line));
+    }
+    preview
 }
 
 fn is_root_filepath(inpath: &Path) -> bool {
@@ -172,19 +923,54 @@ fn is_root_filepath(inpath: &Path) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ops::backup::BackupNaming;
+    use crate::ops::color::ColorChoice;
+    use std::fs::read_to_string;
 
     #[test]
     fn test_replace_subcmd_invalid_inpath_validation() {
         let rw = Recurse::Replace {
-            find: "test".to_string(),
-            replace: "test".to_string(),
+            rules: None,
+            preserve_case: false,
+            template: false,
+            counter_scope: CounterScope::Run,
+            with_cmd: None,
+            cmd_group: None,
+            cmd_timeout: 10,
+            line_endings: LineEndingChoice::Preserve,
+            final_newline: FinalNewline::Preserve,
+            stream_threshold: 16,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            max_per_file: None,
+            nth: None,
+            first_file_only: false,
+            literal_replacement: false,
+            find: Some("test".to_string()),
+            replace: Some("test".to_string()),
             nobu: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            backup_naming: BackupNaming::Simple,
+            preserve_mtime: false,
+            verify_hash: false,
+            no_journal: true,
+            journal_dir: None,
+            journal_keep: 20,
             extension: None,
             hidden: false,
             inpath: PathBuf::from("path/to/bogus"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
+            dry_run: false,
+            interactive: false,
+            expect: None,
+            expect_any: false,
+            diff_context: 3,
+            color: ColorChoice::Never,
         };
         let mut output = Vec::new();
         let res = ReplaceCommand::execute(rw, &mut output);
@@ -197,73 +983,888 @@ mod tests {
     }
 
     // ======================================
-    // get_secondary_filepath function tests
+    // is_root_filepath function tests
     // ======================================
     #[test]
-    fn test_get_secondary_filepath_with_txt_extension() {
-        let testpath = PathBuf::from("test/path/bogus.txt");
+    fn test_is_root_filepath_with_unix_root() {
+        let testpath = PathBuf::from("/");
+        assert!(is_root_filepath(&testpath));
+    }
+
+    #[test]
+    fn test_is_root_filepath_with_win_root() {
+        let testpath = PathBuf::from(r"\");
+        assert!(is_root_filepath(&testpath));
+    }
+
+    #[test]
+    fn test_is_root_filepath_without_root_fp() {
+        let testpath = PathBuf::from("test/path/bogus");
+        assert!(!is_root_filepath(&testpath));
+    }
+
+    #[test]
+    fn test_replace_json() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        std::fs::write(tmpdir.path().join("test.txt"), "a test\nanother test\n").unwrap();
+        let journal_dir = tempfile::tempdir().unwrap();
+        let rw = Recurse::Replace {
+            rules: None,
+            preserve_case: false,
+            template: false,
+            counter_scope: CounterScope::Run,
+            with_cmd: None,
+            cmd_group: None,
+            cmd_timeout: 10,
+            line_endings: LineEndingChoice::Preserve,
+            final_newline: FinalNewline::Preserve,
+            stream_threshold: 16,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            max_per_file: None,
+            nth: None,
+            first_file_only: false,
+            literal_replacement: false,
+            find: Some("test".to_string()),
+            replace: Some("check".to_string()),
+            nobu: true,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            backup_naming: BackupNaming::Simple,
+            preserve_mtime: false,
+            verify_hash: false,
+            no_journal: false,
+            journal_dir: Some(journal_dir.path().to_path_buf()),
+            journal_keep: 20,
+            extension: None,
+            hidden: false,
+            inpath: tmpdir.path().to_path_buf(),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            json: true,
+            dry_run: false,
+            interactive: false,
+            expect: None,
+            expect_any: false,
+            diff_context: 3,
+            color: ColorChoice::Never,
+        };
+        let mut output = Vec::new();
+        let res = ReplaceCommand::execute(rw, &mut output);
+        let contents = read_to_string(tmpdir.path().join("test.txt")).unwrap();
+        assert!(res.is_ok());
+        assert_eq!(contents, "a check\nanother check\n");
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let records: Vec<serde_json::Value> = output_slice
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        let record = &records[0];
+        assert_eq!(record["type"], "edit");
+        assert_eq!(record["data"]["replacements"], 2);
+        assert!(record["data"]["backup"].is_null());
+        // the run is recorded in the journal
+        let runs = crate::ops::journal::read_runs(journal_dir.path()).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(record["data"]["run"], runs[0].id());
+        assert_eq!(runs[0].files.len(), 1);
+        assert_eq!(records[1]["type"], "summary");
+        assert_eq!(records[1]["data"]["files"], 1);
+        assert_eq!(records[1]["data"]["replacements"], 2);
+    }
+
+    fn expect_subcmd(inpath: PathBuf, expect: Option<usize>, expect_any: bool) -> Recurse {
+        Recurse::Replace {
+            rules: None,
+            preserve_case: false,
+            template: false,
+            counter_scope: CounterScope::Run,
+            with_cmd: None,
+            cmd_group: None,
+            cmd_timeout: 10,
+            line_endings: LineEndingChoice::Preserve,
+            final_newline: FinalNewline::Preserve,
+            stream_threshold: 16,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            max_per_file: None,
+            nth: None,
+            first_file_only: false,
+            literal_replacement: false,
+            find: Some("test".to_string()),
+            replace: Some("check".to_string()),
+            nobu: true,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            backup_naming: BackupNaming::Simple,
+            preserve_mtime: false,
+            verify_hash: false,
+            no_journal: true,
+            journal_dir: None,
+            journal_keep: 20,
+            extension: None,
+            hidden: false,
+            inpath,
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            json: false,
+            dry_run: false,
+            interactive: false,
+            expect,
+            expect_any,
+            diff_context: 3,
+            color: ColorChoice::Never,
+        }
+    }
+
+    #[test]
+    fn test_replace_expect_count() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let filepath = tmpdir.path().join("test.txt");
+        std::fs::write(&filepath, "a test\nanother test\n").unwrap();
+
+        // an unmet expectation fails the run without file writes
+        let mut output = Vec::new();
+        let res = ReplaceCommand::execute(
+            expect_subcmd(tmpdir.path().to_path_buf(), Some(3), false),
+            &mut output,
+        );
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "expected 3 replacement(s), found 2"
+        );
+        assert_eq!(read_to_string(&filepath).unwrap(), "a test\nanother test\n");
+        assert!(output.is_empty());
+        // the staged replacements are removed
+        assert_eq!(std::fs::read_dir(tmpdir.path()).unwrap().count(), 1);
+
+        let mut output = Vec::new();
+        let res = ReplaceCommand::execute(
+            expect_subcmd(tmpdir.path().to_path_buf(), Some(2), false),
+            &mut output,
+        );
+        assert!(res.is_ok());
         assert_eq!(
-            get_backup_filepath(&testpath),
-            PathBuf::from("test/path/bogus.txt.bu")
+            read_to_string(&filepath).unwrap(),
+            "a check\nanother check\n"
+        );
+        let output_string = std::str::from_utf8(&output).unwrap().replace(r"\", "/");
+        assert!(output_string.ends_with(
+            "/test.txt updated with 2 replacement(s)\n\
+             1 file(s) updated with 2 replacement(s)\n"
+        ));
+
+        // nothing matched
+        let res = ReplaceCommand::execute(
+            expect_subcmd(tmpdir.path().to_path_buf(), None, false),
+            &mut Vec::new(),
+        );
+        assert_eq!(
+            res.unwrap_err().downcast_ref::<ExitStatus>(),
+            Some(&ExitStatus { code: 1 })
+        );
+        let res = ReplaceCommand::execute(
+            expect_subcmd(tmpdir.path().to_path_buf(), None, true),
+            &mut Vec::new(),
+        );
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "expected at least one replacement, found 0"
         );
     }
 
     #[test]
-    fn test_get_secondary_filepath_with_2_extension() {
-        let testpath = PathBuf::from("test/path/bogus.2");
+    fn test_replace_preserve_case() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let filepath = tmpdir.path().join("test.txt");
+        std::fs::write(&filepath, "test Test TEST test_id TestId\n").unwrap();
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(5), false);
+        if let Recurse::Replace {
+            ref mut preserve_case,
+            ..
+        } = subcmd
+        {
+            *preserve_case = true;
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
         assert_eq!(
-            get_backup_filepath(&testpath),
-            PathBuf::from("test/path/bogus.2.bu")
+            read_to_string(&filepath).unwrap(),
+            "check Check CHECK check_id CheckId\n"
         );
     }
 
     #[test]
-    fn test_get_secondary_filepath_without_extension() {
-        let testpath = PathBuf::from("test/path/bogus");
+    fn test_replace_line_scope() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let filepath = tmpdir.path().join("test.txt");
+        let original = "test\n<!-- BEGIN -->\na test\nkeep test\n<!-- END -->\ntest\n";
+        std::fs::write(&filepath, original).unwrap();
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(1), false);
+        if let Recurse::Replace {
+            ref mut in_lines,
+            ref mut between,
+            ..
+        } = subcmd
+        {
+            *in_lines = Some("^a ".to_string());
+            *between = vec!["BEGIN".to_string(), "END".to_string()];
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
         assert_eq!(
-            get_backup_filepath(&testpath),
-            PathBuf::from("test/path/bogus.bu")
+            read_to_string(&filepath).unwrap(),
+            "test\n<!-- BEGIN -->\na check\nkeep test\n<!-- END -->\ntest\n"
         );
+
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(1), false);
+        if let Recurse::Replace {
+            ref mut line_range, ..
+        } = subcmd
+        {
+            *line_range = Some(":1".parse().unwrap());
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        assert!(read_to_string(&filepath)
+            .unwrap()
+            .starts_with("check\n<!-- BEGIN -->\na check\nkeep test\n"));
     }
-    // ======================================
-    // has_secondary_extension function tests
-    // ======================================
+
     #[test]
-    fn test_has_secondary_extension_with_secondary_extension() {
-        let testpath = PathBuf::from("test/path/bogus.txt.bu");
-        assert!(has_backup_extension(&testpath));
+    fn test_replace_occurrence_limits() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let filepaths = [tmpdir.path().join("a.txt"), tmpdir.path().join("b.txt")];
+        for filepath in &filepaths {
+            std::fs::write(filepath, "test test test\n").unwrap();
+        }
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(2), false);
+        if let Recurse::Replace { ref mut nth, .. } = subcmd {
+            *nth = Some(2);
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        for filepath in &filepaths {
+            assert_eq!(read_to_string(filepath).unwrap(), "test check test\n");
+        }
+
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(2), false);
+        if let Recurse::Replace {
+            ref mut max_per_file,
+            ref mut first_file_only,
+            ..
+        } = subcmd
+        {
+            *max_per_file = Some(2);
+            *first_file_only = true;
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        let contents: Vec<String> = filepaths
+            .iter()
+            .map(|filepath| read_to_string(filepath).unwrap())
+            .collect();
+        assert!(contents.contains(&"check check check\n".to_string()));
+        assert!(contents.contains(&"test check test\n".to_string()));
+
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), None, false);
+        if let Recurse::Replace { ref mut nth, .. } = subcmd {
+            *nth = Some(0);
+        }
+        assert!(ReplaceCommand::execute(subcmd, &mut Vec::new()).is_err());
     }
 
     #[test]
-    fn test_has_secondary_extension_with_secondary_extension_alt1() {
-        let testpath = PathBuf::from("test/path/bogus.bu");
-        assert!(has_backup_extension(&testpath));
+    fn test_replace_template() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let filepaths = [tmpdir.path().join("a.txt"), tmpdir.path().join("b.txt")];
+        for filepath in &filepaths {
+            std::fs::write(filepath, "test test\n").unwrap();
+        }
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(4), false);
+        if let Recurse::Replace {
+            ref mut find,
+            ref mut replace,
+            ref mut template,
+            ..
+        } = subcmd
+        {
+            *find = Some("(t)est".to_string());
+            *replace = Some(r"\U${stem}\E-\u$1-${n}".to_string());
+            *template = true;
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        // the run counter continues across files in traversal order
+        let mut counters: Vec<String> = Vec::new();
+        for (filepath, stem) in filepaths.iter().zip(&["A", "B"]) {
+            let contents = read_to_string(filepath).unwrap();
+            let replacements: Vec<&str> = contents.trim_end().split(' ').collect();
+            for replacement in replacements {
+                let prefix = format!("{}-T-", stem);
+                assert!(replacement.starts_with(&prefix), "{}", replacement);
+                counters.push(replacement[prefix.len()..].to_string());
+            }
+        }
+        counters.sort();
+        assert_eq!(counters, vec!["1", "2", "3", "4"]);
+
+        for filepath in &filepaths {
+            std::fs::write(filepath, "test test\n").unwrap();
+        }
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), None, false);
+        if let Recurse::Replace {
+            ref mut replace,
+            ref mut template,
+            ref mut counter_scope,
+            ..
+        } = subcmd
+        {
+            *replace = Some("check${n}".to_string());
+            *template = true;
+            *counter_scope = CounterScope::File;
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        for filepath in &filepaths {
+            assert_eq!(read_to_string(filepath).unwrap(), "check1 check2\n");
+        }
+
+        // template errors are reported before any file is read
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), None, false);
+        if let Recurse::Replace {
+            ref mut replace,
+            ref mut template,
+            ..
+        } = subcmd
+        {
+            *replace = Some("${env:RECURSE_UNDEFINED_TEMPLATE_VARIABLE}".to_string());
+            *template = true;
+        }
+        assert!(ReplaceCommand::execute(subcmd, &mut Vec::new()).is_err());
     }
 
+    #[cfg(unix)]
     #[test]
-    fn test_has_secondary_extension_without_secondary_extension() {
-        let testpath = PathBuf::from("test/path/bogus.txt");
-        assert_eq!(has_backup_extension(&testpath), false);
+    fn test_replace_with_cmd() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let filepaths = [tmpdir.path().join("a.txt"), tmpdir.path().join("b.txt")];
+        std::fs::write(&filepaths[0], "test test\n").unwrap();
+        std::fs::write(&filepaths[1], "other test\n").unwrap();
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(3), false);
+        if let Recurse::Replace {
+            ref mut replace,
+            ref mut with_cmd,
+            ..
+        } = subcmd
+        {
+            *replace = None;
+            *with_cmd = Some("rev".to_string());
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        assert_eq!(read_to_string(&filepaths[0]).unwrap(), "tset tset\n");
+        assert_eq!(read_to_string(&filepaths[1]).unwrap(), "other tset\n");
+
+        // command failures in the expectation count stop the run before
+        // any file is written
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(3), false);
+        if let Recurse::Replace {
+            ref mut find,
+            ref mut replace,
+            ref mut with_cmd,
+            ..
+        } = subcmd
+        {
+            *find = Some("tset".to_string());
+            *replace = None;
+            *with_cmd = Some("exit 1".to_string());
+        }
+        assert!(ReplaceCommand::execute(subcmd, &mut Vec::new()).is_err());
+        assert_eq!(read_to_string(&filepaths[0]).unwrap(), "tset tset\n");
+
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), None, false);
+        if let Recurse::Replace {
+            ref mut find,
+            ref mut replace,
+            ref mut with_cmd,
+            ref mut cmd_group,
+            ..
+        } = subcmd
+        {
+            *find = Some("t(se)t".to_string());
+            *replace = None;
+            *with_cmd = Some("tr a-z A-Z".to_string());
+            *cmd_group = Some(GroupRef::Index(2));
+        }
+        assert!(ReplaceCommand::execute(subcmd, &mut Vec::new()).is_err());
     }
 
-    // ======================================
-    // is_root_filepath function tests
-    // ======================================
     #[test]
-    fn test_is_root_filepath_with_unix_root() {
-        let testpath = PathBuf::from("/");
-        assert!(is_root_filepath(&testpath));
+    fn test_replace_preserves_text_format() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let filepath = tmpdir.path().join("test.txt");
+        std::fs::write(&filepath, "\u{feff}a test\r\ntest\r\nend").unwrap();
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(2), false);
+        if let Recurse::Replace {
+            ref mut find,
+            ref mut replace,
+            ..
+        } = subcmd
+        {
+            // `$` matches before CRLF line endings and `\n` replacements
+            // are written with the CRLF line endings of the file
+            *find = Some("(?m)test$".to_string());
+            *replace = Some("check\nmore".to_string());
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        assert_eq!(
+            read_to_string(&filepath).unwrap(),
+            "\u{feff}a check\r\nmore\r\ncheck\r\nmore\r\nend"
+        );
+
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(1), false);
+        if let Recurse::Replace {
+            ref mut find,
+            ref mut replace,
+            ref mut line_endings,
+            ref mut final_newline,
+            ..
+        } = subcmd
+        {
+            *find = Some("end".to_string());
+            *replace = Some("done".to_string());
+            *line_endings = LineEndingChoice::Lf;
+            *final_newline = FinalNewline::Add;
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        assert_eq!(
+            read_to_string(&filepath).unwrap(),
+            "\u{feff}a check\nmore\ncheck\nmore\ndone\n"
+        );
     }
 
     #[test]
-    fn test_is_root_filepath_with_win_root() {
-        let testpath = PathBuf::from(r"\");
-        assert!(is_root_filepath(&testpath));
+    fn test_replace_stream() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let journal_dir = tempfile::tempdir().unwrap();
+        let filepath = tmpdir.path().join("test.txt");
+        let original = "\u{feff}a test\r\ntest\r\nend";
+        std::fs::write(&filepath, original).unwrap();
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(2), false);
+        if let Recurse::Replace {
+            ref mut find,
+            ref mut replace,
+            ref mut stream_threshold,
+            ref mut nobu,
+            ref mut no_journal,
+            journal_dir: ref mut journal_dirpath,
+            journal_keep: 20,
+            ..
+        } = subcmd
+        {
+            *find = Some("(?m)test$".to_string());
+            *replace = Some("check\nmore".to_string());
+            *stream_threshold = 0;
+            *nobu = false;
+            *no_journal = false;
+            *journal_dirpath = Some(journal_dir.path().to_path_buf());
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        // streamed files are replaced like files that are read into memory
+        assert_eq!(
+            read_to_string(&filepath).unwrap(),
+            "\u{feff}a check\r\nmore\r\ncheck\r\nmore\r\nend"
+        );
+        assert_eq!(
+            read_to_string(tmpdir.path().join("test.txt.bu")).unwrap(),
+            original
+        );
+        let runs = crate::ops::journal::read_runs(journal_dir.path()).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].files.len(), 1);
+        assert_ne!(
+            runs[0].files[0].original_hash,
+            runs[0].files[0].replaced_hash
+        );
+
+        // multiline patterns are replaced in memory
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(1), false);
+        if let Recurse::Replace {
+            ref mut find,
+            ref mut replace,
+            ref mut stream_threshold,
+            ..
+        } = subcmd
+        {
+            *find = Some("more\ncheck".to_string());
+            *replace = Some("check".to_string());
+            *stream_threshold = 0;
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        assert_eq!(
+            read_to_string(&filepath).unwrap(),
+            "\u{feff}a check\r\ncheck\r\nmore\r\nend"
+        );
+
+        // files with long lines are replaced in memory
+        let long_line = "x".repeat(MAX_LINE_LEN);
+        std::fs::write(&filepath, format!("test\n{}\ntest\n", long_line)).unwrap();
+        let mut subcmd = expect_subcmd(tmpdir.path().to_path_buf(), Some(2), false);
+        if let Recurse::Replace {
+            ref mut replace,
+            ref mut template,
+            ref mut stream_threshold,
+            ..
+        } = subcmd
+        {
+            *replace = Some("check${n}".to_string());
+            *template = true;
+            *stream_threshold = 0;
+        }
+        let res = ReplaceCommand::execute(subcmd, &mut Vec::new());
+        assert!(res.is_ok());
+        assert_eq!(
+            read_to_string(&filepath).unwrap(),
+            format!("check1\n{}\ncheck2\n", long_line)
+        );
     }
 
     #[test]
-    fn test_is_root_filepath_without_root_fp() {
-        let testpath = PathBuf::from("test/path/bogus");
-        assert_eq!(is_root_filepath(&testpath), false);
+    fn test_replace_rules() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let rules_filepath = tmpdir.path().join("rules.toml");
+        std::fs::write(
+            &rules_filepath,
+            "[[rule]]\nfind = \"test\"\nreplace = \"check\"\n\n\
+             [[rule]]\nfind = \"check\"\nreplace = \"verify\"\ninclude = [\"docs/**\"]\n",
+        )
+        .unwrap();
+        let docs_filepath = tmpdir.path().join("docs").join("test.md");
+        std::fs::create_dir_all(docs_filepath.parent().unwrap()).unwrap();
+        std::fs::write(&docs_filepath, "a test\n").unwrap();
+        let filepath = tmpdir.path().join("test.txt");
+        std::fs::write(&filepath, "a test\n").unwrap();
+        let rw = Recurse::Replace {
+            rules: Some(rules_filepath.clone()),
+            preserve_case: false,
+            template: false,
+            counter_scope: CounterScope::Run,
+            with_cmd: None,
+            cmd_group: None,
+            cmd_timeout: 10,
+            line_endings: LineEndingChoice::Preserve,
+            final_newline: FinalNewline::Preserve,
+            stream_threshold: 16,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            max_per_file: None,
+            nth: None,
+            first_file_only: false,
+            literal_replacement: false,
+            find: None,
+            replace: None,
+            nobu: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            backup_naming: BackupNaming::Numbered,
+            preserve_mtime: false,
+            verify_hash: false,
+            no_journal: true,
+            journal_dir: None,
+            journal_keep: 20,
+            extension: None,
+            hidden: false,
+            inpath: tmpdir.path().to_path_buf(),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            json: false,
+            dry_run: false,
+            interactive: false,
+            expect: Some(3),
+            expect_any: false,
+            diff_context: 3,
+            color: ColorChoice::Never,
+        };
+        let mut output = Vec::new();
+        let res = ReplaceCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        // the pairs are applied in sequence with a single write and backup
+        assert_eq!(read_to_string(&docs_filepath).unwrap(), "a verify\n");
+        assert_eq!(read_to_string(&filepath).unwrap(), "a check\n");
+        assert_eq!(
+            read_to_string(tmpdir.path().join("docs").join("test.md.1.bu")).unwrap(),
+            "a test\n"
+        );
+        assert!(!tmpdir.path().join("docs").join("test.md.2.bu").exists());
+        // the rules file is not edited
+        assert!(read_to_string(&rules_filepath)
+            .unwrap()
+            .contains("\"test\""));
+        assert!(std::str::from_utf8(&output)
+            .unwrap()
+            .ends_with("2 file(s) updated with 3 replacement(s)\n"));
+    }
+
+    #[test]
+    fn test_replace_dry_run() {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let original = "a test\nb\nc\nd\ne\nanother test\n";
+        std::fs::write(tmpdir.path().join("test.txt"), original).unwrap();
+        let rw = Recurse::Replace {
+            rules: None,
+            preserve_case: false,
+            template: false,
+            counter_scope: CounterScope::Run,
+            with_cmd: None,
+            cmd_group: None,
+            cmd_timeout: 10,
+            line_endings: LineEndingChoice::Preserve,
+            final_newline: FinalNewline::Preserve,
+            stream_threshold: 16,
+            in_lines: None,
+            line_range: None,
+            between: vec![],
+            max_per_file: None,
+            nth: None,
+            first_file_only: false,
+            literal_replacement: false,
+            find: Some("test".to_string()),
+            replace: Some("check".to_string()),
+            nobu: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            backup_naming: BackupNaming::Simple,
+            preserve_mtime: false,
+            verify_hash: false,
+            no_journal: true,
+            journal_dir: None,
+            journal_keep: 20,
+            extension: None,
+            hidden: false,
+            inpath: tmpdir.path().join("test.txt"),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            json: false,
+            dry_run: true,
+            interactive: false,
+            expect: None,
+            expect_any: false,
+            diff_context: 1,
+            color: ColorChoice::Never,
+        };
+        let mut output = Vec::new();
+        let res = ReplaceCommand::execute(rw, &mut output);
+        let contents = read_to_string(tmpdir.path().join("test.txt")).unwrap();
+        let has_backup = tmpdir.path().join("test.txt.bu").exists();
+        assert!(res.is_ok());
+        // no file writes and no backups
+        assert_eq!(contents, original);
+        assert!(!has_backup);
+        let output_string = std::str::from_utf8(&output).unwrap().replace(r"\", "/");
+        let diff_header = format!(
+            "--- {0}/test.txt\n+++ {0}/test.txt\n",
+            tmpdir.path().display().to_string().replace(r"\", "/")
+        );
+        assert!(output_string.starts_with(&diff_header));
+        assert!(output_string.ends_with(
+            "@@ -1,2 +1,2 @@\n-a test\n+a check\n b\n\
+             @@ -5,2 +5,2 @@\n e\n-another test\n+another check\n\
+             1 file(s) would change with 2 replacement(s)\n"
+        ));
+    }
+
+    #[test]
+    fn test_replace_concurrent_modification_conflict() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let filepath = tmpdir.path().join("test.txt");
+        std::fs::write(&filepath, "a test\n").unwrap();
+        let options = ReplaceOptions {
+            no_backup: false,
+            backup: BackupOptions::new(
+                tmpdir.path(),
+                None,
+                ".bu".to_string(),
+                BackupNaming::Simple,
+            )
+            .unwrap(),
+            preserve_mtime: false,
+            verify_hash: true,
+            json: false,
+            dry_run: false,
+            diff_context: 3,
+            styles: ColorStyles::new(false, &[]).unwrap(),
+            scope: LineScope::default(),
+            occurrences: Occurrences::default(),
+            counter: Cell::new(1),
+            counter_scope: CounterScope::Run,
+            line_endings: LineEndingChoice::Preserve,
+            final_newline: FinalNewline::Preserve,
+            stream_threshold: 16 * 1024 * 1024,
+            journal: None,
+        };
+        let (_, snapshot) = FileSnapshot::read(&filepath, true).unwrap();
+        let pending = PendingEdit::new(&filepath, snapshot, "a check\n", 1).unwrap();
+        // an edit by another process after the read
+        std::fs::write(&filepath, "a test edited elsewhere\n").unwrap();
+        let mut output = Vec::new();
+        let edit = ReplaceCommand::write_replacement(pending, &options, &mut output).unwrap();
+        assert_eq!(edit, FileEdit::Conflict);
+        assert_eq!(
+            read_to_string(&filepath).unwrap(),
+            "a test edited elsewhere\n"
+        );
+        assert!(!tmpdir.path().join("test.txt.bu").exists());
+        // the temporary file is removed
+        assert_eq!(std::fs::read_dir(tmpdir.path()).unwrap().count(), 1);
+        assert!(std::str::from_utf8(&output)
+            .unwrap()
+            .ends_with("conflict: file changed after it was read, not updated\n"));
+
+        let mut stats = ReplaceStats::default();
+        stats.record(&filepath, edit);
+        stats.record(&filepath, FileEdit::Replaced(0));
+        assert_eq!(stats.files, 0);
+        assert_eq!(stats.conflicts, vec![filepath]);
+    }
+
+    fn interactive_test(answers: &str) -> (FileEdit, bool, String, Option<String>, String) {
+        let tmpdir = tempfile::Builder::new()
+            .prefix("recurse-")
+            .tempdir()
+            .unwrap();
+        let filepath = tmpdir.path().join("test.txt");
+        std::fs::write(&filepath, "one test\ntwo test\nthree test\n").unwrap();
+        let options = ReplaceOptions {
+            no_backup: false,
+            backup: BackupOptions::new(
+                tmpdir.path(),
+                None,
+                ".bu".to_string(),
+                BackupNaming::Simple,
+            )
+            .unwrap(),
+            preserve_mtime: false,
+            verify_hash: false,
+            json: false,
+            dry_run: false,
+            diff_context: 1,
+            styles: ColorStyles::new(false, &[]).unwrap(),
+            scope: LineScope::default(),
+            occurrences: Occurrences::default(),
+            counter: Cell::new(1),
+            counter_scope: CounterScope::Run,
+            line_endings: LineEndingChoice::Preserve,
+            final_newline: FinalNewline::Preserve,
+            stream_threshold: 16 * 1024 * 1024,
+            journal: None,
+        };
+        let substitution = Substitution::new(
+            r"(t)est",
+            "${1}ry".to_string(),
+            SubstitutionOptions::default(),
+        )
+        .unwrap();
+        let mut input = std::io::Cursor::new(answers.as_bytes());
+        let mut output = Vec::new();
+        let (replacements, quit) = ReplaceCommand::interactive_replace(
+            &filepath,
+            &substitution,
+            &options,
+            &mut input,
+            &mut output,
+        )
+        .unwrap();
+        let contents = read_to_string(&filepath).unwrap();
+        let backup = read_to_string(tmpdir.path().join("test.txt.bu")).ok();
+        (
+            replacements,
+            quit,
+            contents,
+            backup,
+            String::from_utf8(output).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_replace_interactive_answers() {
+        let (replacements, quit, contents, backup, output) = interactive_test("y\nn\ne\nexam\n");
+        assert_eq!(replacements, FileEdit::Replaced(2));
+        assert!(!quit);
+        assert_eq!(contents, "one try\ntwo test\nthree exam\n");
+        assert_eq!(backup.unwrap(), "one test\ntwo test\nthree test\n");
+        // the match is previewed with the proposed replacement and context
+        assert!(output.contains("test.txt 2:4-8\n one test\n-two test\n+two try\n three test\n"));
+    }
+
+    #[test]
+    fn test_replace_interactive_all_in_file() {
+        let (replacements, quit, contents, _, output) = interactive_test("n\na\n");
+        assert_eq!(replacements, FileEdit::Replaced(2));
+        assert!(!quit);
+        assert_eq!(contents, "one test\ntwo try\nthree try\n");
+        assert_eq!(output.matches("Replace?").count(), 2);
+    }
+
+    #[test]
+    fn test_replace_interactive_quit() {
+        let (replacements, quit, contents, _, _) = interactive_test("y\nq\n");
+        assert_eq!(replacements, FileEdit::Replaced(1));
+        assert!(quit);
+        assert_eq!(contents, "one try\ntwo test\nthree test\n");
+    }
+
+    #[test]
+    fn test_replace_interactive_no_replacements() {
+        let (replacements, quit, contents, backup, _) = interactive_test("n\nn\nn\n");
+        assert_eq!(replacements, FileEdit::Replaced(0));
+        assert!(!quit);
+        assert_eq!(contents, "one test\ntwo test\nthree test\n");
+        assert!(backup.is_none());
     }
 }
@@ -0,0 +1,157 @@
+use std::io::{ErrorKind, Write};
+
+use anyhow::{anyhow, Result};
+
+use crate::command::Command;
+use crate::ops::io::{atomic_write, file_hash};
+use crate::ops::journal::{format_hash, journal_dirpath, read_run, read_runs};
+use crate::{ExitStatus, Recurse};
+
+pub(crate) struct UndoCommand {}
+
+impl Command for UndoCommand {
+    fn execute(subcmd: Recurse, mut writer: impl Write) -> Result<()> {
+        if let Recurse::Undo { run, journal_dir } = subcmd {
+            let journal_dir = journal_dirpath(journal_dir)?;
+            let mut run = match run {
+                Some(id) => read_run(&journal_dir, &id)?,
+                None => read_runs(&journal_dir)?
+                    .into_iter()
+                    .rev()
+                    .find(|run| !run.is_undone())
+                    .ok_or_else(|| anyhow!("no replace runs to undo"))?,
+            };
+            if run.is_undone() {
+                return Err(anyhow!("run '{}' was already undone", run.id()));
+            }
+            // files are only restored when the contents are unchanged
+            // since the run so that later edits are not lost
+            let mut refused = 0;
+            for entry in run.files.clone() {
+                if run.is_restored(entry.index) {
+                    continue;
+                }
+                let current_hash = match file_hash(&entry.path) {
+                    Ok(hash) => Some(format_hash(hash)),
+                    Err(error) if error.kind() == ErrorKind::NotFound => None,
+                    Err(error) => return Err(anyhow!(error)),
+                };
+                match current_hash {
+                    Some(hash) if hash == entry.replaced_hash => {
+                        atomic_write(&entry.path, &run.original(&entry)?, false)?;
+                    }
+                    // the run was interrupted before the file write
+                    Some(hash) if hash == entry.original_hash => {}
+                    _ => {
+                        refused += 1;
+                        writeln!(
+                            writer,
+                            "{} modified after run {}, not restored",
+                            entry.path.display(),
+                            run.id()
+                        )?;
+                        continue;
+                    }
+                }
+                run.mark_restored(entry.index)?;
+                writeln!(writer, "{} restored", entry.path.display())?;
+            }
+            if refused > 0 {
+                writeln!(writer, "{} file(s) not restored", refused)?;
+                writer.flush()?;
+                return Err(ExitStatus { code: 1 }.into());
+            }
+            writeln!(writer, "run {} undone", run.id())?;
+            Ok(())
+        } else {
+            Err(anyhow!("failure to parse undo subcommand."))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::journal::JournalRun;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn undo_subcmd(run: Option<String>, journal_dir: PathBuf) -> Recurse {
+        Recurse::Undo {
+            run,
+            journal_dir: Some(journal_dir),
+        }
+    }
+
+    #[test]
+    fn test_undo_restores_run() {
+        let journal_dir = tempfile::tempdir().unwrap();
+        let workdir = tempfile::tempdir().unwrap();
+        let first = workdir.path().join("first.txt");
+        let second = workdir.path().join("second.txt");
+        let replaced = workdir.path().join("replaced.txt");
+        let run = JournalRun::create(journal_dir.path()).unwrap();
+        for (filepath, original, check) in [
+            (&first, "a test\n", "a check\n"),
+            (&second, "b test\n", "b check\n"),
+        ] {
+            fs::write(filepath, original).unwrap();
+            fs::write(&replaced, check).unwrap();
+            run.record_file(filepath, &replaced).unwrap();
+            fs::rename(&replaced, filepath).unwrap();
+        }
+        run.finish(10).unwrap();
+        // an edit after the run
+        fs::write(&second, "b edited\n").unwrap();
+
+        let mut output = Vec::new();
+        let res = UndoCommand::execute(
+            undo_subcmd(None, journal_dir.path().to_path_buf()),
+            &mut output,
+        );
+        assert_eq!(
+            res.unwrap_err().downcast_ref::<ExitStatus>(),
+            Some(&ExitStatus { code: 1 })
+        );
+        assert_eq!(fs::read_to_string(&first).unwrap(), "a test\n");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "b edited\n");
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        assert!(output_slice.contains("second.txt modified after run"));
+        assert!(output_slice.ends_with("1 file(s) not restored\n"));
+
+        // the refused file is restored when the later edit is reverted
+        fs::write(&second, "b check\n").unwrap();
+        let mut output = Vec::new();
+        let res = UndoCommand::execute(
+            undo_subcmd(None, journal_dir.path().to_path_buf()),
+            &mut output,
+        );
+        assert!(res.is_ok());
+        assert_eq!(fs::read_to_string(&second).unwrap(), "b test\n");
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        assert_eq!(output_slice.lines().count(), 2);
+
+        // all runs are undone
+        let res = UndoCommand::execute(
+            undo_subcmd(None, journal_dir.path().to_path_buf()),
+            &mut Vec::new(),
+        );
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("no replace runs to undo"));
+    }
+
+    #[test]
+    fn test_undo_invalid_run_id() {
+        let journal_dir = tempfile::tempdir().unwrap();
+        let res = UndoCommand::execute(
+            undo_subcmd(
+                Some("20211018T153012Z".to_string()),
+                journal_dir.path().to_path_buf(),
+            ),
+            &mut Vec::new(),
+        );
+        assert!(res.unwrap_err().to_string().contains("no such run"));
+    }
+}
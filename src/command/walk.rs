@@ -1,10 +1,16 @@
+use std::fs::Metadata;
 use std::io::Write;
+use std::time::UNIX_EPOCH;
 
 use anyhow::{anyhow, Result};
+use serde_json::json;
+use walkdir::DirEntry;
 
 use crate::command::Command;
+use crate::ops::backup::{BackupNaming, BackupOptions};
 use crate::ops::io::walk;
 use crate::ops::path::{path_has_extension, path_is_hidden};
+use crate::report::json::{path_value, write_record};
 use crate::Recurse;
 
 pub(crate) struct WalkCommand {}
@@ -15,10 +21,13 @@ impl Command for WalkCommand {
             extension,
             dir_only,
             hidden,
+            backup_dir,
+            backup_suffix,
             inpath,
             mindepth,
             maxdepth,
             symlinks,
+            json,
         } = subcmd
         {
             // ------------
@@ -35,21 +44,28 @@ impl Command for WalkCommand {
             let has_extension_filter = extension.is_some();
 
             // Recursive walk of inpath with user-specified filters
+            // replace sub-command backups are excluded, the naming scheme
+            // does not change the excluded files
+            let backup =
+                BackupOptions::new(&inpath, backup_dir, backup_suffix, BackupNaming::Simple)?;
             for entry in walk(inpath, &mindepth, &maxdepth, &symlinks).filter_map(|f| f.ok()) {
                 let md = entry.metadata().unwrap();
                 if !dir_only && md.is_file() {
                     // File path listings
                     let filepath = entry.path();
-                    if !hidden && path_is_hidden(filepath) {
+                    if backup.excludes(filepath) {
+                        // skip replace sub-command backup files
+                        continue;
+                    } else if !hidden && path_is_hidden(filepath) {
                         // if file is in a hidden path, skip it
                         continue;
                     } else if has_extension_filter {
                         // if user requested extension filter, filter on it
                         if path_has_extension(filepath, extension.as_ref().unwrap()) {
-                            writeln!(writer, "{}", filepath.display())?;
+                            WalkCommand::write_entry(&entry, &md, &json, &mut writer)?;
                         }
                     } else {
-                        writeln!(writer, "{}", filepath.display())?;
+                        WalkCommand::write_entry(&entry, &md, &json, &mut writer)?;
                     }
                 } else if dir_only && md.is_dir() {
                     // Directory path listings
@@ -57,7 +73,7 @@ impl Command for WalkCommand {
                     if !hidden && path_is_hidden(dirpath) {
                         continue;
                     } else {
-                        writeln!(writer, "{}", dirpath.display())?;
+                        WalkCommand::write_entry(&entry, &md, &json, &mut writer)?;
                     }
                 }
             }
@@ -68,6 +84,39 @@ impl Command for WalkCommand {
     }
 }
 
+impl WalkCommand {
+    fn write_entry(
+        entry: &DirEntry,
+        md: &Metadata,
+        json: &bool,
+        writer: &mut impl Write,
+    ) -> Result<()> {
+        if *json {
+            let file_type = if md.is_dir() { "dir" } else { "file" };
+            let modified = md
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs());
+            write_record(
+                writer,
+                "entry",
+                json!({
+                    "path": path_value(entry.path()),
+                    "file_type": file_type,
+                    "depth": entry.depth(),
+                    "size": md.len(),
+                    "modified": modified,
+                    "readonly": md.permissions().readonly(),
+                }),
+            )?;
+        } else {
+            writeln!(writer, "{}", entry.path().display())?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,10 +128,13 @@ mod tests {
             extension: None,
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("path/to/bogus"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -98,12 +150,19 @@ mod tests {
     fn test_walk_invalid_recurse_enum_arg() {
         let rw = Recurse::Contains {
             extension: None,
-            find: "test".to_string(),
+            find: Some("test".to_string()),
             hidden: false,
-            inpath: PathBuf::from("path/to/bogus"),
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: Some(PathBuf::from("path/to/bogus")),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            all_of: vec![],
+            any_of: vec![],
+            none_of: vec![],
+            query: None,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -123,10 +182,13 @@ mod tests {
             extension: None,
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/io/stablepaths"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -143,7 +205,7 @@ mod tests {
         // includes total of 4 lines
         assert!(output_vec.len() == 4);
         // last line is empty string after newline
-        assert!(output_vec[3] == "");
+        assert!(output_vec[3].is_empty());
     }
 
     #[test]
@@ -152,10 +214,13 @@ mod tests {
             extension: None,
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/io/depthtests"),
             mindepth: None,
             maxdepth: Some(1),
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -169,7 +234,7 @@ mod tests {
         // includes total of 2 lines
         assert!(output_vec.len() == 2);
         // last line is empty string after newline
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
@@ -178,10 +243,13 @@ mod tests {
             extension: None,
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/io/depthtests"),
             mindepth: None,
             maxdepth: Some(2),
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -196,7 +264,7 @@ mod tests {
         // includes total of 3 lines
         assert!(output_vec.len() == 3);
         // last line is empty string after newline
-        assert!(output_vec[2] == "");
+        assert!(output_vec[2].is_empty());
     }
 
     #[test]
@@ -205,10 +273,13 @@ mod tests {
             extension: None,
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/io/depthtests"),
             mindepth: Some(3),
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -222,7 +293,7 @@ mod tests {
         // includes total of 2 lines
         assert!(output_vec.len() == 2);
         // last line is empty string after newline
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
@@ -231,10 +302,13 @@ mod tests {
             extension: Some("txt".to_string()),
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/io/stablepaths"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -249,7 +323,7 @@ mod tests {
         // includes total of 2 lines
         assert!(output_vec.len() == 2);
         // last line is empty string after newline
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
@@ -258,10 +332,13 @@ mod tests {
             extension: Some(".txt".to_string()),
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/io/stablepaths"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -276,7 +353,7 @@ mod tests {
         // includes total of 2 lines
         assert!(output_vec.len() == 2);
         // last line is empty string after newline
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
@@ -285,10 +362,13 @@ mod tests {
             extension: None,
             dir_only: false,
             hidden: true,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/.dotdir"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -304,7 +384,7 @@ mod tests {
         // includes total of 4 lines
         assert!(output_vec.len() == 4);
         // last line is empty string after newline
-        assert!(output_vec[3] == "");
+        assert!(output_vec[3].is_empty());
     }
 
     #[test]
@@ -313,10 +393,13 @@ mod tests {
             extension: Some("txt".to_string()),
             dir_only: false,
             hidden: true,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/.dotdir"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -330,7 +413,7 @@ mod tests {
         // includes total of 2 lines
         assert!(output_vec.len() == 2);
         // last line is empty string after newline
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
@@ -339,10 +422,13 @@ mod tests {
             extension: Some(".txt".to_string()),
             dir_only: false,
             hidden: true,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/.dotdir"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -356,7 +442,7 @@ mod tests {
         // includes total of 2 lines
         assert!(output_vec.len() == 2);
         // last line is empty string after newline
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
     }
 
     #[test]
@@ -365,10 +451,13 @@ mod tests {
             extension: None,
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/.dotdir"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -378,7 +467,7 @@ mod tests {
         // includes total of 1 lines with no paths
         assert!(output_vec.len() == 1);
         // last line is empty string after newline
-        assert!(output_vec[0] == "");
+        assert!(output_vec[0].is_empty());
     }
 
     // =================
@@ -390,10 +479,13 @@ mod tests {
             extension: None,
             dir_only: true,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/io/depthtests"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -409,7 +501,7 @@ mod tests {
         // includes total of 4 lines
         assert!(output_vec.len() == 4);
         // last line is empty string after newline
-        assert!(output_vec[3] == "");
+        assert!(output_vec[3].is_empty());
     }
 
     #[test]
@@ -418,10 +510,13 @@ mod tests {
             extension: None,
             dir_only: true,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/.dotdir"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -431,7 +526,7 @@ mod tests {
         // includes total of 1 lines
         assert!(output_vec.len() == 1);
         // last line is empty string after newline
-        assert!(output_vec[0] == "");
+        assert!(output_vec[0].is_empty());
     }
 
     #[test]
@@ -440,10 +535,13 @@ mod tests {
             extension: None,
             dir_only: true,
             hidden: true,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("tests/testfiles/.dotdir"),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let mut output = Vec::new();
         let res = WalkCommand::execute(rw, &mut output);
@@ -457,6 +555,35 @@ mod tests {
         // includes total of 2 lines
         assert!(output_vec.len() == 2);
         // last line is empty string after newline
-        assert!(output_vec[1] == "");
+        assert!(output_vec[1].is_empty());
+    }
+
+    #[test]
+    fn test_walk_subcmd_json() {
+        let rw = Recurse::Walk {
+            extension: Some("txt".to_string()),
+            dir_only: false,
+            hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
+            inpath: PathBuf::from("tests/testfiles/io/stablepaths"),
+            mindepth: None,
+            maxdepth: None,
+            symlinks: false,
+            json: true,
+        };
+        let mut output = Vec::new();
+        let res = WalkCommand::execute(rw, &mut output);
+        assert!(res.is_ok());
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        let record: serde_json::Value = serde_json::from_str(output_slice.trim_end()).unwrap();
+        assert_eq!(record["version"], 1);
+        assert_eq!(record["type"], "entry");
+        let data = &record["data"];
+        assert!(data["path"]["text"].as_str().unwrap().ends_with("test.txt"));
+        assert_eq!(data["file_type"], "file");
+        assert_eq!(data["depth"], 1);
+        assert!(data["size"].is_u64());
+        assert!(data["readonly"].is_boolean());
     }
 }
@@ -22,10 +22,13 @@ mod tests {
             extension: None,
             dir_only: false,
             hidden: false,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("."),
             mindepth: None,
             maxdepth: None,
             symlinks: false,
+            json: false,
         };
         let config = Config::new(rec_enum);
         match &config.subcmd {
@@ -37,14 +40,15 @@ mod tests {
                 mindepth,
                 maxdepth,
                 symlinks,
+                ..
             } => {
-                assert_eq!(extension.is_none(), true);
-                assert_eq!(*dir_only, false);
-                assert_eq!(*hidden, false);
+                assert!(extension.is_none());
+                assert!(!(*dir_only));
+                assert!(!(*hidden));
                 assert_eq!(inpath, &PathBuf::from("."));
-                assert_eq!(mindepth.is_none(), true);
-                assert_eq!(maxdepth.is_none(), true);
-                assert_eq!(*symlinks, false)
+                assert!(mindepth.is_none());
+                assert!(maxdepth.is_none());
+                assert!(!(*symlinks))
             }
             _ => panic!("The configuration test did not match on the Walk subcommand"),
         }
@@ -56,10 +60,13 @@ mod tests {
             extension: Some(String::from("md")),
             dir_only: true,
             hidden: true,
+            backup_dir: None,
+            backup_suffix: ".bu".to_string(),
             inpath: PathBuf::from("."),
             mindepth: Some(3),
             maxdepth: Some(3),
             symlinks: true,
+            json: false,
         };
         let config = Config::new(rec_enum);
         match &config.subcmd {
@@ -71,14 +78,15 @@ mod tests {
                 mindepth,
                 maxdepth,
                 symlinks,
+                ..
             } => {
                 assert_eq!(extension, &Some(String::from("md")));
-                assert_eq!(*dir_only, true);
-                assert_eq!(*hidden, true);
+                assert!(*dir_only);
+                assert!(*hidden);
                 assert_eq!(inpath, &PathBuf::from("."));
                 assert_eq!(mindepth, &Some(3));
                 assert_eq!(maxdepth, &Some(3));
-                assert_eq!(*symlinks, true)
+                assert!(*symlinks)
             }
             _ => panic!("The configuration test did not match on the Walk subcommand"),
         }
@@ -39,6 +39,7 @@
 //!
 //! [Apache License, v2.0](https://github.com/chrissimpkins/recurse/blob/master/LICENSE.md)
 
+use std::fmt;
 use std::path::PathBuf;
 
 use anyhow::Result;
@@ -47,17 +48,168 @@ use structopt::StructOpt;
 pub(crate) mod command;
 pub(crate) mod config;
 pub(crate) mod ops;
+pub(crate) mod report;
 
+use command::backups::{BackupAction, BackupsCommand};
+use command::check::CheckCommand;
 use command::contains::ContainsCommand;
 use command::find::FindCommand;
+use command::history::HistoryCommand;
 use command::replace::ReplaceCommand;
+use command::undo::UndoCommand;
 use command::walk::WalkCommand;
 use command::Command;
 use config::Config;
+use ops::backup::BackupNaming;
+use ops::color::ColorChoice;
+use ops::newline::{FinalNewline, LineEndingChoice};
+use ops::pattern::GroupRef;
+use ops::scope::LineRange;
+use ops::transform::CounterScope;
+
 /// The command line argument implementation
 #[derive(StructOpt, Debug)]
 #[structopt(about = "Recursive directory traversal file management tool")]
 enum Recurse {
+    #[structopt(about = "List, restore, or clean replace backups")]
+    Backups {
+        /// Backup action
+        #[structopt(
+            possible_values = &["list", "restore", "clean"],
+            help = "Backup action: list, restore, or clean"
+        )]
+        action: BackupAction,
+
+        /// Include backups under dot directory or dot file paths
+        /// The default is to not include these files
+        #[structopt(short = "a", long = "all", help = "Include hidden files")]
+        hidden: bool,
+
+        /// Restore files that changed after the replace run
+        #[structopt(long = "force", help = "Restore files that changed after the replace")]
+        force: bool,
+
+        /// Backup directory
+        /// The default is to write backups next to the original files
+        #[structopt(long = "backup-dir", parse(from_os_str), help = "Backup directory")]
+        backup_dir: Option<PathBuf>,
+
+        /// Backup file name suffix
+        #[structopt(
+            long = "backup-suffix",
+            env = "RECURSE_BACKUP_SUFFIX",
+            default_value = ".bu",
+            help = "Backup file name suffix"
+        )]
+        backup_suffix: String,
+
+        /// Backup file naming scheme
+        #[structopt(
+            long = "backup-naming",
+            default_value = "simple",
+            possible_values = &["simple", "numbered", "timestamped"],
+            help = "Backup naming scheme"
+        )]
+        backup_naming: BackupNaming,
+
+        /// Journal directory
+        #[structopt(
+            long = "journal-dir",
+            env = "RECURSE_JOURNAL_DIR",
+            parse(from_os_str),
+            help = "Journal directory"
+        )]
+        journal_dir: Option<PathBuf>,
+
+        /// Number of most recent backups of each file that are kept
+        #[structopt(
+            long = "keep",
+            default_value = "0",
+            help = "Number of backups per file kept by clean"
+        )]
+        keep: usize,
+
+        /// Input file
+        #[structopt(parse(from_os_str), help = "Traversal start path")]
+        inpath: PathBuf,
+    },
+    #[structopt(about = "Check text files against pattern rules")]
+    Check {
+        /// File extension filter
+        #[structopt(short = "e", long = "ext", help = "File extension filter")]
+        extension: Option<String>,
+
+        /// Include hidden files under dot directory or dot file paths
+        /// The default is to not include these files
+        #[structopt(short = "a", long = "all", help = "Include hidden files")]
+        hidden: bool,
+
+        /// Backup directory of the replace sub-command
+        /// Files in the backup directory are excluded
+        #[structopt(
+            long = "backup-dir",
+            parse(from_os_str),
+            help = "Excluded backup directory"
+        )]
+        backup_dir: Option<PathBuf>,
+
+        /// Backup file name suffix of the replace sub-command
+        /// Files with the backup suffix are excluded
+        #[structopt(
+            long = "backup-suffix",
+            env = "RECURSE_BACKUP_SUFFIX",
+            default_value = ".bu",
+            help = "Excluded backup file name suffix"
+        )]
+        backup_suffix: String,
+
+        /// Define the minimum depth of the directory traversal
+        #[structopt(long = "mindepth", help = "Minimum directory depth")]
+        mindepth: Option<usize>,
+
+        /// Define the maximum depth of the directory traversal
+        #[structopt(long = "maxdepth", help = "Maximum directory depth")]
+        maxdepth: Option<usize>,
+
+        /// Follow symbolic links
+        /// Default is to not follow symbolic links
+        #[structopt(long = "symlinks", help = "Follow symbolic links")]
+        symlinks: bool,
+
+        /// TOML rules file
+        #[structopt(
+            short = "r",
+            long = "rules",
+            parse(from_os_str),
+            default_value = "recurse.toml",
+            help = "TOML rules file"
+        )]
+        rules: PathBuf,
+
+        /// SARIF 2.1.0 report format
+        #[structopt(long = "sarif", help = "SARIF report format")]
+        sarif: bool,
+
+        /// JUnit XML report format
+        #[structopt(
+            long = "junit",
+            conflicts_with = "sarif",
+            help = "JUnit XML report format"
+        )]
+        junit: bool,
+
+        /// Checkstyle XML report format
+        #[structopt(
+            long = "checkstyle",
+            conflicts_with_all = &["sarif", "junit"],
+            help = "Checkstyle XML report format"
+        )]
+        checkstyle: bool,
+
+        /// Input file
+        #[structopt(parse(from_os_str), help = "Traversal start path")]
+        inpath: PathBuf,
+    },
     #[structopt(about = "Test for string in text files")]
     Contains {
         /// File extension filter
@@ -69,6 +221,25 @@ enum Recurse {
         #[structopt(short = "a", long = "all", help = "Include hidden files")]
         hidden: bool,
 
+        /// Backup directory of the replace sub-command
+        /// Files in the backup directory are excluded
+        #[structopt(
+            long = "backup-dir",
+            parse(from_os_str),
+            help = "Excluded backup directory"
+        )]
+        backup_dir: Option<PathBuf>,
+
+        /// Backup file name suffix of the replace sub-command
+        /// Files with the backup suffix are excluded
+        #[structopt(
+            long = "backup-suffix",
+            env = "RECURSE_BACKUP_SUFFIX",
+            default_value = ".bu",
+            help = "Excluded backup file name suffix"
+        )]
+        backup_suffix: String,
+
         /// Define the minimum depth of the directory traversal
         #[structopt(long = "mindepth", help = "Minimum directory depth")]
         mindepth: Option<usize>,
@@ -82,13 +253,54 @@ enum Recurse {
         #[structopt(long = "symlinks", help = "Follow symbolic links")]
         symlinks: bool,
 
+        /// Patterns that must all match in a file
+        #[structopt(
+            long = "all-of",
+            number_of_values = 1,
+            help = "Require regular expression pattern match (repeatable)"
+        )]
+        all_of: Vec<String>,
+
+        /// Patterns where at least one must match in a file
+        #[structopt(
+            long = "any-of",
+            number_of_values = 1,
+            help = "Require any regular expression pattern match (repeatable)"
+        )]
+        any_of: Vec<String>,
+
+        /// Patterns that must not match in a file
+        #[structopt(
+            long = "none-of",
+            number_of_values = 1,
+            help = "Exclude regular expression pattern match (repeatable)"
+        )]
+        none_of: Vec<String>,
+
+        /// Boolean query expression
+        /// Supports the `&`, `|`, and `!` operators with parentheses
+        #[structopt(
+            short = "q",
+            long = "query",
+            help = "Boolean pattern query expression (e.g., 'A & B & !C')"
+        )]
+        query: Option<String>,
+
+        /// JSON Lines output
+        #[structopt(long = "json", help = "JSON Lines output")]
+        json: bool,
+
         /// Find string
-        #[structopt(help = "Find regular expression pattern")]
-        find: String,
+        /// Optional when patterns are defined with options
+        #[structopt(
+            required_unless_one = &["all-of", "any-of", "none-of", "query"],
+            help = "Find regular expression pattern"
+        )]
+        find: Option<String>,
 
         /// Input file
         #[structopt(parse(from_os_str), help = "Traversal start path")]
-        inpath: PathBuf,
+        inpath: Option<PathBuf>,
     },
     #[structopt(about = "Find strings in text files")]
     Find {
@@ -101,6 +313,25 @@ enum Recurse {
         #[structopt(short = "a", long = "all", help = "Include hidden files")]
         hidden: bool,
 
+        /// Backup directory of the replace sub-command
+        /// Files in the backup directory are excluded
+        #[structopt(
+            long = "backup-dir",
+            parse(from_os_str),
+            help = "Excluded backup directory"
+        )]
+        backup_dir: Option<PathBuf>,
+
+        /// Backup file name suffix of the replace sub-command
+        /// Files with the backup suffix are excluded
+        #[structopt(
+            long = "backup-suffix",
+            env = "RECURSE_BACKUP_SUFFIX",
+            default_value = ".bu",
+            help = "Excluded backup file name suffix"
+        )]
+        backup_suffix: String,
+
         /// Define the minimum depth of the directory traversal
         #[structopt(long = "mindepth", help = "Minimum directory depth")]
         mindepth: Option<usize>,
@@ -114,13 +345,176 @@ enum Recurse {
         #[structopt(long = "symlinks", help = "Follow symbolic links")]
         symlinks: bool,
 
+        /// Additional find patterns
+        /// All patterns are matched in a single pass
+        #[structopt(
+            short = "p",
+            long = "pattern",
+            number_of_values = 1,
+            help = "Find regular expression pattern (repeatable)"
+        )]
+        patterns: Vec<String>,
+
+        /// Find patterns file with one pattern per line
+        #[structopt(
+            long = "patterns-file",
+            parse(from_os_str),
+            help = "Read find patterns from file"
+        )]
+        patterns_file: Option<PathBuf>,
+
+        /// Treat all find patterns as literal strings
+        #[structopt(
+            short = "F",
+            long = "fixed-strings",
+            help = "Treat find patterns as literal strings"
+        )]
+        fixed_strings: bool,
+
+        /// Report a capture group instead of the whole match
+        /// Defined by group index or group name
+        #[structopt(
+            short = "o",
+            long = "only-group",
+            help = "Report capture group by index or name"
+        )]
+        only_group: Option<GroupRef>,
+
+        /// Match report template
+        #[structopt(
+            long = "format",
+            help = "Match report template (e.g., '{path}:{line}:{col}\\t{1}')"
+        )]
+        format: Option<String>,
+
+        /// Color output choice
+        /// The default is to use color when the standard output stream is a terminal
+        #[structopt(
+            long = "color",
+            default_value = "auto",
+            possible_values = &["auto", "always", "never"],
+            help = "Color output"
+        )]
+        color: ColorChoice,
+
+        /// Color style definitions
+        #[structopt(
+            long = "colors",
+            number_of_values = 1,
+            help = "Color style definition TYPE:STYLE (repeatable)"
+        )]
+        colors: Vec<String>,
+
+        /// Vim quickfix report format
+        #[structopt(
+            long = "vimgrep",
+            conflicts_with_all = &["format", "json", "emacs", "vscode"],
+            help = "Vim quickfix report format"
+        )]
+        vimgrep: bool,
+
+        /// Emacs compilation mode report format
+        #[structopt(
+            long = "emacs",
+            conflicts_with_all = &["format", "json", "vscode"],
+            help = "Emacs compilation mode report format"
+        )]
+        emacs: bool,
+
+        /// VS Code problem matcher report format
+        #[structopt(
+            long = "vscode",
+            conflicts_with_all = &["format", "json"],
+            help = "VS Code problem matcher report format"
+        )]
+        vscode: bool,
+
+        /// SARIF 2.1.0 report format
+        #[structopt(
+            long = "sarif",
+            conflicts_with_all = &["format", "json", "vimgrep", "emacs", "vscode"],
+            help = "SARIF report format"
+        )]
+        sarif: bool,
+
+        /// JUnit XML report format
+        #[structopt(
+            long = "junit",
+            conflicts_with_all = &["format", "json", "vimgrep", "emacs", "vscode", "sarif"],
+            help = "JUnit XML report format"
+        )]
+        junit: bool,
+
+        /// Checkstyle XML report format
+        #[structopt(
+            long = "checkstyle",
+            conflicts_with_all = &["format", "json", "vimgrep", "emacs", "vscode", "sarif", "junit"],
+            help = "Checkstyle XML report format"
+        )]
+        checkstyle: bool,
+
+        /// Number of context lines before and after each line with a match
+        #[structopt(
+            short = "C",
+            long = "context",
+            default_value = "0",
+            help = "Context lines before and after matches"
+        )]
+        context: usize,
+
+        /// Limit matches to the lines that match a regular expression
+        /// pattern
+        #[structopt(
+            long = "in-lines",
+            value_name = "REGEX",
+            help = "Limit matches to lines that match REGEX"
+        )]
+        in_lines: Option<String>,
+
+        /// Limit matches to a 1-based inclusive line number range
+        /// (e.g., `1:10`, `5:`, or `:20`)
+        #[structopt(
+            long = "line-range",
+            value_name = "A:B",
+            help = "Limit matches to the line range A:B"
+        )]
+        line_range: Option<LineRange>,
+
+        /// Limit matches to the lines between START and END marker lines
+        #[structopt(
+            long = "between",
+            value_names = &["START", "END"],
+            number_of_values = 2,
+            help = "Limit matches to lines between START and END marker lines"
+        )]
+        between: Vec<String>,
+
+        /// JSON Lines output
+        #[structopt(long = "json", conflicts_with = "format", help = "JSON Lines output")]
+        json: bool,
+
         /// Find string
-        #[structopt(help = "Find regular expression pattern")]
-        find: String,
+        /// Optional when patterns are defined with options
+        #[structopt(
+            required_unless_one = &["patterns", "patterns-file"],
+            help = "Find regular expression pattern"
+        )]
+        find: Option<String>,
 
         /// Input file
         #[structopt(parse(from_os_str), help = "Traversal start path")]
-        inpath: PathBuf,
+        inpath: Option<PathBuf>,
+    },
+    #[structopt(about = "List the journaled replace runs")]
+    History {
+        /// Journal directory
+        #[structopt(
+            long = "journal-dir",
+            env = "RECURSE_JOURNAL_DIR",
+            parse(from_os_str),
+            help = "Journal directory"
+        )]
+        journal_dir: Option<PathBuf>,
     },
     #[structopt(about = "Replace strings in text files")]
     Replace {
@@ -137,6 +531,62 @@ enum Recurse {
         #[structopt(long = "nobu", help = "Write inplace without backup")]
         nobu: bool,
 
+        /// Backup directory
+        /// The default is to write backups next to the original files
+        #[structopt(long = "backup-dir", parse(from_os_str), help = "Backup directory")]
+        backup_dir: Option<PathBuf>,
+
+        /// Backup file name suffix
+        #[structopt(
+            long = "backup-suffix",
+            env = "RECURSE_BACKUP_SUFFIX",
+            default_value = ".bu",
+            help = "Backup file name suffix"
+        )]
+        backup_suffix: String,
+
+        /// Backup file naming scheme
+        #[structopt(
+            long = "backup-naming",
+            default_value = "simple",
+            possible_values = &["simple", "numbered", "timestamped"],
+            help = "Backup naming scheme"
+        )]
+        backup_naming: BackupNaming,
+
+        /// Preserve the modification time of edited files
+        #[structopt(long = "preserve-mtime", help = "Preserve file modification times")]
+        preserve_mtime: bool,
+
+        /// Compare file content hashes in the concurrent modification check
+        #[structopt(long = "verify-hash", help = "Detect file changes with content hashes")]
+        verify_hash: bool,
+
+        /// Skip the undo journal record of the run
+        #[structopt(
+            long = "no-journal",
+            help = "Do not record the run in the undo journal"
+        )]
+        no_journal: bool,
+
+        /// Journal directory
+        #[structopt(
+            long = "journal-dir",
+            env = "RECURSE_JOURNAL_DIR",
+            parse(from_os_str),
+            help = "Journal directory"
+        )]
+        journal_dir: Option<PathBuf>,
+
+        /// The number of runs that are kept in the undo journal
+        #[structopt(
+            long = "journal-keep",
+            value_name = "N",
+            default_value = "20",
+            help = "Number of journaled runs to keep"
+        )]
+        journal_keep: usize,
+
         /// Define the minimum depth of the directory traversal
         #[structopt(long = "mindepth", help = "Minimum directory depth")]
         mindepth: Option<usize>,
@@ -150,18 +600,264 @@ enum Recurse {
         #[structopt(long = "symlinks", help = "Follow symbolic links")]
         symlinks: bool,
 
+        /// JSON Lines output
+        #[structopt(long = "json", help = "JSON Lines output")]
+        json: bool,
+
+        /// Report a unified diff of the replacements without file writes
+        #[structopt(
+            long = "dry-run",
+            conflicts_with = "json",
+            help = "Report unified diff without file writes"
+        )]
+        dry_run: bool,
+
+        /// Confirm each replacement
+        #[structopt(
+            short = "i",
+            long = "interactive",
+            conflicts_with_all = &["json", "dry-run"],
+            help = "Confirm each replacement"
+        )]
+        interactive: bool,
+
+        /// Fail the run without file writes when the number of
+        /// replacements is not N
+        #[structopt(
+            long = "expect",
+            value_name = "N",
+            conflicts_with = "interactive",
+            help = "Require N replacements"
+        )]
+        expect: Option<usize>,
+
+        /// Fail the run without file writes when nothing matches
+        #[structopt(
+            long = "expect-any",
+            conflicts_with_all = &["interactive", "expect"],
+            help = "Require at least one replacement"
+        )]
+        expect_any: bool,
+
+        /// Number of context lines in dry run and interactive reports
+        #[structopt(
+            short = "U",
+            long = "unified",
+            default_value = "3",
+            help = "Dry run and interactive context lines"
+        )]
+        diff_context: usize,
+
+        /// Color output choice
+        /// The default is to use color when the standard output stream is a terminal
+        #[structopt(
+            long = "color",
+            default_value = "auto",
+            possible_values = &["auto", "always", "never"],
+            help = "Color output"
+        )]
+        color: ColorChoice,
+
+        /// Limit replacements to the lines that match a regular expression
+        /// pattern
+        #[structopt(
+            long = "in-lines",
+            value_name = "REGEX",
+            help = "Limit replacements to lines that match REGEX"
+        )]
+        in_lines: Option<String>,
+
+        /// Limit replacements to a 1-based inclusive line number range
+        /// (e.g., `1:10`, `5:`, or `:20`)
+        #[structopt(
+            long = "line-range",
+            value_name = "A:B",
+            help = "Limit replacements to the line range A:B"
+        )]
+        line_range: Option<LineRange>,
+
+        /// Limit replacements to the lines between START and END marker lines
+        #[structopt(
+            long = "between",
+            value_names = &["START", "END"],
+            number_of_values = 2,
+            help = "Limit replacements to lines between START and END marker lines"
+        )]
+        between: Vec<String>,
+
+        /// Replace at most N occurrences of each find pattern in a file
+        #[structopt(
+            long = "max-per-file",
+            value_name = "N",
+            conflicts_with = "nth",
+            help = "Replace at most N occurrences in each file"
+        )]
+        max_per_file: Option<usize>,
+
+        /// Replace only the K-th occurrence of each find pattern in a file
+        #[structopt(
+            long = "nth",
+            value_name = "K",
+            help = "Replace only the K-th occurrence in each file"
+        )]
+        nth: Option<usize>,
+
+        /// Stop the traversal after the first file with replacements
+        #[structopt(
+            long = "first-file-only",
+            help = "Stop after the first file with replacements"
+        )]
+        first_file_only: bool,
+
+        /// Insert the replacement string without capture group
+        /// expansion
+        #[structopt(
+            long = "literal-replacement",
+            help = "Replace with the literal replacement string"
+        )]
+        literal_replacement: bool,
+
+        /// Match case-insensitively and reproduce the letter case pattern
+        /// of each match in the replacement
+        #[structopt(long = "preserve-case", help = "Preserve the letter case of matches")]
+        preserve_case: bool,
+
+        /// Evaluate the replacement string as a template with `\U`, `\L`,
+        /// `\u`, `\l`, and `\E` case conversions and the `${n}`,
+        /// `${path}`, `${stem}`, `${date}`, and `${env:NAME}` variables
+        #[structopt(
+            long = "template",
+            conflicts_with = "literal-replacement",
+            help = "Evaluate the replacement as a template"
+        )]
+        template: bool,
+
+        /// Template `${n}` counter scope
+        /// The counter increments across all files in a run or restarts
+        /// at 1 in each file
+        #[structopt(
+            long = "counter-scope",
+            default_value = "run",
+            possible_values = &["run", "file"],
+            help = "Template counter scope"
+        )]
+        counter_scope: CounterScope,
+
+        /// Shell command that receives each match on the standard input
+        /// stream and writes the replacement to the standard output stream
+        #[structopt(
+            long = "with-cmd",
+            value_name = "CMD",
+            conflicts_with_all = &["replace", "rules", "template", "literal-replacement"],
+            help = "Replace with the output of a shell command"
+        )]
+        with_cmd: Option<String>,
+
+        /// Capture group index or name of the match text that is written
+        /// to the command
+        /// The default is the complete match
+        #[structopt(
+            long = "cmd-group",
+            value_name = "GROUP",
+            requires = "with-cmd",
+            help = "Command input capture group"
+        )]
+        cmd_group: Option<GroupRef>,
+
+        /// Maximum run time of each command in seconds
+        #[structopt(
+            long = "cmd-timeout",
+            value_name = "SECONDS",
+            default_value = "10",
+            help = "Command timeout in seconds"
+        )]
+        cmd_timeout: u64,
+
+        /// Line ending style of replaced files
+        /// The default preserves the LF, CRLF, or mixed line endings of
+        /// each file
+        #[structopt(
+            long = "line-endings",
+            default_value = "preserve",
+            possible_values = &["preserve", "lf", "crlf"],
+            help = "Line ending style of replaced files"
+        )]
+        line_endings: LineEndingChoice,
+
+        /// Final newline state of replaced files
+        /// The default preserves the final newline state of each file
+        #[structopt(
+            long = "final-newline",
+            default_value = "preserve",
+            possible_values = &["preserve", "add", "remove"],
+            help = "Final newline of replaced files"
+        )]
+        final_newline: FinalNewline,
+
+        /// Minimum file size in MiB of streaming replacements
+        /// Files of at least this size are read and written one line at a
+        /// time when the find patterns and line scope are limited to single
+        /// lines.  A value of 0 streams all such files.
+        #[structopt(
+            long = "stream-threshold",
+            value_name = "MIB",
+            default_value = "16",
+            help = "Streaming replacement file size threshold in MiB"
+        )]
+        stream_threshold: u64,
+
+        /// TOML, YAML, or CSV rules file of ordered find and replace
+        /// pairs that are applied in sequence to each file
+        #[structopt(
+            long = "rules",
+            value_name = "FILE",
+            parse(from_os_str),
+            conflicts_with_all = &["find", "replace", "interactive"],
+            help = "Find and replace rules file"
+        )]
+        rules: Option<PathBuf>,
+
         /// Find string
-        #[structopt(short = "f", long = "find", help = "Find regular expression pattern")]
-        find: String,
+        /// Optional when pairs are defined in a rules file
+        #[structopt(
+            short = "f",
+            long = "find",
+            required_unless = "rules",
+            help = "Find regular expression pattern"
+        )]
+        find: Option<String>,
 
         /// Replace string
-        #[structopt(short = "r", long = "replace", help = "Replace string")]
-        replace: String,
+        /// Optional when pairs are defined in a rules file or replacements
+        /// are command outputs
+        #[structopt(
+            short = "r",
+            long = "replace",
+            required_unless_one = &["rules", "with-cmd"],
+            help = "Replace string"
+        )]
+        replace: Option<String>,
 
         /// Input file
         #[structopt(parse(from_os_str), help = "Traversal start path")]
         inpath: PathBuf,
     },
+    #[structopt(about = "Undo a journaled replace run")]
+    Undo {
+        /// Run id
+        /// The default is the most recent run that was not undone
+        #[structopt(long = "run", help = "Run id (default: most recent run)")]
+        run: Option<String>,
+
+        /// Journal directory
+        #[structopt(
+            long = "journal-dir",
+            env = "RECURSE_JOURNAL_DIR",
+            parse(from_os_str),
+            help = "Journal directory"
+        )]
+        journal_dir: Option<PathBuf>,
+    },
     #[structopt(about = "Walk the directory structure for paths")]
     Walk {
         /// File extension filter
@@ -177,6 +873,25 @@ enum Recurse {
         #[structopt(short = "a", long = "all", help = "Include hidden paths")]
         hidden: bool,
 
+        /// Backup directory of the replace sub-command
+        /// Files in the backup directory are excluded
+        #[structopt(
+            long = "backup-dir",
+            parse(from_os_str),
+            help = "Excluded backup directory"
+        )]
+        backup_dir: Option<PathBuf>,
+
+        /// Backup file name suffix of the replace sub-command
+        /// Files with the backup suffix are excluded
+        #[structopt(
+            long = "backup-suffix",
+            env = "RECURSE_BACKUP_SUFFIX",
+            default_value = ".bu",
+            help = "Excluded backup file name suffix"
+        )]
+        backup_suffix: String,
+
         /// Input file
         #[structopt(parse(from_os_str), help = "Traversal start path")]
         inpath: PathBuf,
@@ -193,20 +908,68 @@ enum Recurse {
         /// Default is to not follow symbolic links
         #[structopt(long = "symlinks", help = "Follow symbolic links")]
         symlinks: bool,
+
+        /// JSON Lines output
+        #[structopt(long = "json", help = "JSON Lines output")]
+        json: bool,
     },
 }
 
+/// A non-zero exit status without an error message.  Sub-commands
+/// return this error when the run succeeded with a result that is
+/// reported through the exit status (e.g., `check` rule violations).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExitStatus {
+    pub code: i32,
+}
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "exit status {}", self.code)
+    }
+}
+
+impl std::error::Error for ExitStatus {}
+
+/// An error that is reported with a non-zero exit status other than
+/// the default `1` exit status of errors.
+#[derive(Debug)]
+pub struct ExitError {
+    pub code: i32,
+    pub error: anyhow::Error,
+}
+
+impl fmt::Display for ExitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for ExitError {}
+
+/// Returns the `error` as an `ExitError` with the `code` exit status.
+/// `ExitStatus` errors are not changed.
+fn with_exit_code(error: anyhow::Error, code: i32) -> anyhow::Error {
+    if error.is::<ExitStatus>() {
+        error
+    } else {
+        ExitError { code, error }.into()
+    }
+}
+
 /// `recurse` executable execution entry point
 pub fn run() -> Result<()> {
     let config = Config::new(Recurse::from_args());
     match &config.subcmd {
-        Recurse::Contains { .. } => {
-            return ContainsCommand::execute(config.subcmd, &mut std::io::stdout())
-        }
-        Recurse::Find { .. } => return FindCommand::execute(config.subcmd, &mut std::io::stdout()),
-        Recurse::Replace { .. } => {
-            return ReplaceCommand::execute(config.subcmd, &mut std::io::stdout())
-        }
-        Recurse::Walk { .. } => return WalkCommand::execute(config.subcmd, &mut std::io::stdout()),
+        Recurse::Backups { .. } => BackupsCommand::execute(config.subcmd, &mut std::io::stdout()),
+        Recurse::Check { .. } => CheckCommand::execute(config.subcmd, &mut std::io::stdout()),
+        Recurse::Contains { .. } => ContainsCommand::execute(config.subcmd, &mut std::io::stdout()),
+        Recurse::Find { .. } => FindCommand::execute(config.subcmd, &mut std::io::stdout()),
+        Recurse::History { .. } => HistoryCommand::execute(config.subcmd, &mut std::io::stdout()),
+        // grep-like exit status: 0 files changed, 1 nothing matched, 2 error
+        Recurse::Replace { .. } => ReplaceCommand::execute(config.subcmd, &mut std::io::stdout())
+            .map_err(|error| with_exit_code(error, 2)),
+        Recurse::Undo { .. } => UndoCommand::execute(config.subcmd, &mut std::io::stdout()),
+        Recurse::Walk { .. } => WalkCommand::execute(config.subcmd, &mut std::io::stdout()),
     }
 }
@@ -0,0 +1,424 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use crate::ops::io::walk;
+use crate::ops::path::path_is_hidden;
+use crate::ops::time::UtcDateTime;
+
+/// Returns a boolean that indicates whether the `filepath` file name
+/// ends with the backup `suffix`.
+pub(crate) fn is_backup_filepath(filepath: &Path, suffix: &str) -> bool {
+    match filepath.file_name() {
+        Some(name) => {
+            let name = name.to_string_lossy();
+            name.len() > suffix.len() && name.ends_with(suffix)
+        }
+        None => false,
+    }
+}
+
+/// Backup file naming scheme defined with the `--backup-naming` option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BackupNaming {
+    /// `file.txt.bu`, overwritten on each run
+    Simple,
+    /// `file.txt.1.bu`, `file.txt.2.bu`, ...
+    Numbered,
+    /// `file.txt.20211018T153012Z.bu`
+    Timestamped,
+}
+
+impl FromStr for BackupNaming {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "simple" => Ok(BackupNaming::Simple),
+            "numbered" => Ok(BackupNaming::Numbered),
+            "timestamped" => Ok(BackupNaming::Timestamped),
+            _ => Err(anyhow!("invalid backup naming scheme '{}'", s)),
+        }
+    }
+}
+
+/// The version of a backup.  Numbered backups are ordered by number
+/// and timestamped backups by time and then by the numeric suffix of
+/// backups that are written in the same second.
+type BackupVersion = (String, u64);
+
+/// A backup file and the path of the original file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Backup {
+    pub(crate) path: PathBuf,
+    pub(crate) original: PathBuf,
+    version: BackupVersion,
+}
+
+/// The backup location and naming options.  Backups are written next
+/// to the original file by default.  When a backup directory is
+/// defined, backups are written in the backup directory at the path of
+/// the original file relative to the traversal start directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BackupOptions {
+    inpath: PathBuf,
+    base: PathBuf,
+    dir: Option<PathBuf>,
+    suffix: String,
+    naming: BackupNaming,
+}
+
+impl BackupOptions {
+    /// Returns the backup options of a traversal that starts at the
+    /// `inpath` path.
+    pub(crate) fn new(
+        inpath: &Path,
+        dir: Option<PathBuf>,
+        suffix: String,
+        naming: BackupNaming,
+    ) -> Result<Self> {
+        if suffix.is_empty() || suffix.contains(['/', '\\']) {
+            return Err(anyhow!("invalid backup suffix '{}'", suffix));
+        }
+        let base = if inpath.is_dir() {
+            inpath.to_path_buf()
+        } else {
+            inpath
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .to_path_buf()
+        };
+        Ok(Self {
+            inpath: inpath.to_path_buf(),
+            base,
+            dir,
+            suffix,
+            naming,
+        })
+    }
+
+    /// Returns a boolean that indicates whether the `filepath` file is
+    /// a backup file.
+    pub(crate) fn is_backup(&self, filepath: &Path) -> bool {
+        is_backup_filepath(filepath, &self.suffix)
+    }
+
+    /// Returns a boolean that indicates whether the `filepath` file is
+    /// in the backup directory.
+    pub(crate) fn in_backup_dir(&self, filepath: &Path) -> bool {
+        match &self.dir {
+            Some(dir) => match (dir.canonicalize(), filepath.canonicalize()) {
+                (Ok(dir), Ok(filepath)) => filepath.starts_with(dir),
+                _ => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Returns a boolean that indicates whether the `filepath` file is
+    /// a backup file or is in the backup directory.  Backups are excluded
+    /// from the traversals of all sub-commands.
+    pub(crate) fn excludes(&self, filepath: &Path) -> bool {
+        self.is_backup(filepath) || self.in_backup_dir(filepath)
+    }
+
+    /// Returns the path of a new backup of the `filepath` file.
+    pub(crate) fn backup_filepath(&self, filepath: &Path) -> PathBuf {
+        let stem = self.backup_stem(filepath);
+        match self.naming {
+            BackupNaming::Simple => append_to_filename(&stem, &self.suffix),
+            BackupNaming::Numbered => {
+                let number = self
+                    .versions_of(&stem)
+                    .into_iter()
+                    .map(|(_, number)| number)
+                    .max()
+                    .unwrap_or(0)
+                    + 1;
+                append_to_filename(&stem, &format!(".{}{}", number, self.suffix))
+            }
+            BackupNaming::Timestamped => {
+                let timestamp = UtcDateTime::now().compact();
+                let mut count = 0;
+                loop {
+                    let marker = match count {
+                        0 => timestamp.clone(),
+                        _ => format!("{}-{}", timestamp, count),
+                    };
+                    let backup_filepath =
+                        append_to_filename(&stem, &format!(".{}{}", marker, self.suffix));
+                    if !backup_filepath.exists() {
+                        break backup_filepath;
+                    }
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    /// Returns the backups of the traversal in original file path and
+    /// version order.  Backups under hidden paths are included when
+    /// `hidden` is `true`.
+    pub(crate) fn backups(&self, hidden: bool) -> Vec<Backup> {
+        let root = self.dir.as_ref().unwrap_or(&self.base);
+        let walk_root = match root.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => root.as_path(),
+        };
+        if !walk_root.exists() {
+            return Vec::new();
+        }
+        // the backups of a file start path are in the backup root
+        let maxdepth = match self.inpath.is_file() {
+            true => Some(1),
+            false => None,
+        };
+        let mut backups = Vec::new();
+        for entry in walk(walk_root, &None, &maxdepth, &false).filter_map(|f| f.ok()) {
+            let filepath = entry.path();
+            if !entry.file_type().is_file() || !self.is_backup(filepath) {
+                continue;
+            }
+            let relpath = filepath.strip_prefix(walk_root).unwrap_or(filepath);
+            if !hidden && path_is_hidden(relpath) {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy();
+            let (original_name, version) = match self.parse_backup_name(&name) {
+                Some(parsed) => parsed,
+                None => continue,
+            };
+            let original = match &self.dir {
+                Some(_) => self
+                    .base
+                    .join(relpath.parent().unwrap_or_else(|| Path::new("")))
+                    .join(original_name),
+                None => self.base.join(relpath).with_file_name(original_name),
+            };
+            // a file start path only includes backups of the file
+            if self.inpath.is_file() && original != self.inpath {
+                continue;
+            }
+            backups.push(Backup {
+                path: filepath.to_path_buf(),
+                original,
+                version,
+            });
+        }
+        backups.sort_by(|a, b| (&a.original, &a.version).cmp(&(&b.original, &b.version)));
+        backups
+    }
+
+    /// Returns the backup path of the `filepath` file without the
+    /// version and suffix.
+    fn backup_stem(&self, filepath: &Path) -> PathBuf {
+        match &self.dir {
+            Some(dir) => match filepath.strip_prefix(&self.base) {
+                Ok(relpath) if !relpath.as_os_str().is_empty() => dir.join(relpath),
+                _ => dir.join(filepath.file_name().unwrap_or_default()),
+            },
+            None => filepath.to_path_buf(),
+        }
+    }
+
+    /// Returns the versions of the existing backups at the `stem`
+    /// backup path.
+    fn versions_of(&self, stem: &Path) -> Vec<BackupVersion> {
+        let dirpath = match stem.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let stem_name = stem.file_name().unwrap_or_default().to_string_lossy();
+        match dirpath.read_dir() {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    match self.parse_backup_name(&name) {
+                        Some((original_name, version)) if original_name == stem_name => {
+                            Some(version)
+                        }
+                        _ => None,
+                    }
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the original file name and the version of the `name`
+    /// backup file name in the naming scheme.
+    fn parse_backup_name(&self, name: &str) -> Option<(String, BackupVersion)> {
+        let rest = name.strip_suffix(&self.suffix)?;
+        if rest.is_empty() {
+            return None;
+        }
+        match self.naming {
+            BackupNaming::Simple => Some((rest.to_string(), (String::new(), 0))),
+            BackupNaming::Numbered => {
+                let (original_name, number) = rest.rsplit_once('.')?;
+                if original_name.is_empty() || !is_digits(number) {
+                    return None;
+                }
+                Some((
+                    original_name.to_string(),
+                    (String::new(), number.parse().ok()?),
+                ))
+            }
+            BackupNaming::Timestamped => {
+                let (original_name, marker) = rest.rsplit_once('.')?;
+                let (timestamp, count) = match marker.split_once('-') {
+                    Some((timestamp, count)) if is_digits(count) => {
+                        (timestamp, count.parse().ok()?)
+                    }
+                    Some(_) => return None,
+                    None => (marker, 0),
+                };
+                if original_name.is_empty() || !is_compact_timestamp(timestamp) {
+                    return None;
+                }
+                Some((original_name.to_string(), (timestamp.to_string(), count)))
+            }
+        }
+    }
+}
+
+fn append_to_filename(filepath: &Path, text: &str) -> PathBuf {
+    let mut filename = OsString::from(filepath.file_name().unwrap_or_default());
+    filename.push(text);
+    filepath.with_file_name(filename)
+}
+
+fn is_digits(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Returns a boolean that indicates whether the `text` string is a
+/// `YYYYMMDDTHHMMSSZ` timestamp.
+fn is_compact_timestamp(text: &str) -> bool {
+    text.len() == 16
+        && is_digits(&text[..8])
+        && &text[8..9] == "T"
+        && is_digits(&text[9..15])
+        && text.ends_with('Z')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn simple_options(inpath: &Path) -> BackupOptions {
+        BackupOptions::new(inpath, None, ".bu".to_string(), BackupNaming::Simple).unwrap()
+    }
+
+    #[test]
+    fn test_backup_filepath_simple() {
+        let options = simple_options(Path::new("test/path"));
+        assert_eq!(
+            options.backup_filepath(Path::new("test/path/bogus.txt")),
+            PathBuf::from("test/path/bogus.txt.bu")
+        );
+        assert_eq!(
+            options.backup_filepath(Path::new("test/path/bogus.2")),
+            PathBuf::from("test/path/bogus.2.bu")
+        );
+        assert_eq!(
+            options.backup_filepath(Path::new("test/path/bogus")),
+            PathBuf::from("test/path/bogus.bu")
+        );
+    }
+
+    #[test]
+    fn test_is_backup_filepath() {
+        assert!(is_backup_filepath(
+            Path::new("test/path/bogus.txt.bu"),
+            ".bu"
+        ));
+        assert!(is_backup_filepath(Path::new("test/path/bogus.bu"), ".bu"));
+        assert!(!is_backup_filepath(Path::new("test/path/bogus.txt"), ".bu"));
+        assert!(!is_backup_filepath(Path::new("test/path/.bu"), ".bu"));
+        assert!(is_backup_filepath(Path::new("bogus.txt~"), "~"));
+    }
+
+    #[test]
+    fn test_backup_options_invalid_suffix() {
+        let new = |suffix: &str| {
+            BackupOptions::new(
+                Path::new("."),
+                None,
+                suffix.to_string(),
+                BackupNaming::Simple,
+            )
+        };
+        assert!(new("").is_err());
+        assert!(new("/bu").is_err());
+        assert!(new("~").is_ok());
+    }
+
+    #[test]
+    fn test_backup_dir_numbered_backups() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let inpath = tmpdir.path().join("src");
+        let backup_dir = tmpdir.path().join("backups");
+        fs::create_dir_all(inpath.join("sub")).unwrap();
+        let filepath = inpath.join("sub").join("test.txt");
+        fs::write(&filepath, "test").unwrap();
+        let options = BackupOptions::new(
+            &inpath,
+            Some(backup_dir.clone()),
+            "~".to_string(),
+            BackupNaming::Numbered,
+        )
+        .unwrap();
+
+        let first = options.backup_filepath(&filepath);
+        assert_eq!(first, backup_dir.join("sub").join("test.txt.1~"));
+        fs::create_dir_all(first.parent().unwrap()).unwrap();
+        fs::write(&first, "first").unwrap();
+        let second = options.backup_filepath(&filepath);
+        assert_eq!(second, backup_dir.join("sub").join("test.txt.2~"));
+        fs::write(&second, "second").unwrap();
+        assert!(options.in_backup_dir(&second));
+        assert!(!options.in_backup_dir(&filepath));
+
+        let backups = options.backups(false);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].path, first);
+        assert_eq!(backups[1].path, second);
+        assert!(backups.iter().all(|backup| backup.original == filepath));
+    }
+
+    #[test]
+    fn test_timestamped_backups() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let filepath = tmpdir.path().join("test.txt");
+        fs::write(&filepath, "test").unwrap();
+        let options = BackupOptions::new(
+            tmpdir.path(),
+            None,
+            ".bu".to_string(),
+            BackupNaming::Timestamped,
+        )
+        .unwrap();
+        let first = options.backup_filepath(&filepath);
+        fs::write(&first, "first").unwrap();
+        // a second backup in the same second has a numeric suffix
+        let second = options.backup_filepath(&filepath);
+        assert_ne!(first, second);
+        fs::write(&second, "second").unwrap();
+        // backups in other naming schemes are not parsed
+        fs::write(tmpdir.path().join("test.txt.1.bu"), "other").unwrap();
+
+        let backups = options.backups(true);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[1].path, second);
+        assert_eq!(backups[1].original, filepath);
+        assert!(options
+            .parse_backup_name("test.txt.20211018T153012Z-2.bu")
+            .is_some());
+        assert!(options.parse_backup_name("test.txt.2021.bu").is_none());
+    }
+}
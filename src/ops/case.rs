@@ -0,0 +1,197 @@
+/// The letter case of a word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WordCase {
+    Lower,
+    Upper,
+    Title,
+    Mixed,
+}
+
+impl WordCase {
+    /// Returns the letter case of the `word`.  A single uppercase letter
+    /// is title case.
+    fn of(word: &str) -> Option<Self> {
+        let cased: Vec<char> = word
+            .chars()
+            .filter(|c| c.is_lowercase() || c.is_uppercase())
+            .collect();
+        if cased.is_empty() {
+            None
+        } else if cased.iter().all(|c| c.is_lowercase()) {
+            Some(WordCase::Lower)
+        } else if cased.len() > 1 && cased.iter().all(|c| c.is_uppercase()) {
+            Some(WordCase::Upper)
+        } else if cased[0].is_uppercase() && cased[1..].iter().all(|c| c.is_lowercase()) {
+            Some(WordCase::Title)
+        } else {
+            Some(WordCase::Mixed)
+        }
+    }
+
+    /// Returns the `word` in this letter case.
+    fn apply(&self, word: &str) -> String {
+        match self {
+            WordCase::Lower => word.to_lowercase(),
+            WordCase::Upper => word.to_uppercase(),
+            WordCase::Title => {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first
+                        .to_uppercase()
+                        .chain(chars.flat_map(char::to_lowercase))
+                        .collect(),
+                    None => String::new(),
+                }
+            }
+            WordCase::Mixed => word.to_string(),
+        }
+    }
+}
+
+/// Returns the `text` with an uppercase first character.
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Returns the words of the `text` identifier and the first word
+/// separator.  Words are separated by `_`, `-`, and space characters
+/// and by camel case boundaries.  The separator is `None` when the
+/// words are only separated by camel case boundaries.
+fn split_words(text: &str) -> (Vec<&str>, Option<char>) {
+    let mut words = Vec::new();
+    let mut separator = None;
+    let mut start = 0;
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    for (index, &(offset, c)) in chars.iter().enumerate() {
+        if c == '_' || c == '-' || c == ' ' {
+            if start < offset {
+                words.push(&text[start..offset]);
+            }
+            separator.get_or_insert(c);
+            start = offset + c.len_utf8();
+            continue;
+        }
+        if index == 0 || offset == start || !c.is_uppercase() {
+            continue;
+        }
+        let previous = chars[index - 1].1;
+        let next_is_lower = chars
+            .get(index + 1)
+            .is_some_and(|(_, next)| next.is_lowercase());
+        // `widgetId` and `HTTPServer` boundaries
+        if previous.is_lowercase()
+            || previous.is_ascii_digit()
+            || (previous.is_uppercase() && next_is_lower)
+        {
+            words.push(&text[start..offset]);
+            start = offset;
+        }
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    (words, separator)
+}
+
+/// Returns the `replacement` string in the letter case pattern of the
+/// `matched` string.  Uppercase and lowercase matches produce uppercase
+/// and lowercase replacements, and a match that begins with an uppercase
+/// letter produces a replacement that begins with an uppercase letter.
+/// Matches of more than one word produce the replacement words joined
+/// with the snake, kebab, or camel case word separation of the match in
+/// the letter case of the matched words.
+pub(crate) fn preserve_case(matched: &str, replacement: &str) -> String {
+    let (matched_words, separator) = split_words(matched);
+    // single uppercase letters are uppercase words in uppercase matches
+    let is_uppercase = !matched.chars().any(char::is_lowercase);
+    let cases: Vec<WordCase> = matched_words
+        .iter()
+        .filter_map(|word| WordCase::of(word))
+        .map(|case| if is_uppercase { WordCase::Upper } else { case })
+        .collect();
+    if cases.is_empty() {
+        return replacement.to_string();
+    }
+    if cases.len() == 1 {
+        return match cases[0] {
+            WordCase::Mixed => replacement.to_string(),
+            WordCase::Title => capitalize(replacement),
+            case => case.apply(replacement),
+        };
+    }
+    let (replacement_words, _) = split_words(replacement);
+    let separator = separator.map(String::from).unwrap_or_default();
+    replacement_words
+        .iter()
+        .enumerate()
+        .map(|(index, word)| cases[index.min(cases.len() - 1)].apply(word))
+        .collect::<Vec<String>>()
+        .join(&separator)
+}
+
+/// Returns the case-insensitive regular expression pattern of the `find`
+/// pattern.  Identifiers of more than one word (e.g., `widget_id`,
+/// `widget-id`, or `widgetId`) match the snake, kebab, and camel case
+/// variants of the identifier.
+pub(crate) fn case_insensitive_pattern(find: &str) -> String {
+    let is_identifier = !find.is_empty()
+        && find
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == ' ');
+    let (words, _) = split_words(find);
+    if is_identifier && words.len() > 1 {
+        format!("(?i){}", words.join("[-_ ]?"))
+    } else {
+        format!("(?i){}", find)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words("widget"), (vec!["widget"], None));
+        assert_eq!(split_words("widget_id"), (vec!["widget", "id"], Some('_')));
+        assert_eq!(split_words("widget-id"), (vec!["widget", "id"], Some('-')));
+        assert_eq!(split_words("widgetId"), (vec!["widget", "Id"], None));
+        assert_eq!(split_words("HTTPServer2"), (vec!["HTTP", "Server2"], None));
+        assert_eq!(split_words("WIDGET_ID"), (vec!["WIDGET", "ID"], Some('_')));
+    }
+
+    #[test]
+    fn test_preserve_case_single_word() {
+        assert_eq!(preserve_case("widget", "gadget"), "gadget");
+        assert_eq!(preserve_case("widget", "Gadget"), "gadget");
+        assert_eq!(preserve_case("Widget", "gadget"), "Gadget");
+        assert_eq!(preserve_case("WIDGET", "gadget"), "GADGET");
+        assert_eq!(preserve_case("wIdGeT", "gadget"), "gadget");
+        assert_eq!(preserve_case("123", "gadget"), "gadget");
+    }
+
+    #[test]
+    fn test_preserve_case_identifier_variants() {
+        assert_eq!(preserve_case("widget_id", "gadget_name"), "gadget_name");
+        assert_eq!(preserve_case("widget_id", "gadgetName"), "gadget_name");
+        assert_eq!(preserve_case("WIDGET_ID", "gadget_name"), "GADGET_NAME");
+        assert_eq!(preserve_case("WIDGET_ID", "gadgetName"), "GADGET_NAME");
+        assert_eq!(preserve_case("widget-id", "gadget_name"), "gadget-name");
+        assert_eq!(preserve_case("widgetId", "gadget_name"), "gadgetName");
+        assert_eq!(preserve_case("WidgetId", "gadget_name"), "GadgetName");
+        assert_eq!(preserve_case("Widget_Id", "gadget_name_x"), "Gadget_Name_X");
+        assert_eq!(preserve_case("WidgetId", "thing"), "Thing");
+    }
+
+    #[test]
+    fn test_case_insensitive_pattern() {
+        assert_eq!(case_insensitive_pattern("widget"), "(?i)widget");
+        assert_eq!(case_insensitive_pattern("widget_id"), "(?i)widget[-_ ]?id");
+        assert_eq!(case_insensitive_pattern("widgetId"), "(?i)widget[-_ ]?Id");
+        assert_eq!(case_insensitive_pattern(r"wid(get)\b"), r"(?i)wid(get)\b");
+    }
+}
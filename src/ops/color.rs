@@ -0,0 +1,274 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use colored::Color;
+
+/// Color output choice defined with the `--color` option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(anyhow!("invalid color choice '{}'", s)),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Returns a boolean that indicates whether color output is used.
+    /// The `auto` choice disables color when the `NO_COLOR` environment
+    /// variable is defined, enables color when the `CLICOLOR_FORCE`
+    /// environment variable is defined with a value other than `0`, and
+    /// otherwise uses color when `CLICOLOR` is not `0` and the standard
+    /// output stream is a terminal.
+    pub(crate) fn use_color(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                if env_is_set("NO_COLOR") {
+                    false
+                } else if env_is_set("CLICOLOR_FORCE") && !env_is_value("CLICOLOR_FORCE", "0") {
+                    true
+                } else if env_is_value("CLICOLOR", "0") {
+                    false
+                } else {
+                    io::stdout().is_terminal()
+                }
+            }
+        }
+    }
+}
+
+fn env_is_set(key: &str) -> bool {
+    matches!(env::var_os(key), Some(value) if !value.is_empty())
+}
+
+fn env_is_value(key: &str, expected: &str) -> bool {
+    matches!(env::var(key), Ok(value) if value == expected)
+}
+
+/// A terminal text style with an optional foreground color and
+/// text attributes.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Style {
+    fg: Option<Color>,
+    bold: bool,
+    dimmed: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl Style {
+    fn fg(color: Color) -> Self {
+        Self {
+            fg: Some(color),
+            ..Self::default()
+        }
+    }
+
+    /// Updates the style with a color name (e.g., `red`, `bright blue`),
+    /// a text attribute (`bold`, `dimmed`, `italic`, `underline`), or
+    /// `none` to clear the style.
+    fn update(&mut self, spec: &str) -> Result<()> {
+        match spec {
+            "none" => *self = Self::default(),
+            "bold" => self.bold = true,
+            "dimmed" => self.dimmed = true,
+            "italic" => self.italic = true,
+            "underline" => self.underline = true,
+            color => {
+                self.fg = Some(
+                    color
+                        .parse::<Color>()
+                        .map_err(|_| anyhow!("invalid color style '{}'", color))?,
+                )
+            }
+        }
+        Ok(())
+    }
+
+    fn escape_codes(&self) -> Vec<String> {
+        let mut codes = Vec::new();
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if self.dimmed {
+            codes.push("2".to_string());
+        }
+        if self.italic {
+            codes.push("3".to_string());
+        }
+        if self.underline {
+            codes.push("4".to_string());
+        }
+        if let Some(fg) = self.fg {
+            codes.push(fg.to_fg_str().to_string());
+        }
+        codes
+    }
+}
+
+/// The color styles of the report output elements.  Styles are only
+/// applied when color output is enabled.  The diff styles are not
+/// user-defined.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ColorStyles {
+    enabled: bool,
+    path: Style,
+    line: Style,
+    matched: Style,
+    separator: Style,
+    added: Style,
+    removed: Style,
+    hunk: Style,
+}
+
+impl ColorStyles {
+    /// Returns the default `ColorStyles` updated with the `specs` color
+    /// style definitions in order.  The definition format is
+    /// `TYPE:STYLE[,STYLE...]` where `TYPE` is one of `path`, `line`,
+    /// `match`, or `separator`.
+    pub(crate) fn new(enabled: bool, specs: &[String]) -> Result<Self> {
+        let mut styles = Self {
+            enabled,
+            path: Style::default(),
+            line: Style::fg(Color::Green),
+            matched: Style::fg(Color::Red),
+            separator: Style {
+                bold: true,
+                dimmed: true,
+                ..Style::default()
+            },
+            added: Style::fg(Color::Green),
+            removed: Style::fg(Color::Red),
+            hunk: Style::fg(Color::Cyan),
+        };
+        for spec in specs {
+            let (element, values) = spec.split_once(':').ok_or_else(|| {
+                anyhow!(
+                    "invalid color style definition '{}', expected TYPE:STYLE",
+                    spec
+                )
+            })?;
+            let style = match element {
+                "path" => &mut styles.path,
+                "line" => &mut styles.line,
+                "match" => &mut styles.matched,
+                "separator" => &mut styles.separator,
+                _ => return Err(anyhow!("invalid color style type '{}'", element)),
+            };
+            for value in values.split(',') {
+                style.update(value.trim())?;
+            }
+        }
+        Ok(styles)
+    }
+
+    pub(crate) fn path(&self, text: &str) -> String {
+        self.paint(&self.path, text)
+    }
+
+    pub(crate) fn line(&self, text: &str) -> String {
+        self.paint(&self.line, text)
+    }
+
+    pub(crate) fn matched(&self, text: &str) -> String {
+        self.paint(&self.matched, text)
+    }
+
+    pub(crate) fn separator(&self, text: &str) -> String {
+        self.paint(&self.separator, text)
+    }
+
+    pub(crate) fn added(&self, text: &str) -> String {
+        self.paint(&self.added, text)
+    }
+
+    pub(crate) fn removed(&self, text: &str) -> String {
+        self.paint(&self.removed, text)
+    }
+
+    pub(crate) fn hunk(&self, text: &str) -> String {
+        self.paint(&self.hunk, text)
+    }
+
+    fn paint(&self, style: &Style, text: &str) -> String {
+        let codes = style.escape_codes();
+        if !self.enabled || codes.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1B[{}m{}\x1B[0m", codes.join(";"), text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_choice_from_str() {
+        assert_eq!("auto".parse::<ColorChoice>().unwrap(), ColorChoice::Auto);
+        assert_eq!(
+            "always".parse::<ColorChoice>().unwrap(),
+            ColorChoice::Always
+        );
+        assert_eq!("never".parse::<ColorChoice>().unwrap(), ColorChoice::Never);
+        assert!("bogus".parse::<ColorChoice>().is_err());
+    }
+
+    #[test]
+    fn test_color_choice_explicit() {
+        assert!(ColorChoice::Always.use_color());
+        assert!(!ColorChoice::Never.use_color());
+    }
+
+    #[test]
+    fn test_color_styles_default() {
+        let styles = ColorStyles::new(true, &[]).unwrap();
+        assert_eq!(styles.path("a.txt"), "a.txt");
+        assert_eq!(styles.line("1:0-4"), "\x1B[32m1:0-4\x1B[0m");
+        assert_eq!(styles.matched("test"), "\x1B[31mtest\x1B[0m");
+        assert_eq!(styles.separator("["), "\x1B[1;2m[\x1B[0m");
+    }
+
+    #[test]
+    fn test_color_styles_disabled() {
+        let styles = ColorStyles::new(false, &[]).unwrap();
+        assert_eq!(styles.line("1:0-4"), "1:0-4");
+        assert_eq!(styles.matched("test"), "test");
+    }
+
+    #[test]
+    fn test_color_styles_custom() {
+        let specs = vec![
+            "path:bright blue,underline".to_string(),
+            "match:none".to_string(),
+            "match:yellow, bold".to_string(),
+            "separator:none".to_string(),
+        ];
+        let styles = ColorStyles::new(true, &specs).unwrap();
+        assert_eq!(styles.path("a.txt"), "\x1B[4;94ma.txt\x1B[0m");
+        assert_eq!(styles.matched("test"), "\x1B[1;33mtest\x1B[0m");
+        assert_eq!(styles.separator("["), "[");
+    }
+
+    #[test]
+    fn test_color_styles_invalid_definitions() {
+        assert!(ColorStyles::new(true, &["path".to_string()]).is_err());
+        assert!(ColorStyles::new(true, &["bogus:red".to_string()]).is_err());
+        assert!(ColorStyles::new(true, &["path:bogus".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use similar::{ChangeTag, TextDiff};
+
+use crate::ops::color::ColorStyles;
+
+/// Returns the unified diff of the `old` and `new` file contents with
+/// `context` lines of context around each change.  The `path` file
+/// path is used in the diff header.  An empty string is returned when
+/// the contents are the same.
+pub(crate) fn unified_diff(
+    path: &Path,
+    old: &str,
+    new: &str,
+    context: usize,
+    styles: &ColorStyles,
+) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let mut udiff = diff.unified_diff();
+    udiff.context_radius(context);
+    let mut hunks = udiff.iter_hunks().peekable();
+    if hunks.peek().is_none() {
+        return String::new();
+    }
+    let mut report = String::new();
+    let path = path.display();
+    report.push_str(&styles.separator(&format!("--- {}", path)));
+    report.push('\n');
+    report.push_str(&styles.separator(&format!("+++ {}", path)));
+    report.push('\n');
+    for hunk in hunks {
+        report.push_str(&styles.hunk(&hunk.header().to_string()));
+        report.push('\n');
+        for change in hunk.iter_changes() {
+            let value = change.to_string_lossy();
+            let line = value.trim_end_matches(&['\r', '\n'][..]);
+            let styled = match change.tag() {
+                ChangeTag::Delete => styles.removed(&format!("-{}", line)),
+                ChangeTag::Insert => styles.added(&format!("+{}", line)),
+                ChangeTag::Equal => format!(" {}", line),
+            };
+            report.push_str(&styled);
+            report.push('\n');
+            if change.missing_newline() {
+                report.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let styles = ColorStyles::new(false, &[]).unwrap();
+        let old = "a\nb\nc\nd\ne\n";
+        let new = "a\nB\nc\nd\ne\n";
+        assert_eq!(
+            unified_diff(Path::new("a.txt"), old, new, 1, &styles),
+            "--- a.txt\n+++ a.txt\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_missing_newline() {
+        let styles = ColorStyles::new(false, &[]).unwrap();
+        assert_eq!(
+            unified_diff(Path::new("a.txt"), "a", "b", 3, &styles),
+            "--- a.txt\n+++ a.txt\n@@ -1 +1 @@\n-a\n\\ No newline at end of file\n+b\n\\ No newline at end of file\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_color() {
+        let styles = ColorStyles::new(true, &[]).unwrap();
+        let diff = unified_diff(Path::new("a.txt"), "a\n", "b\n", 3, &styles);
+        assert!(diff.contains("\x1B[36m@@ -1 +1 @@\x1B[0m\n"));
+        assert!(diff.contains("\x1B[31m-a\x1B[0m\n"));
+        assert!(diff.contains("\x1B[32m+b\x1B[0m\n"));
+    }
+
+    #[test]
+    fn test_unified_diff_unchanged() {
+        let styles = ColorStyles::new(false, &[]).unwrap();
+        assert!(unified_diff(Path::new("a.txt"), "a\n", "a\n", 3, &styles).is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use regex::Captures;
+
+use crate::ops::pattern::GroupRef;
+
+/// An external replacement command.  Each match (or the `group` capture
+/// group of each match) is written to the standard input stream of the
+/// command and the standard output stream is the replacement.  The
+/// results of identical inputs are cached for the run.
+#[derive(Debug)]
+pub(crate) struct ExternalCommand {
+    pub(crate) command: String,
+    pub(crate) group: GroupRef,
+    timeout: Duration,
+    cache: RefCell<HashMap<String, Result<String, String>>>,
+}
+
+impl ExternalCommand {
+    /// Returns an `ExternalCommand` for the `command` shell command line
+    /// that is stopped when it runs longer than the `timeout` duration.
+    pub(crate) fn new(command: String, group: GroupRef, timeout: Duration) -> Self {
+        Self {
+            command,
+            group,
+            timeout,
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the command replacement of the `caps` match.  A group that
+    /// does not participate in the match is an empty input.
+    pub(crate) fn replacement(&self, caps: &Captures) -> Result<String> {
+        let input = match &self.group {
+            GroupRef::Index(index) => caps.get(*index),
+            GroupRef::Name(name) => caps.name(name),
+        }
+        .map_or("", |mat| mat.as_str());
+        if let Some(result) = self.cache.borrow().get(input) {
+            return result.clone().map_err(|error| anyhow!(error));
+        }
+        let result = self.run(input);
+        self.cache
+            .borrow_mut()
+            .insert(input.to_string(), result.clone());
+        result.map_err(|error| anyhow!(error))
+    }
+
+    /// Runs the command with the `input` string on the standard input
+    /// stream and returns the standard output stream without a single
+    /// trailing line ending.  Failures are returned as error messages so
+    /// that they can be cached.
+    fn run(&self, input: &str) -> Result<String, String> {
+        let mut child = shell(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| format!("unable to run command '{}': {}", self.command, error))?;
+        // the streams are written and read on separate threads so that
+        // large inputs and outputs do not block the command
+        let mut stdin = child.stdin.take().unwrap();
+        let input = input.to_string();
+        let stdin = thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let stdout = read_stream(child.stdout.take().unwrap());
+        let stderr = read_stream(child.stderr.take().unwrap());
+        let start = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) if start.elapsed() >= self.timeout => {
+                    // the processes that the command started are stopped
+                    // with it so that the streams are closed
+                    let _ = kill(&mut child);
+                    let _ = child.wait();
+                    break Err(format!(
+                        "command '{}' timed out after {} second(s)",
+                        self.command,
+                        self.timeout.as_secs()
+                    ));
+                }
+                Ok(None) => thread::sleep(Duration::from_millis(5)),
+                Err(error) => {
+                    let _ = kill(&mut child);
+                    let _ = child.wait();
+                    break Err(format!("command '{}' failed: {}", self.command, error));
+                }
+            }
+        };
+        let _ = stdin.join();
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+        let status = status?;
+        if !status.success() {
+            let message = String::from_utf8_lossy(&stderr);
+            return Err(match message.trim() {
+                "" => format!("command '{}' failed with {}", self.command, status),
+                message => format!(
+                    "command '{}' failed with {}: {}",
+                    self.command, status, message
+                ),
+            });
+        }
+        let output = String::from_utf8(stdout)
+            .map_err(|_| format!("command '{}' output is not valid UTF-8", self.command))?;
+        let output = output.strip_suffix('\n').unwrap_or(&output);
+        Ok(output.strip_suffix('\r').unwrap_or(output).to_string())
+    }
+}
+
+/// Returns the platform shell command that runs the `command` line.
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// Returns the platform shell command that runs the `command` line.  The
+/// shell runs in a new process group with the processes that it starts.
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command).process_group(0);
+    shell
+}
+
+/// Stops the `child` command.
+#[cfg(windows)]
+fn kill(child: &mut Child) -> io::Result<()> {
+    child.kill()
+}
+
+/// Stops the `child` command and the processes in the process group of
+/// the command.
+#[cfg(not(windows))]
+fn kill(child: &mut Child) -> io::Result<()> {
+    use std::convert::TryFrom;
+
+    // the process group id is the process id of the group leader.  The
+    // child is not waited on yet so that the id is not reused.
+    let pgid = libc::pid_t::try_from(child.id())
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    // SAFETY: kill has no memory safety requirements
+    match unsafe { libc::kill(-pgid, libc::SIGKILL) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Returns a thread that reads the `stream` to the end.
+fn read_stream(mut stream: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut contents = Vec::new();
+        let _ = stream.read_to_end(&mut contents);
+        contents
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn command(command: &str, group: usize, timeout: u64) -> ExternalCommand {
+        ExternalCommand::new(
+            command.to_string(),
+            GroupRef::Index(group),
+            Duration::from_secs(timeout),
+        )
+    }
+
+    #[test]
+    fn test_external_command_replacement() {
+        let re = Regex::new(r"v(\d)").unwrap();
+        let caps = re.captures("v1").unwrap();
+        let upper = command("tr a-z A-Z", 0, 10);
+        assert_eq!(upper.replacement(&caps).unwrap(), "V1");
+        let group = command("sed 's/1/one/'", 1, 10);
+        assert_eq!(group.replacement(&caps).unwrap(), "one");
+        // only a single trailing line ending is removed
+        let lines = command("printf 'a\\n\\n'", 0, 10);
+        assert_eq!(lines.replacement(&caps).unwrap(), "a\n");
+    }
+
+    #[test]
+    fn test_external_command_cache() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let counter = tmpdir.path().join("runs");
+        let cached = command(&format!("echo run >> '{}'; cat", counter.display()), 0, 10);
+        let re = Regex::new(r"\w+").unwrap();
+        for text in &["a", "b", "a", "a"] {
+            let caps = re.captures(text).unwrap();
+            assert_eq!(cached.replacement(&caps).unwrap(), *text);
+        }
+        assert_eq!(
+            std::fs::read_to_string(&counter).unwrap().lines().count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_external_command_failures() {
+        let re = Regex::new(r"\w+").unwrap();
+        let caps = re.captures("a").unwrap();
+        let error = command("echo bad >&2; exit 3", 0, 10)
+            .replacement(&caps)
+            .unwrap_err();
+        assert!(error.to_string().contains("bad"));
+        let error = command("sleep 5", 0, 1).replacement(&caps).unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+        // the processes that the command starts are stopped with it
+        let start = Instant::now();
+        let error = command("(sleep 30; echo late); echo done", 0, 1)
+            .replacement(&caps)
+            .unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        let error = command("printf '\\377'", 0, 10)
+            .replacement(&caps)
+            .unwrap_err();
+        assert!(error.to_string().contains("UTF-8"));
+    }
+}
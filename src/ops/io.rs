@@ -1,5 +1,12 @@
-use std::path::Path;
+use std::fs::{self, File, FileTimes, OpenOptions};
+use std::hash::Hasher;
+use std::io::{self, BufReader, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
+use anyhow::{Context, Result};
+use fnv::FnvHasher;
+use tempfile::{Builder, TempPath};
 use walkdir::{IntoIter, WalkDir};
 
 pub(crate) fn walk<P>(
@@ -30,6 +37,240 @@ where
     wd.into_iter()
 }
 
+/// The state of a file at read time.  The file size and modification
+/// time are recorded along with an optional hash of the file contents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FileSnapshot {
+    len: u64,
+    modified: Option<SystemTime>,
+    hash: Option<u64>,
+}
+
+impl FileSnapshot {
+    /// Reads the `filepath` file and returns the file contents with the
+    /// file state at read time.  A hash of the file contents is
+    /// recorded when `hash` is `true`.
+    pub(crate) fn read(filepath: &Path, hash: bool) -> io::Result<(String, Self)> {
+        // the metadata is read first so that a change during the read
+        // is detected as a change
+        let metadata = fs::metadata(filepath)?;
+        let contents = fs::read_to_string(filepath)?;
+        let snapshot = Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            hash: if hash {
+                Some(content_hash(contents.as_bytes()))
+            } else {
+                None
+            },
+        };
+        Ok((contents, snapshot))
+    }
+
+    /// Returns the state of the `filepath` file without a read of the
+    /// file contents into memory.  A hash of the file contents is
+    /// recorded when `hash` is `true`.
+    pub(crate) fn stat(filepath: &Path, hash: bool) -> io::Result<Self> {
+        let metadata = fs::metadata(filepath)?;
+        Ok(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            hash: if hash {
+                Some(file_hash(filepath)?)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Returns a boolean that indicates whether the `filepath` file was
+    /// modified or removed after the snapshot.
+    pub(crate) fn is_changed(&self, filepath: &Path) -> io::Result<bool> {
+        let metadata = match fs::metadata(filepath) {
+            Ok(metadata) => metadata,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(true),
+            Err(error) => return Err(error),
+        };
+        if metadata.len() != self.len || metadata.modified().ok() != self.modified {
+            return Ok(true);
+        }
+        match self.hash {
+            Some(hash) => Ok(file_hash(filepath)? != hash),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Returns the 64-bit FNV-1a hash of the `contents` bytes.  The hash
+/// is stable across builds and platforms.
+pub(crate) fn content_hash(contents: &[u8]) -> u64 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(contents);
+    hasher.finish()
+}
+
+/// Returns the 64-bit FNV-1a hash of the `filepath` file contents.  The
+/// file is read in blocks and the hash is the `content_hash` of the
+/// contents.
+pub(crate) fn file_hash(filepath: &Path) -> io::Result<u64> {
+    let mut reader = BufReader::new(File::open(filepath)?);
+    let mut hasher = FnvHasher::default();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        match reader.read(&mut buffer)? {
+            0 => return Ok(hasher.finish()),
+            read => hasher.write(&buffer[..read]),
+        }
+    }
+}
+
+/// Atomically replaces the contents of the `filepath` file with the
+/// `contents` bytes.  The contents are written to a temporary file in
+/// the same directory, the file permissions, ownership, and extended
+/// attributes are copied to the temporary file, the temporary file is
+/// synced to disk, and the temporary file is renamed over the original
+/// file.  The original file modification time is preserved when
+/// `preserve_mtime` is `true`.  Symbolic links are resolved so that the
+/// link target is written.
+pub(crate) fn atomic_write(filepath: &Path, contents: &[u8], preserve_mtime: bool) -> Result<()> {
+    let mut file = AtomicFile::create(filepath)?;
+    file.file()?.write_all(contents)?;
+    file.persist(preserve_mtime)
+}
+
+/// A temporary file in the directory of a file that atomically replaces
+/// the file when it is persisted.  The temporary file is removed when it
+/// is dropped without a persist.
+pub(crate) struct AtomicFile {
+    filepath: PathBuf,
+    metadata: fs::Metadata,
+    /// The open temporary file.  Closed files are reopened on access.
+    file: Option<File>,
+    tmppath: TempPath,
+}
+
+impl AtomicFile {
+    /// Creates the temporary file of the `filepath` file.  Symbolic
+    /// links are resolved so that the link target is replaced.
+    pub(crate) fn create(filepath: &Path) -> Result<Self> {
+        let filepath = fs::canonicalize(filepath)?;
+        let metadata = fs::metadata(&filepath)?;
+        let dirpath = filepath.parent().unwrap_or_else(|| Path::new("."));
+        // hidden temporary files are skipped in default traversals when a
+        // failed write leaves one behind
+        let (file, tmppath) = Builder::new()
+            .prefix(".recurse-")
+            .suffix(".tmp")
+            .tempfile_in(dirpath)
+            .with_context(|| format!("unable to create temporary file in '{}'", dirpath.display()))?
+            .into_parts();
+        Ok(Self {
+            filepath,
+            metadata,
+            file: Some(file),
+            tmppath,
+        })
+    }
+
+    /// Returns the temporary file for writes.  Writes to a closed file
+    /// are appended.
+    pub(crate) fn file(&mut self) -> io::Result<&mut File> {
+        let file = match self.file.take() {
+            Some(file) => file,
+            None => OpenOptions::new().append(true).open(&self.tmppath)?,
+        };
+        Ok(self.file.insert(file))
+    }
+
+    /// Closes the temporary file so that files that wait for a persist
+    /// do not hold open file descriptors.
+    pub(crate) fn close(&mut self) {
+        self.file = None;
+    }
+
+    /// Returns the temporary file path.
+    pub(crate) fn path(&self) -> &Path {
+        &self.tmppath
+    }
+
+    /// Copies the file permissions, ownership, and extended attributes
+    /// of the original file to the temporary file, syncs the temporary
+    /// file to disk, and renames it over the original file.  The
+    /// original file modification time is preserved when
+    /// `preserve_mtime` is `true`.
+    pub(crate) fn persist(self, preserve_mtime: bool) -> Result<()> {
+        let Self {
+            filepath,
+            metadata,
+            file,
+            tmppath,
+        } = self;
+        let file = match file {
+            Some(file) => file,
+            None => OpenOptions::new().write(true).open(&tmppath)?,
+        };
+        file.set_permissions(metadata.permissions())?;
+        copy_ownership(&file, &metadata);
+        copy_xattrs(&filepath, &tmppath);
+        if preserve_mtime {
+            file.set_times(
+                FileTimes::new()
+                    .set_accessed(metadata.accessed()?)
+                    .set_modified(metadata.modified()?),
+            )?;
+        }
+        file.sync_all()?;
+        // the temporary file is closed before the rename
+        drop(file);
+        tmppath
+            .persist(&filepath)
+            .with_context(|| format!("unable to replace file '{}'", filepath.display()))?;
+        sync_dir(filepath.parent().unwrap_or_else(|| Path::new(".")));
+        Ok(())
+    }
+}
+
+/// Copies the file owner and group to the `file` file.  Changes that
+/// are not permitted for the current user are skipped.
+#[cfg(unix)]
+fn copy_ownership(file: &File, metadata: &fs::Metadata) {
+    use std::os::unix::fs::{fchown, MetadataExt};
+    if fchown(file, Some(metadata.uid()), Some(metadata.gid())).is_err() {
+        let _ = fchown(file, None, Some(metadata.gid()));
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_file: &File, _metadata: &fs::Metadata) {}
+
+/// Copies the extended attributes of the `src` file to the `dest` file.
+/// Attributes that cannot be read or written are skipped.
+#[cfg(unix)]
+fn copy_xattrs(src: &Path, dest: &Path) {
+    if let Ok(names) = xattr::list(src) {
+        for name in names {
+            if let Ok(Some(value)) = xattr::get(src, &name) {
+                let _ = xattr::set(dest, &name, &value);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_xattrs(_src: &Path, _dest: &Path) {}
+
+/// Syncs the directory entries of the `dirpath` directory so that a
+/// rename is durable.
+#[cfg(unix)]
+fn sync_dir(dirpath: &Path) {
+    if let Ok(dir) = File::open(dirpath) {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dirpath: &Path) {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -171,4 +412,92 @@ mod tests {
         }
         assert_eq!(index, 4);
     }
+
+    #[test]
+    fn test_atomic_write() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let filepath = tmpdir.path().join("test.sh");
+        fs::write(&filepath, "echo test\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&filepath, fs::Permissions::from_mode(0o754)).unwrap();
+        }
+        let mtime = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        File::options()
+            .write(true)
+            .open(&filepath)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        atomic_write(&filepath, b"echo check\n", true).unwrap();
+        assert_eq!(fs::read_to_string(&filepath).unwrap(), "echo check\n");
+        let metadata = fs::metadata(&filepath).unwrap();
+        assert_eq!(metadata.modified().unwrap(), mtime);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o754);
+        }
+        // no temporary files are left in the directory
+        assert_eq!(fs::read_dir(tmpdir.path()).unwrap().count(), 1);
+
+        atomic_write(&filepath, b"echo again\n", false).unwrap();
+        assert_ne!(fs::metadata(&filepath).unwrap().modified().unwrap(), mtime);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_atomic_write_symlink_target() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let target = tmpdir.path().join("target.txt");
+        let link = tmpdir.path().join("link.txt");
+        fs::write(&target, "test\n").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        atomic_write(&link, b"check\n", false).unwrap();
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "check\n");
+    }
+
+    #[test]
+    fn test_file_snapshot_changes() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let filepath = tmpdir.path().join("test.txt");
+        fs::write(&filepath, "test\n").unwrap();
+        let mtime = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+        let set_mtime = || {
+            File::options()
+                .write(true)
+                .open(&filepath)
+                .unwrap()
+                .set_modified(mtime)
+                .unwrap()
+        };
+        set_mtime();
+
+        let (contents, snapshot) = FileSnapshot::read(&filepath, false).unwrap();
+        assert_eq!(contents, "test\n");
+        assert!(!snapshot.is_changed(&filepath).unwrap());
+        let (_, hashed) = FileSnapshot::read(&filepath, true).unwrap();
+        assert!(!hashed.is_changed(&filepath).unwrap());
+
+        // a same size edit that restores the modification time is only
+        // detected with the content hash
+        fs::write(&filepath, "best\n").unwrap();
+        set_mtime();
+        assert!(!snapshot.is_changed(&filepath).unwrap());
+        assert!(hashed.is_changed(&filepath).unwrap());
+
+        fs::write(&filepath, "a test\n").unwrap();
+        set_mtime();
+        assert!(snapshot.is_changed(&filepath).unwrap());
+
+        fs::remove_file(&filepath).unwrap();
+        assert!(snapshot.is_changed(&filepath).unwrap());
+    }
 }
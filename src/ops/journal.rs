@@ -0,0 +1,517 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::ops::io::file_hash;
+use crate::ops::time::UtcDateTime;
+
+/// The journal format version.
+const JOURNAL_VERSION: u32 = 1;
+
+const RUN_FILENAME: &str = "run.json";
+const FILES_FILENAME: &str = "files.jsonl";
+const UNDO_FILENAME: &str = "undo.jsonl";
+
+/// Returns the journal directory path.  The `journal_dir` path is used
+/// when it is defined.  The default is the `recurse/journal` directory
+/// in the platform user data directory (`$XDG_DATA_HOME` or
+/// `~/.local/share` on Unix platforms and `%LOCALAPPDATA%` on Windows).
+pub(crate) fn journal_dirpath(journal_dir: Option<PathBuf>) -> Result<PathBuf> {
+    match journal_dir {
+        Some(dirpath) => Ok(dirpath),
+        None => data_dirpath()
+            .map(|dirpath| dirpath.join("recurse").join("journal"))
+            .ok_or_else(|| {
+                anyhow!("unable to locate the journal directory, define it with --journal-dir")
+            }),
+    }
+}
+
+fn data_dirpath() -> Option<PathBuf> {
+    let env_path = |name| {
+        env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+    if cfg!(windows) {
+        env_path("LOCALAPPDATA").or_else(|| env_path("APPDATA"))
+    } else {
+        env_path("XDG_DATA_HOME")
+            .filter(|dirpath| dirpath.is_absolute())
+            .or_else(|| env_path("HOME").map(|home| home.join(".local").join("share")))
+    }
+}
+
+/// The description of a journaled `replace` run.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct RunManifest {
+    pub(crate) version: u32,
+    pub(crate) id: String,
+    pub(crate) timestamp: String,
+    pub(crate) cwd: PathBuf,
+    pub(crate) command: Vec<String>,
+}
+
+impl RunManifest {
+    /// Returns the command line of the run.  Arguments with characters
+    /// that are special in POSIX shells are single quoted so that the
+    /// command line can be pasted in a shell.
+    pub(crate) fn command_line(&self) -> String {
+        let mut command_line = String::from("recurse");
+        for arg in &self.command {
+            command_line.push(' ');
+            command_line.push_str(&shell_quote(arg));
+        }
+        command_line
+    }
+}
+
+/// Returns the `arg` single quoted when it is empty or has characters
+/// other than the ASCII alphanumeric characters and `-_./=:,+@%`.  Single
+/// quotes in the `arg` are written as `'\''`.
+fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', "'\\''")))
+    }
+}
+
+/// A file that was edited in a journaled run.  The original file
+/// contents are stored in the run directory and the hashes are the
+/// 64-bit FNV-1a hashes of the original and replaced file contents.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileEntry {
+    pub(crate) index: usize,
+    pub(crate) path: PathBuf,
+    pub(crate) original_hash: String,
+    pub(crate) replaced_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UndoEntry {
+    index: usize,
+}
+
+/// Returns the journal representation of the `contents` hash.
+pub(crate) fn format_hash(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+/// A journal run that records the original contents of the files that
+/// are edited in a `replace` run.  Each file is recorded before it is
+/// written so that an interrupted run can be undone.
+pub(crate) struct JournalRun {
+    id: String,
+    dirpath: PathBuf,
+    files: Cell<usize>,
+}
+
+impl JournalRun {
+    /// Creates a new run directory in the `journal_dir` directory.  The
+    /// run id is the UTC start time with a numeric suffix when runs
+    /// start in the same second.
+    pub(crate) fn create(journal_dir: &Path) -> Result<Self> {
+        fs::create_dir_all(journal_dir).with_context(|| {
+            format!(
+                "unable to create journal directory '{}'",
+                journal_dir.display()
+            )
+        })?;
+        let now = UtcDateTime::now();
+        let mut suffix = 0;
+        let (id, dirpath) = loop {
+            let id = match suffix {
+                0 => now.compact(),
+                _ => format!("{}-{}", now.compact(), suffix),
+            };
+            let dirpath = journal_dir.join(&id);
+            match fs::create_dir(&dirpath) {
+                Ok(()) => break (id, dirpath),
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => suffix += 1,
+                Err(error) => return Err(anyhow!(error)),
+            }
+        };
+        let manifest = RunManifest {
+            version: JOURNAL_VERSION,
+            id: id.clone(),
+            timestamp: now.to_string(),
+            cwd: env::current_dir().unwrap_or_default(),
+            command: env::args_os()
+                .skip(1)
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+        };
+        write_synced(
+            &dirpath.join(RUN_FILENAME),
+            serde_json::to_string_pretty(&manifest)?.as_bytes(),
+        )?;
+        Ok(Self {
+            id,
+            dirpath,
+            files: Cell::new(0),
+        })
+    }
+
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Records a copy of the `filepath` file and the hashes of the
+    /// `filepath` file and the `replaced` file contents.  The files are
+    /// copied and hashed without a read of the file contents into
+    /// memory.
+    pub(crate) fn record_file(&self, filepath: &Path, replaced: &Path) -> Result<()> {
+        let index = self.files.get();
+        let copy = self.dirpath.join(original_filename(index));
+        fs::copy(filepath, &copy)?;
+        File::open(&copy)?.sync_all()?;
+        self.append_entry(
+            filepath,
+            format_hash(file_hash(filepath)?),
+            format_hash(file_hash(replaced)?),
+        )
+    }
+
+    fn append_entry(
+        &self,
+        filepath: &Path,
+        original_hash: String,
+        replaced_hash: String,
+    ) -> Result<()> {
+        let index = self.files.get();
+        let entry = FileEntry {
+            index,
+            path: fs::canonicalize(filepath)?,
+            original_hash,
+            replaced_hash,
+        };
+        append_line(
+            &self.dirpath.join(FILES_FILENAME),
+            &serde_json::to_string(&entry)?,
+        )?;
+        self.files.set(index + 1);
+        Ok(())
+    }
+
+    /// Completes the run and removes the oldest runs so that the journal
+    /// directory keeps at most `keep` runs.  The run directory is removed
+    /// when no files were edited.
+    pub(crate) fn finish(self, keep: usize) -> Result<()> {
+        if self.files.get() == 0 {
+            fs::remove_dir_all(&self.dirpath)?;
+        }
+        match self.dirpath.parent() {
+            Some(journal_dir) => prune_runs(journal_dir, keep),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A journaled run read from the journal directory.
+#[derive(Debug)]
+pub(crate) struct RunRecord {
+    pub(crate) manifest: RunManifest,
+    pub(crate) files: Vec<FileEntry>,
+    restored: Vec<bool>,
+    dirpath: PathBuf,
+}
+
+impl RunRecord {
+    /// Reads the run in the `dirpath` run directory.
+    pub(crate) fn read(dirpath: &Path) -> Result<Self> {
+        let manifest_path = dirpath.join(RUN_FILENAME);
+        let manifest: RunManifest = serde_json::from_str(
+            &fs::read_to_string(&manifest_path)
+                .with_context(|| format!("unable to read '{}'", manifest_path.display()))?,
+        )
+        .with_context(|| format!("invalid journal run '{}'", manifest_path.display()))?;
+        // a run that is interrupted while a line is appended leaves a
+        // partial line.  The file of a partial entry was not written.
+        let files: Vec<FileEntry> = read_lines(&dirpath.join(FILES_FILENAME))?
+            .iter()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let mut restored = vec![false; files.len()];
+        for line in read_lines(&dirpath.join(UNDO_FILENAME))? {
+            if let Ok(UndoEntry { index }) = serde_json::from_str(&line) {
+                if let Some(flag) = restored.get_mut(index) {
+                    *flag = true;
+                }
+            }
+        }
+        Ok(Self {
+            manifest,
+            files,
+            restored,
+            dirpath: dirpath.to_path_buf(),
+        })
+    }
+
+    pub(crate) fn id(&self) -> &str {
+        &self.manifest.id
+    }
+
+    pub(crate) fn is_restored(&self, index: usize) -> bool {
+        self.restored[index]
+    }
+
+    /// Returns the number of files that were restored with an undo.
+    pub(crate) fn restored_count(&self) -> usize {
+        self.restored.iter().filter(|restored| **restored).count()
+    }
+
+    /// Returns a boolean that indicates whether all files of the run
+    /// were restored.
+    pub(crate) fn is_undone(&self) -> bool {
+        self.restored_count() == self.files.len()
+    }
+
+    /// Returns the original contents of the `entry` file.
+    pub(crate) fn original(&self, entry: &FileEntry) -> Result<Vec<u8>> {
+        let filepath = self.dirpath.join(original_filename(entry.index));
+        fs::read(&filepath).with_context(|| format!("unable to read '{}'", filepath.display()))
+    }
+
+    /// Records the restore of the `index` file.
+    pub(crate) fn mark_restored(&mut self, index: usize) -> Result<()> {
+        append_line(
+            &self.dirpath.join(UNDO_FILENAME),
+            &serde_json::to_string(&UndoEntry { index })?,
+        )?;
+        self.restored[index] = true;
+        Ok(())
+    }
+}
+
+/// Returns the runs in the `journal_dir` directory in run order.
+pub(crate) fn read_runs(journal_dir: &Path) -> Result<Vec<RunRecord>> {
+    run_dirpaths(journal_dir)?
+        .iter()
+        .map(|dirpath| RunRecord::read(dirpath))
+        .collect()
+}
+
+/// Returns the replaced contents hash of the most recent journaled edit
+/// of the `filepath` file from the contents with the `original_hash` hash.
+/// The edited file paths are resolved against the run working directory.
+pub(crate) fn replaced_hash(
+    journal_dir: &Path,
+    filepath: &Path,
+    original_hash: &str,
+) -> Result<Option<String>> {
+    let filepath = filepath.canonicalize()?;
+    for run in read_runs(journal_dir)?.iter().rev() {
+        let entry = run.files.iter().rev().find(|entry| {
+            entry.original_hash == original_hash
+                && run
+                    .manifest
+                    .cwd
+                    .join(&entry.path)
+                    .canonicalize()
+                    .is_ok_and(|path| path == filepath)
+        });
+        if let Some(entry) = entry {
+            return Ok(Some(entry.replaced_hash.clone()));
+        }
+    }
+    Ok(None)
+}
+
+/// Removes the oldest runs in the `journal_dir` directory so that at
+/// most `keep` runs remain.
+pub(crate) fn prune_runs(journal_dir: &Path, keep: usize) -> Result<()> {
+    let dirpaths = run_dirpaths(journal_dir)?;
+    for dirpath in &dirpaths[..dirpaths.len().saturating_sub(keep)] {
+        fs::remove_dir_all(dirpath)
+            .with_context(|| format!("unable to remove journal run '{}'", dirpath.display()))?;
+    }
+    Ok(())
+}
+
+/// Returns the run directory paths in the `journal_dir` directory in run
+/// order.
+fn run_dirpaths(journal_dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(journal_dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(anyhow!(error)),
+    };
+    let mut dirpaths = Vec::new();
+    for entry in entries {
+        let dirpath = entry?.path();
+        if dirpath.join(RUN_FILENAME).is_file() {
+            dirpaths.push(dirpath);
+        }
+    }
+    dirpaths.sort_by_cached_key(|dirpath| {
+        run_order_key(&dirpath.file_name().unwrap_or_default().to_string_lossy())
+    });
+    Ok(dirpaths)
+}
+
+/// Returns the run with the `id` run id in the `journal_dir` directory.
+pub(crate) fn read_run(journal_dir: &Path, id: &str) -> Result<RunRecord> {
+    let dirpath = journal_dir.join(id);
+    if id.contains(['/', '\\']) || !dirpath.join(RUN_FILENAME).is_file() {
+        return Err(anyhow!("no such run '{}'", id));
+    }
+    RunRecord::read(&dirpath)
+}
+
+/// Returns the sort key of the `id` run id.  Runs are ordered by start
+/// time and then by the numeric suffix.
+fn run_order_key(id: &str) -> (String, usize) {
+    match id.split_once('-') {
+        Some((time, suffix)) => (time.to_string(), suffix.parse().unwrap_or(0)),
+        None => (id.to_string(), 0),
+    }
+}
+
+fn original_filename(index: usize) -> String {
+    format!("{}.orig", index)
+}
+
+fn write_synced(filepath: &Path, contents: &[u8]) -> Result<()> {
+    let mut file = File::create(filepath)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn append_line(filepath: &Path, line: &str) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(filepath)?;
+    writeln!(file, "{}", line)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn read_lines(filepath: &Path) -> Result<Vec<String>> {
+    match fs::read_to_string(filepath) {
+        Ok(contents) => Ok(contents.lines().map(String::from).collect()),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(error) => Err(anyhow!(error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::io::content_hash;
+
+    #[test]
+    fn test_journal_run_record_and_read() {
+        let journal_dir = tempfile::tempdir().unwrap();
+        let workdir = tempfile::tempdir().unwrap();
+        let filepath = workdir.path().join("test.txt");
+        let replaced = workdir.path().join("replaced.txt");
+        fs::write(&filepath, "a test\n").unwrap();
+        fs::write(&replaced, "a check\n").unwrap();
+
+        let first = JournalRun::create(journal_dir.path()).unwrap();
+        let second = JournalRun::create(journal_dir.path()).unwrap();
+        assert_ne!(first.id(), second.id());
+        first.record_file(&filepath, &replaced).unwrap();
+        fs::rename(&replaced, &filepath).unwrap();
+        first.finish(10).unwrap();
+        // runs without edits are removed
+        second.finish(10).unwrap();
+
+        let mut runs = read_runs(journal_dir.path()).unwrap();
+        assert_eq!(runs.len(), 1);
+        let run = &mut runs[0];
+        assert_eq!(run.manifest.version, JOURNAL_VERSION);
+        assert_eq!(run.files.len(), 1);
+        let entry = run.files[0].clone();
+        assert_eq!(entry.path, fs::canonicalize(&filepath).unwrap());
+        assert_eq!(entry.original_hash, format_hash(content_hash(b"a test\n")));
+        assert_eq!(entry.replaced_hash, format_hash(content_hash(b"a check\n")));
+        assert_eq!(run.original(&entry).unwrap(), b"a test\n");
+        assert!(!run.is_undone());
+
+        run.mark_restored(0).unwrap();
+        let run = read_run(journal_dir.path(), runs[0].id()).unwrap();
+        assert!(run.is_restored(0));
+        assert!(run.is_undone());
+        assert!(read_run(journal_dir.path(), "bogus").is_err());
+    }
+
+    #[test]
+    fn test_journal_missing_directory() {
+        let journal_dir = tempfile::tempdir().unwrap();
+        assert!(read_runs(&journal_dir.path().join("bogus"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_run_order_and_command_line() {
+        assert!(run_order_key("20211018T153012Z-2") < run_order_key("20211018T153012Z-10"));
+        assert!(run_order_key("20211018T153012Z") < run_order_key("20211018T153012Z-1"));
+        let manifest = RunManifest {
+            version: JOURNAL_VERSION,
+            id: "20211018T153012Z".to_string(),
+            timestamp: "2021-10-18T15:30:12Z".to_string(),
+            cwd: PathBuf::from("."),
+            command: vec![
+                "replace".to_string(),
+                "-f".to_string(),
+                "a b".to_string(),
+                "-r".to_string(),
+                "".to_string(),
+                ".".to_string(),
+            ],
+        };
+        assert_eq!(manifest.command_line(), "recurse replace -f 'a b' -r '' .");
+        let manifest = RunManifest {
+            command: vec![
+                "replace".to_string(),
+                "-f".to_string(),
+                "it's".to_string(),
+                "-r".to_string(),
+                "$HOME\\*;`x`\"".to_string(),
+                "--glob=*.rs".to_string(),
+            ],
+            ..manifest
+        };
+        assert_eq!(
+            manifest.command_line(),
+            r#"recurse replace -f 'it'\''s' -r '$HOME\*;`x`"' '--glob=*.rs'"#
+        );
+    }
+
+    #[test]
+    fn test_journal_run_finish_prunes_runs() {
+        let journal_dir = tempfile::tempdir().unwrap();
+        let workdir = tempfile::tempdir().unwrap();
+        let filepath = workdir.path().join("test.txt");
+        fs::write(&filepath, "a test\n").unwrap();
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let run = JournalRun::create(journal_dir.path()).unwrap();
+            ids.push(run.id().to_string());
+            run.record_file(&filepath, &filepath).unwrap();
+            run.finish(2).unwrap();
+        }
+        // the oldest runs are removed
+        let runs = read_runs(journal_dir.path()).unwrap();
+        let kept: Vec<&str> = runs.iter().map(|run| run.id()).collect();
+        assert_eq!(kept, vec![ids[1].as_str(), ids[2].as_str()]);
+        assert!(!journal_dir.path().join(&ids[0]).exists());
+
+        prune_runs(journal_dir.path(), 1).unwrap();
+        assert_eq!(read_runs(journal_dir.path()).unwrap()[0].id(), ids[2]);
+        // missing journal directories have no runs to remove
+        prune_runs(&journal_dir.path().join("missing"), 1).unwrap();
+    }
+}
@@ -1,2 +1,20 @@
+pub(crate) mod backup;
+pub(crate) mod case;
+pub(crate) mod color;
+pub(crate) mod diff;
+pub(crate) mod external;
 pub(crate) mod io;
+pub(crate) mod journal;
+pub(crate) mod newline;
 pub(crate) mod path;
+pub(crate) mod pattern;
+pub(crate) mod prompt;
+pub(crate) mod query;
+pub(crate) mod replace_rules;
+pub(crate) mod rules;
+pub(crate) mod scope;
+pub(crate) mod stream;
+pub(crate) mod substitution;
+pub(crate) mod template;
+pub(crate) mod time;
+pub(crate) mod transform;
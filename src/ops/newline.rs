@@ -0,0 +1,301 @@
+use std::borrow::Cow;
+use std::io::{self, ErrorKind, Read};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+const BOM: char = '\u{feff}';
+
+/// The line ending style of a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LineEnding {
+    /// `\n` line endings.  Files without line endings are LF files.
+    Lf,
+    /// `\r\n` line endings
+    Crlf,
+    /// Both `\n` and `\r\n` line endings
+    Mixed,
+}
+
+impl LineEnding {
+    /// Returns the line ending style of a text with `lf` `\n` line
+    /// endings and `crlf` `\r\n` line endings.
+    fn from_counts(lf: usize, crlf: usize) -> Self {
+        match (lf, crlf) {
+            (_, 0) => LineEnding::Lf,
+            (0, _) => LineEnding::Crlf,
+            _ => LineEnding::Mixed,
+        }
+    }
+}
+
+/// The line ending style of replaced files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LineEndingChoice {
+    /// The line ending style of the original file
+    Preserve,
+    Lf,
+    Crlf,
+}
+
+impl FromStr for LineEndingChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "preserve" => Ok(LineEndingChoice::Preserve),
+            "lf" => Ok(LineEndingChoice::Lf),
+            "crlf" => Ok(LineEndingChoice::Crlf),
+            _ => Err(anyhow!("invalid line ending style '{}'", s)),
+        }
+    }
+}
+
+/// The final newline state of replaced files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FinalNewline {
+    /// The final newline state of the original file
+    Preserve,
+    Add,
+    Remove,
+}
+
+impl FromStr for FinalNewline {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "preserve" => Ok(FinalNewline::Preserve),
+            "add" => Ok(FinalNewline::Add),
+            "remove" => Ok(FinalNewline::Remove),
+            _ => Err(anyhow!("invalid final newline choice '{}'", s)),
+        }
+    }
+}
+
+/// The byte order mark, line ending style, and final newline state of
+/// a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct TextFormat {
+    pub(crate) bom: bool,
+    pub(crate) line_ending: LineEnding,
+    pub(crate) final_newline: bool,
+}
+
+impl TextFormat {
+    /// Returns the `TextFormat` of the `text` file contents.
+    pub(crate) fn detect(text: &str) -> Self {
+        let crlf = text.matches("\r\n").count();
+        let lf = text.matches('\n').count() - crlf;
+        Self {
+            bom: text.starts_with(BOM),
+            line_ending: LineEnding::from_counts(lf, crlf),
+            final_newline: text.ends_with('\n'),
+        }
+    }
+
+    /// Returns the `TextFormat` of the `reader` stream contents.  The
+    /// stream is read in blocks and is not required to be valid UTF-8.
+    pub(crate) fn detect_reader(mut reader: impl Read) -> io::Result<Self> {
+        let mut buffer = [0; 64 * 1024];
+        let mut start = Vec::with_capacity(BOM.len_utf8());
+        let (mut lf, mut crlf) = (0, 0);
+        let mut previous = None;
+        loop {
+            let len = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            for &byte in &buffer[..len] {
+                if start.len() < BOM.len_utf8() {
+                    start.push(byte);
+                }
+                if byte == b'\n' {
+                    match previous {
+                        Some(b'\r') => crlf += 1,
+                        _ => lf += 1,
+                    }
+                }
+                previous = Some(byte);
+            }
+        }
+        Ok(Self {
+            bom: start == BOM.encode_utf8(&mut [0; 4]).as_bytes(),
+            line_ending: LineEnding::from_counts(lf, crlf),
+            final_newline: previous == Some(b'\n'),
+        })
+    }
+
+    /// Returns the `text` file contents without the byte order mark and
+    /// with `\n` line endings in CRLF files.  Patterns and replacements
+    /// operate on `\n` line endings in LF and CRLF files.  The line
+    /// endings of files with mixed line endings are not changed.
+    pub(crate) fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let text = text.strip_prefix(BOM).unwrap_or(text);
+        match self.line_ending {
+            LineEnding::Crlf => Cow::Owned(text.replace("\r\n", "\n")),
+            _ => Cow::Borrowed(text),
+        }
+    }
+
+    /// Returns the normalized `text` file contents in the original file
+    /// format or in the `line_endings` style and `final_newline` state
+    /// when they are defined.  The final newline state of empty files is
+    /// not changed.
+    pub(crate) fn restore(
+        &self,
+        text: &str,
+        line_endings: LineEndingChoice,
+        final_newline: FinalNewline,
+    ) -> String {
+        let add_final_newline = match final_newline {
+            FinalNewline::Preserve => self.final_newline,
+            FinalNewline::Add => true,
+            FinalNewline::Remove => false,
+        };
+        let mut restored = text.to_string();
+        if !restored.is_empty() {
+            if add_final_newline && !restored.ends_with('\n') {
+                restored.push('\n');
+            } else if !add_final_newline && restored.ends_with('\n') {
+                // only the final line ending is removed
+                restored.pop();
+                if restored.ends_with('\r') {
+                    restored.pop();
+                }
+            }
+        }
+        if let Cow::Owned(converted) = self.convert_line_endings(&restored, line_endings) {
+            restored = converted;
+        }
+        match self.bom {
+            true => format!("{}{}", BOM, restored),
+            false => restored,
+        }
+    }
+
+    /// Returns the normalized `text` with the line endings of the original
+    /// file or in the `line_endings` style when it is defined.  Texts that
+    /// are split after `\n` characters are converted one part at a time.
+    pub(crate) fn convert_line_endings<'a>(
+        &self,
+        text: &'a str,
+        line_endings: LineEndingChoice,
+    ) -> Cow<'a, str> {
+        let line_ending = match line_endings {
+            LineEndingChoice::Preserve => self.line_ending,
+            LineEndingChoice::Lf => LineEnding::Lf,
+            LineEndingChoice::Crlf => LineEnding::Crlf,
+        };
+        match line_ending {
+            LineEnding::Lf if self.line_ending != LineEnding::Lf && text.contains("\r\n") => {
+                Cow::Owned(text.replace("\r\n", "\n"))
+            }
+            LineEnding::Crlf if text.contains('\n') => {
+                Cow::Owned(text.replace("\r\n", "\n").replace('\n', "\r\n"))
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(text: &str, edit: fn(&str) -> String) -> String {
+        let format = TextFormat::detect(text);
+        format.restore(
+            &edit(&format.normalize(text)),
+            LineEndingChoice::Preserve,
+            FinalNewline::Preserve,
+        )
+    }
+
+    #[test]
+    fn test_text_format_detect() {
+        let format = TextFormat::detect("\u{feff}a\r\nb\r\n");
+        assert!(format.bom && format.final_newline);
+        assert_eq!(format.line_ending, LineEnding::Crlf);
+        assert_eq!(format.normalize("\u{feff}a\r\nb\r\n"), "a\nb\n");
+        let format = TextFormat::detect("a\nb");
+        assert!(!format.bom && !format.final_newline);
+        assert_eq!(format.line_ending, LineEnding::Lf);
+        assert_eq!(TextFormat::detect("").line_ending, LineEnding::Lf);
+        let format = TextFormat::detect("a\r\nb\n");
+        assert_eq!(format.line_ending, LineEnding::Mixed);
+        assert_eq!(format.normalize("a\r\nb\n"), "a\r\nb\n");
+    }
+
+    #[test]
+    fn test_text_format_detect_reader() {
+        let texts = [
+            "\u{feff}a\r\nb\r\n",
+            "a\nb",
+            "",
+            "a\r\nb\n",
+            "\r",
+            "\u{feff}",
+        ];
+        for text in &texts {
+            assert_eq!(
+                TextFormat::detect_reader(text.as_bytes()).unwrap(),
+                TextFormat::detect(text),
+                "{:?}",
+                text
+            );
+        }
+        // line endings and byte order marks that span reads
+        let format = TextFormat::detect_reader("\u{feff}a\r".as_bytes().chain(&b"\nb\r\n"[..]));
+        assert_eq!(format.unwrap(), TextFormat::detect("\u{feff}a\r\nb\r\n"));
+        let format = TextFormat::detect_reader((&b"\xef"[..]).chain(&b"\xbb\xbfa"[..]));
+        assert!(format.unwrap().bom);
+    }
+
+    #[test]
+    fn test_text_format_restore_preserves_format() {
+        // replacements with `\n` line endings in CRLF files
+        assert_eq!(
+            roundtrip("\u{feff}a\r\nb\r\n", |text| text.replace('a', "x\ny")),
+            "\u{feff}x\r\ny\r\nb\r\n"
+        );
+        // the final newline state is not changed
+        assert_eq!(roundtrip("a\r\nb", |text| format!("{}\n", text)), "a\r\nb");
+        assert_eq!(
+            roundtrip("a\nb\n", |text| text.replace("b\n", "c")),
+            "a\nc\n"
+        );
+        assert_eq!(roundtrip("a\n", |_| String::new()), "");
+        // blank lines in replacements are not removed
+        assert_eq!(
+            roundtrip("a\nb", |text| text.replace('b', "b\n\n\n")),
+            "a\nb\n\n"
+        );
+        assert_eq!(
+            roundtrip("a\r\nb", |text| text.replace('b', "b\n\n")),
+            "a\r\nb\r\n"
+        );
+        // mixed line endings are not changed
+        assert_eq!(
+            roundtrip("a\r\nb\n", |text| text.replace('b', "c\nd")),
+            "a\r\nc\nd\n"
+        );
+    }
+
+    #[test]
+    fn test_text_format_restore_choices() {
+        let format = TextFormat::detect("a\r\nb\n");
+        assert_eq!(
+            format.restore("a\r\nb\n", LineEndingChoice::Lf, FinalNewline::Remove),
+            "a\nb"
+        );
+        assert_eq!(
+            format.restore("a\r\nb", LineEndingChoice::Crlf, FinalNewline::Add),
+            "a\r\nb\r\n"
+        );
+        assert!("cr".parse::<LineEndingChoice>().is_err());
+        assert_eq!("add".parse::<FinalNewline>().unwrap(), FinalNewline::Add);
+    }
+}
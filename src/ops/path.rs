@@ -34,9 +34,23 @@ where
             }
             // or no period character in the extension
             // parameter (e.g., `txt`)
-            return ext.to_str().unwrap() == extension;
+            ext.to_str().unwrap() == extension
         }
-        None => return false,
+        None => false,
+    }
+}
+
+/// Returns the `filepath` path relative to the `start` traversal start
+/// path.  The file name is returned when the start path is the file
+/// path, and the `filepath` path is returned when it is not under the
+/// start path.
+pub(crate) fn path_relative_to<'a>(filepath: &'a Path, start: &Path) -> &'a Path {
+    match filepath.strip_prefix(start) {
+        Ok(relpath) if relpath.as_os_str().is_empty() => {
+            Path::new(filepath.file_name().unwrap_or_default())
+        }
+        Ok(relpath) => relpath,
+        Err(_) => filepath,
     }
 }
 
@@ -94,15 +108,38 @@ mod tests {
     #[test]
     fn test_path_has_extension_with_incorrect_extension() {
         let testpath = Path::new("./tests/testfiles/path/test.txt");
-        assert_eq!(path_has_extension(testpath, ".yaml"), false);
-        assert_eq!(path_has_extension(testpath, "yaml"), false);
+        assert!(!path_has_extension(testpath, ".yaml"));
+        assert!(!path_has_extension(testpath, "yaml"));
     }
 
     #[test]
     fn test_path_has_extension_with_no_extension() {
         let testpath = Path::new("./tests/testfiles/path/testfile");
-        assert_eq!(path_has_extension(testpath, ".txt"), false);
-        assert_eq!(path_has_extension(testpath, "txt"), false);
+        assert!(!path_has_extension(testpath, ".txt"));
+        assert!(!path_has_extension(testpath, "txt"));
+    }
+
+    // ======================================
+    // path_relative_to function tests
+    // ======================================
+    #[test]
+    fn test_path_relative_to() {
+        let start = Path::new("./tests/testfiles");
+        assert_eq!(
+            path_relative_to(Path::new("./tests/testfiles/path/test.txt"), start),
+            Path::new("path/test.txt")
+        );
+        assert_eq!(
+            path_relative_to(
+                Path::new("./tests/testfiles/path/test.txt"),
+                Path::new("./tests/testfiles/path/test.txt")
+            ),
+            Path::new("test.txt")
+        );
+        assert_eq!(
+            path_relative_to(Path::new("./src/lib.rs"), start),
+            Path::new("./src/lib.rs")
+        );
     }
 
     // ======================================
@@ -139,6 +176,6 @@ mod tests {
     #[test]
     fn test_path_is_not_hidden_without_dotfile_or_dotdir() {
         let testpath = Path::new("./tests/testfiles/path/testfile");
-        assert_eq!(path_is_hidden(testpath), false);
+        assert!(!path_is_hidden(testpath));
     }
 }
@@ -0,0 +1,390 @@
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
+
+use aho_corasick::AhoCorasick;
+use anyhow::{anyhow, Result};
+use regex::{Regex, RegexSet, RegexSetBuilder};
+
+/// A single pattern match in a haystack string.  The `pattern`
+/// field is the index of the pattern that produced the match in
+/// the `PatternSet` pattern list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PatternMatch {
+    pub(crate) pattern: usize,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+/// A capture group reference by index or by name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum GroupRef {
+    Index(usize),
+    Name(String),
+}
+
+impl fmt::Display for GroupRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GroupRef::Index(index) => write!(f, "{}", index),
+            GroupRef::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl FromStr for GroupRef {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.is_empty() {
+            return Err(anyhow!("empty capture group reference"));
+        }
+        match s.parse::<usize>() {
+            Ok(index) => Ok(GroupRef::Index(index)),
+            Err(_) => Ok(GroupRef::Name(s.to_string())),
+        }
+    }
+}
+
+enum Matcher {
+    Literal(Box<AhoCorasick>),
+    Regex { set: RegexSet, regexes: Vec<Regex> },
+}
+
+/// A set of patterns that are matched against a haystack in a single
+/// pass.  Literal pattern sets are searched with an Aho-Corasick
+/// automaton, all other pattern sets are searched with a `RegexSet`
+/// that identifies the patterns that match before the individual
+/// pattern match offsets are located.
+pub(crate) struct PatternSet {
+    patterns: Vec<String>,
+    matcher: Matcher,
+}
+
+impl PatternSet {
+    /// Returns a new `PatternSet` for the `patterns` argument.  When
+    /// `fixed_strings` is `true`, all patterns are treated as literal
+    /// strings.  Otherwise a literal search is only used when none of
+    /// the patterns include regular expression meta characters.
+    pub(crate) fn new(patterns: Vec<String>, fixed_strings: bool) -> Result<Self> {
+        if patterns.is_empty() {
+            return Err(anyhow!("at least one find pattern is required"));
+        }
+        let is_literal = fixed_strings || patterns.iter().all(|p| regex::escape(p) == *p);
+        let matcher = if is_literal {
+            if patterns.iter().any(|p| p.is_empty()) {
+                return Err(anyhow!("empty literal find patterns are not supported"));
+            }
+            Matcher::Literal(Box::new(AhoCorasick::new(&patterns)))
+        } else {
+            let mut regexes = Vec::with_capacity(patterns.len());
+            for pattern in &patterns {
+                regexes.push(Regex::new(pattern)?);
+            }
+            // the set is also used to test whole files, multi-line mode
+            // keeps `^` and `$` anchors consistent with the line searches
+            Matcher::Regex {
+                set: RegexSetBuilder::new(&patterns).multi_line(true).build()?,
+                regexes,
+            }
+        };
+        Ok(Self { patterns, matcher })
+    }
+
+    /// Returns the pattern string at the `index` position in the set.
+    pub(crate) fn pattern(&self, index: usize) -> &str {
+        &self.patterns[index]
+    }
+
+    /// Returns the number of patterns in the set.
+    pub(crate) fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// Returns a boolean that indicates whether any of the patterns
+    /// match anywhere in the `haystack` argument.
+    pub(crate) fn is_match(&self, haystack: &str) -> bool {
+        match &self.matcher {
+            Matcher::Literal(ac) => ac.is_match(haystack),
+            Matcher::Regex { set, .. } => set.is_match(haystack),
+        }
+    }
+
+    /// Returns all matches of all patterns in the `haystack` argument,
+    /// ordered by start offset and then by pattern index.  Each pattern
+    /// reports its own non-overlapping leftmost matches so that matches
+    /// of different patterns may overlap.
+    pub(crate) fn find_all(&self, haystack: &str) -> Vec<PatternMatch> {
+        let mut matches = Vec::new();
+        match &self.matcher {
+            Matcher::Literal(ac) => {
+                // end offset of the last reported match by pattern index
+                // used to skip self-overlapping matches of a pattern
+                let mut last_end = vec![0; self.patterns.len()];
+                for mat in ac.find_overlapping_iter(haystack) {
+                    if mat.start() < last_end[mat.pattern()] {
+                        continue;
+                    }
+                    last_end[mat.pattern()] = mat.end();
+                    matches.push(PatternMatch {
+                        pattern: mat.pattern(),
+                        start: mat.start(),
+                        end: mat.end(),
+                    });
+                }
+            }
+            Matcher::Regex { set, regexes } => {
+                for index in set.matches(haystack).iter() {
+                    for mat in regexes[index].find_iter(haystack) {
+                        matches.push(PatternMatch {
+                            pattern: index,
+                            start: mat.start(),
+                            end: mat.end(),
+                        });
+                    }
+                }
+            }
+        }
+        matches.sort_by_key(|m| (m.start, m.pattern));
+        matches
+    }
+
+    /// Returns a boolean that indicates whether the `group` capture
+    /// group is defined in at least one pattern of the set.  Literal
+    /// patterns only define the implicit whole match group 0.
+    pub(crate) fn has_group(&self, group: &GroupRef) -> bool {
+        match (&self.matcher, group) {
+            (_, GroupRef::Index(0)) => true,
+            (Matcher::Literal(_), _) => false,
+            (Matcher::Regex { regexes, .. }, GroupRef::Index(index)) => {
+                regexes.iter().any(|re| *index < re.captures_len())
+            }
+            (Matcher::Regex { regexes, .. }, GroupRef::Name(name)) => regexes
+                .iter()
+                .any(|re| re.capture_names().any(|n| n == Some(name.as_str()))),
+        }
+    }
+
+    /// Returns the start and end offsets of the `group` capture group
+    /// in the `mat` match of the `haystack` string.  Returns `None` when
+    /// the group is not defined in the pattern of the match or when the
+    /// group did not participate in the match.
+    pub(crate) fn group_offsets(
+        &self,
+        haystack: &str,
+        mat: &PatternMatch,
+        group: &GroupRef,
+    ) -> Option<(usize, usize)> {
+        match (&self.matcher, group) {
+            (_, GroupRef::Index(0)) => Some((mat.start, mat.end)),
+            (Matcher::Literal(_), _) => None,
+            (Matcher::Regex { regexes, .. }, group) => {
+                let re = &regexes[mat.pattern];
+                let index = match group {
+                    GroupRef::Index(index) => *index,
+                    GroupRef::Name(name) => {
+                        re.capture_names().position(|n| n == Some(name.as_str()))?
+                    }
+                };
+                let mut locs = re.capture_locations();
+                re.captures_read_at(&mut locs, haystack, mat.start)?;
+                locs.get(index)
+            }
+        }
+    }
+}
+
+/// Returns the 1-based character column of the `offset` byte offset
+/// in the `line` string.
+pub(crate) fn char_column(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+/// Returns the list of patterns in the `filepath` patterns file.  The
+/// file format is one pattern per line.  Empty lines are ignored.
+pub(crate) fn read_patterns_file<P>(filepath: P) -> Result<Vec<String>>
+where
+    P: AsRef<Path>,
+{
+    match read_to_string(&filepath) {
+        Ok(filestr) => Ok(filestr
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect()),
+        Err(error) => Err(anyhow!(
+            "unable to read patterns file '{}': {}",
+            filepath.as_ref().display(),
+            error
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ======================================
+    // PatternSet tests
+    // ======================================
+    #[test]
+    fn test_pattern_set_empty_patterns() {
+        assert!(PatternSet::new(vec![], false).is_err());
+    }
+
+    #[test]
+    fn test_pattern_set_invalid_regex() {
+        let ps = PatternSet::new(vec!["ok".to_string(), "(bad".to_string()], false);
+        assert!(ps.is_err());
+    }
+
+    #[test]
+    fn test_pattern_set_literal_matches() {
+        let ps = PatternSet::new(vec!["foo".to_string(), "bar".to_string()], false).unwrap();
+        assert!(matches!(ps.matcher, Matcher::Literal(_)));
+        assert!(ps.is_match("a bar"));
+        assert!(!ps.is_match("baz"));
+        assert_eq!(
+            ps.find_all("bar foo bar"),
+            vec![
+                PatternMatch {
+                    pattern: 1,
+                    start: 0,
+                    end: 3
+                },
+                PatternMatch {
+                    pattern: 0,
+                    start: 4,
+                    end: 7
+                },
+                PatternMatch {
+                    pattern: 1,
+                    start: 8,
+                    end: 11
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pattern_set_literal_overlapping_patterns() {
+        let ps = PatternSet::new(vec!["abc".to_string(), "bc".to_string()], false).unwrap();
+        let matches = ps.find_all("abc");
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].pattern, 0);
+        assert_eq!(matches[1].pattern, 1);
+        assert_eq!((matches[1].start, matches[1].end), (1, 3));
+    }
+
+    #[test]
+    fn test_pattern_set_literal_no_self_overlap() {
+        let ps = PatternSet::new(vec!["aa".to_string()], false).unwrap();
+        assert_eq!(ps.find_all("aaaa").len(), 2);
+    }
+
+    #[test]
+    fn test_pattern_set_fixed_strings_with_meta_characters() {
+        let ps = PatternSet::new(vec!["a.b".to_string()], true).unwrap();
+        assert!(matches!(ps.matcher, Matcher::Literal(_)));
+        assert!(ps.is_match("x a.b y"));
+        assert!(!ps.is_match("x axb y"));
+    }
+
+    #[test]
+    fn test_pattern_set_regex_matches() {
+        let ps = PatternSet::new(vec![r"\d+".to_string(), "ab".to_string()], false).unwrap();
+        assert!(matches!(ps.matcher, Matcher::Regex { .. }));
+        let matches = ps.find_all("ab 12 ab");
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[0].pattern, 1);
+        assert_eq!(matches[1].pattern, 0);
+        assert_eq!((matches[1].start, matches[1].end), (3, 5));
+        assert_eq!(ps.pattern(0), r"\d+");
+        assert_eq!(ps.len(), 2);
+    }
+
+    #[test]
+    fn test_group_ref_from_str() {
+        assert_eq!("2".parse::<GroupRef>().unwrap(), GroupRef::Index(2));
+        assert_eq!(
+            "name".parse::<GroupRef>().unwrap(),
+            GroupRef::Name("name".to_string())
+        );
+        assert!("".parse::<GroupRef>().is_err());
+    }
+
+    #[test]
+    fn test_pattern_set_group_offsets() {
+        let ps = PatternSet::new(
+            vec![r"(?P<key>\w+)=(\d+)".to_string(), "x".to_string()],
+            false,
+        )
+        .unwrap();
+        let haystack = "a x b=12";
+        let matches = ps.find_all(haystack);
+        assert_eq!(matches.len(), 2);
+        // the match of the pattern without groups
+        assert_eq!(
+            ps.group_offsets(haystack, &matches[0], &GroupRef::Index(0)),
+            Some((2, 3))
+        );
+        assert_eq!(
+            ps.group_offsets(haystack, &matches[0], &GroupRef::Index(1)),
+            None
+        );
+        // the match of the pattern with groups
+        let key = GroupRef::Name("key".to_string());
+        assert_eq!(ps.group_offsets(haystack, &matches[1], &key), Some((4, 5)));
+        assert_eq!(
+            ps.group_offsets(haystack, &matches[1], &GroupRef::Index(2)),
+            Some((6, 8))
+        );
+        assert!(ps.has_group(&key));
+        assert!(ps.has_group(&GroupRef::Index(2)));
+        assert!(!ps.has_group(&GroupRef::Index(3)));
+        assert!(!ps.has_group(&GroupRef::Name("bogus".to_string())));
+    }
+
+    #[test]
+    fn test_pattern_set_literal_groups() {
+        let ps = PatternSet::new(vec!["abc".to_string()], false).unwrap();
+        let matches = ps.find_all("xabc");
+        assert!(ps.has_group(&GroupRef::Index(0)));
+        assert!(!ps.has_group(&GroupRef::Index(1)));
+        assert_eq!(
+            ps.group_offsets("xabc", &matches[0], &GroupRef::Index(0)),
+            Some((1, 4))
+        );
+    }
+
+    // ======================================
+    // char_column function tests
+    // ======================================
+    #[test]
+    fn test_char_column() {
+        assert_eq!(char_column("abc", 0), 1);
+        assert_eq!(char_column("abc", 2), 3);
+        // multi-byte characters count as one column
+        assert_eq!(char_column("ऄऄx", 6), 3);
+    }
+
+    // ======================================
+    // read_patterns_file function tests
+    // ======================================
+    #[test]
+    fn test_read_patterns_file() {
+        let patterns = read_patterns_file("tests/testfiles/find/patterns.txt").unwrap();
+        assert_eq!(patterns, vec!["1010".to_string(), r"\d\d\d\d".to_string()]);
+    }
+
+    #[test]
+    fn test_read_patterns_file_missing() {
+        let res = read_patterns_file("tests/testfiles/find/bogus.txt");
+        assert!(res.is_err());
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("unable to read patterns file"));
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::{BufRead, Write};
+
+use anyhow::Result;
+
+/// An interactive replacement confirmation answer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Answer {
+    /// Replace the match
+    Yes,
+    /// Skip the match
+    No,
+    /// Replace the match and all remaining matches in the file
+    All,
+    /// Skip the match and all remaining matches in the traversal
+    Quit,
+    /// Replace the match with a user-defined replacement string
+    Edit(String),
+}
+
+const ANSWER_HELP: &str = "y - replace this match
+n - skip this match
+a - replace this match and all remaining matches in the file
+q - quit, skip this match and all remaining matches
+e - edit the replacement string of this match";
+
+/// Writes the `question` prompt and returns the answer read from the
+/// `input` stream.  The question is repeated with a help message until
+/// a valid answer is entered.  The end of the input stream is a `Quit`
+/// answer.
+pub(crate) fn read_answer(
+    question: &str,
+    input: &mut impl BufRead,
+    writer: &mut impl Write,
+) -> Result<Answer> {
+    loop {
+        write!(writer, "{} [y,n,a,q,e,?] ", question)?;
+        writer.flush()?;
+        let response = match read_line(input)? {
+            Some(response) => response,
+            None => return Ok(Answer::Quit),
+        };
+        match response.trim() {
+            "y" | "Y" => return Ok(Answer::Yes),
+            "n" | "N" => return Ok(Answer::No),
+            "a" | "A" => return Ok(Answer::All),
+            "q" | "Q" => return Ok(Answer::Quit),
+            "e" | "E" => {
+                write!(writer, "Replacement: ")?;
+                writer.flush()?;
+                return match read_line(input)? {
+                    Some(replacement) => Ok(Answer::Edit(replacement)),
+                    None => Ok(Answer::Quit),
+                };
+            }
+            _ => writeln!(writer, "{}", ANSWER_HELP)?,
+        }
+    }
+}
+
+/// Returns the next line in the `input` stream without the line ending
+/// or `None` at the end of the stream.
+fn read_line(input: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let trimmed_len = line.trim_end_matches(&['\r', '\n'][..]).len();
+    line.truncate(trimmed_len);
+    Ok(Some(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_answer() {
+        let mut output = Vec::new();
+        for (response, expected) in &[
+            ("y\n", Answer::Yes),
+            ("N\n", Answer::No),
+            ("a\n", Answer::All),
+            ("q\n", Answer::Quit),
+            ("e\nnew text \r\n", Answer::Edit("new text ".to_string())),
+            ("", Answer::Quit),
+            ("e\n", Answer::Quit),
+        ] {
+            let mut input = Cursor::new(response.as_bytes());
+            assert_eq!(
+                &read_answer("Replace?", &mut input, &mut output).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_answer_repeats_on_invalid_answer() {
+        let mut output = Vec::new();
+        let mut input = Cursor::new(&b"x\ny\n"[..]);
+        assert_eq!(
+            read_answer("Replace?", &mut input, &mut output).unwrap(),
+            Answer::Yes
+        );
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        assert_eq!(output_slice.matches("Replace? [y,n,a,q,e,?] ").count(), 2);
+        assert!(output_slice.contains("q - quit"));
+    }
+}
@@ -0,0 +1,327 @@
+use anyhow::{anyhow, Result};
+use regex::{RegexSet, RegexSetBuilder};
+
+/// Boolean query expression tree.  `Pattern` leaves hold the index
+/// of a pattern in the `Query` pattern list.
+#[derive(Debug, PartialEq)]
+enum Expr {
+    Pattern(usize),
+    Not(Box<Expr>),
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, matched: &[bool]) -> bool {
+        match self {
+            Expr::Pattern(index) => matched[*index],
+            Expr::Not(expr) => !expr.eval(matched),
+            Expr::And(exprs) => exprs.iter().all(|e| e.eval(matched)),
+            Expr::Or(exprs) => exprs.iter().any(|e| e.eval(matched)),
+        }
+    }
+}
+
+/// A file-level boolean query over a set of regular expression
+/// patterns.  All patterns are tested against the file text in a
+/// single `RegexSet` pass and the query expression is evaluated
+/// against the set of patterns that matched.
+#[derive(Debug)]
+pub(crate) struct Query {
+    patterns: Vec<String>,
+    set: RegexSet,
+    expr: Expr,
+}
+
+impl Query {
+    /// Returns a new `Query` that requires all of the `all_of` patterns,
+    /// at least one of the `any_of` patterns (when defined), none of the
+    /// `none_of` patterns, and a true `expression` result (when defined).
+    ///
+    /// The `expression` syntax supports the `&` (and), `|` (or), and `!`
+    /// (not) operators with parentheses for grouping.  Operands are bare
+    /// words or single/double quoted regular expression patterns.
+    pub(crate) fn new(
+        all_of: &[String],
+        any_of: &[String],
+        none_of: &[String],
+        expression: Option<&str>,
+    ) -> Result<Self> {
+        let mut patterns = Vec::new();
+        let mut terms = Vec::new();
+        for pattern in all_of {
+            terms.push(Expr::Pattern(add_pattern(&mut patterns, pattern)));
+        }
+        if !any_of.is_empty() {
+            terms.push(Expr::Or(
+                any_of
+                    .iter()
+                    .map(|p| Expr::Pattern(add_pattern(&mut patterns, p)))
+                    .collect(),
+            ));
+        }
+        for pattern in none_of {
+            terms.push(Expr::Not(Box::new(Expr::Pattern(add_pattern(
+                &mut patterns,
+                pattern,
+            )))));
+        }
+        if let Some(expression) = expression {
+            terms.push(Parser::new(expression, &mut patterns).parse()?);
+        }
+        if terms.is_empty() {
+            return Err(anyhow!("at least one query pattern is required"));
+        }
+        // multi-line mode so that `^` and `$` anchors match at line boundaries
+        let set = RegexSetBuilder::new(&patterns).multi_line(true).build()?;
+        Ok(Self {
+            patterns,
+            set,
+            expr: Expr::And(terms),
+        })
+    }
+
+    /// Returns a boolean that indicates whether the `haystack` text
+    /// satisfies the query.
+    pub(crate) fn is_match(&self, haystack: &str) -> bool {
+        let mut matched = vec![false; self.patterns.len()];
+        for index in self.set.matches(haystack).iter() {
+            matched[index] = true;
+        }
+        self.expr.eval(&matched)
+    }
+}
+
+/// Returns the index of `pattern` in `patterns`, appending the
+/// pattern to the list when it is not present.
+fn add_pattern(patterns: &mut Vec<String>, pattern: &str) -> usize {
+    match patterns.iter().position(|p| p == pattern) {
+        Some(index) => index,
+        None => {
+            patterns.push(pattern.to_string());
+            patterns.len() - 1
+        }
+    }
+}
+
+/// Recursive descent parser for query expressions.
+///
+/// ```text
+/// or      := and ( '|' and )*
+/// and     := unary ( '&' unary )*
+/// unary   := '!' unary | '(' or ')' | operand
+/// operand := bare word | quoted string
+/// ```
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    patterns: &'a mut Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(expression: &str, patterns: &'a mut Vec<String>) -> Self {
+        Self {
+            chars: expression.chars().collect(),
+            pos: 0,
+            patterns,
+        }
+    }
+
+    fn parse(mut self) -> Result<Expr> {
+        let expr = self.parse_or()?;
+        self.skip_whitespace();
+        match self.peek() {
+            None => Ok(expr),
+            Some(c) => Err(self.error(&format!("unexpected '{}'", c))),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.consume('|') {
+            exprs.push(self.parse_and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.parse_unary()?];
+        while self.consume('&') {
+            exprs.push(self.parse_unary()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            Expr::And(exprs)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.consume('!') {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.consume('(') {
+            let expr = self.parse_or()?;
+            if !self.consume(')') {
+                return Err(self.error("missing closing ')'"));
+            }
+            return Ok(expr);
+        }
+        let operand = self.parse_operand()?;
+        Ok(Expr::Pattern(add_pattern(self.patterns, &operand)))
+    }
+
+    fn parse_operand(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(quote) if quote == '"' || quote == '\'' => {
+                self.pos += 1;
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if c == quote {
+                        let operand: String = self.chars[start..self.pos].iter().collect();
+                        self.pos += 1;
+                        return Ok(operand);
+                    }
+                    self.pos += 1;
+                }
+                Err(self.error("unterminated quoted pattern"))
+            }
+            Some(_) => {
+                let start = self.pos;
+                while let Some(c) = self.peek() {
+                    if c.is_whitespace() || "&|!()".contains(c) {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                if start == self.pos {
+                    return Err(self.error("expected a pattern"));
+                }
+                Ok(self.chars[start..self.pos].iter().collect())
+            }
+            None => Err(self.error("expected a pattern")),
+        }
+    }
+
+    fn consume(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        anyhow!(
+            "invalid query expression at character {}: {}",
+            self.pos + 1,
+            message
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(expression: &str) -> Result<Query> {
+        Query::new(&[], &[], &[], Some(expression))
+    }
+
+    #[test]
+    fn test_query_requires_pattern() {
+        assert!(Query::new(&[], &[], &[], None).is_err());
+    }
+
+    #[test]
+    fn test_query_all_of_none_of() {
+        let q = Query::new(
+            &["alpha".to_string(), "beta".to_string()],
+            &[],
+            &["gamma".to_string()],
+            None,
+        )
+        .unwrap();
+        assert!(q.is_match("alpha\nbeta\n"));
+        assert!(!q.is_match("alpha\n"));
+        assert!(!q.is_match("alpha beta gamma"));
+    }
+
+    #[test]
+    fn test_query_any_of() {
+        let q = Query::new(&[], &["alpha".to_string(), "beta".to_string()], &[], None).unwrap();
+        assert!(q.is_match("beta"));
+        assert!(!q.is_match("gamma"));
+    }
+
+    #[test]
+    fn test_query_expression_and_not() {
+        let q = query("A & B & !C").unwrap();
+        assert_eq!(q.patterns, vec!["A", "B", "C"]);
+        assert!(q.is_match("A B"));
+        assert!(!q.is_match("A B C"));
+        assert!(!q.is_match("B"));
+    }
+
+    #[test]
+    fn test_query_expression_precedence_and_grouping() {
+        // `&` binds tighter than `|`
+        let q = query("A | B & C").unwrap();
+        assert!(q.is_match("A"));
+        assert!(!q.is_match("B"));
+        let q = query("(A | B) & C").unwrap();
+        assert!(!q.is_match("A"));
+        assert!(q.is_match("B C"));
+    }
+
+    #[test]
+    fn test_query_expression_quoted_regex_operands() {
+        let q = query(r#""open\(" & !'close\(\)'"#).unwrap();
+        assert!(q.is_match("open(file)"));
+        assert!(!q.is_match("open(file)\nclose()"));
+    }
+
+    #[test]
+    fn test_query_expression_line_anchors() {
+        let q = query(r"'^1010$'").unwrap();
+        assert!(q.is_match("test\n1010\ntest"));
+    }
+
+    #[test]
+    fn test_query_combines_options_and_expression() {
+        let q = Query::new(&["A".to_string()], &[], &[], Some("B | C")).unwrap();
+        assert!(q.is_match("A C"));
+        assert!(!q.is_match("B C"));
+    }
+
+    #[test]
+    fn test_query_expression_errors() {
+        assert!(query("A &").is_err());
+        assert!(query("(A | B").is_err());
+        assert!(query("A B").is_err());
+        assert!(query("'A").is_err());
+        assert!(query("A & (").is_err());
+        assert!(query("(bad").is_err());
+        assert!(query("'(bad'").is_err());
+    }
+}
@@ -0,0 +1,312 @@
+use std::fs::read_to_string;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::ops::rules::build_globset;
+use crate::ops::substitution::{Substitution, SubstitutionOptions};
+
+/// The replace rules file format.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SubstitutionsFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<SubstitutionDefinition>,
+}
+
+/// A find and replace pair definition in the replace rules file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SubstitutionDefinition {
+    find: String,
+    replace: String,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    extension: Option<String>,
+}
+
+/// A find and replace pair definition record in a CSV replace rules
+/// file.  Multiple `include` and `exclude` globs are separated by
+/// semicolons.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CsvSubstitutionDefinition {
+    find: String,
+    replace: String,
+    #[serde(default)]
+    include: String,
+    #[serde(default)]
+    exclude: String,
+    extension: Option<String>,
+}
+
+impl From<CsvSubstitutionDefinition> for SubstitutionDefinition {
+    fn from(record: CsvSubstitutionDefinition) -> Self {
+        let globs = |field: &str| {
+            field
+                .split(';')
+                .map(str::trim)
+                .filter(|glob| !glob.is_empty())
+                .map(String::from)
+                .collect()
+        };
+        Self {
+            include: globs(&record.include),
+            exclude: globs(&record.exclude),
+            find: record.find,
+            replace: record.replace,
+            extension: record.extension.filter(|extension| !extension.is_empty()),
+        }
+    }
+}
+
+/// The replace rules file formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RulesFormat {
+    Toml,
+    Yaml,
+    Csv,
+}
+
+impl RulesFormat {
+    /// Returns the rules file format of the `filepath` file extension.
+    fn from_filepath(filepath: &Path) -> Result<Self> {
+        match filepath.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(RulesFormat::Toml),
+            Some("yaml") | Some("yml") => Ok(RulesFormat::Yaml),
+            Some("csv") => Ok(RulesFormat::Csv),
+            _ => Err(anyhow!(
+                "unsupported rules file format '{}', expected a .toml, .yaml, .yml, or .csv file",
+                filepath.display()
+            )),
+        }
+    }
+}
+
+/// Returns the ordered substitutions defined in the `filepath` replace
+/// rules file.  The TOML, YAML, or CSV format is selected by the file
+/// extension.  The `options` apply to all substitutions.
+pub(crate) fn substitutions_from_file<P>(
+    filepath: P,
+    options: SubstitutionOptions,
+) -> Result<Vec<Substitution>>
+where
+    P: AsRef<Path>,
+{
+    let filepath = filepath.as_ref();
+    let format = RulesFormat::from_filepath(filepath)?;
+    let contents = read_to_string(filepath)
+        .with_context(|| format!("unable to read rules file '{}'", filepath.display()))?;
+    substitutions_from_str(&contents, format, options)
+        .with_context(|| format!("invalid rules file '{}'", filepath.display()))
+}
+
+fn substitutions_from_str(
+    contents: &str,
+    format: RulesFormat,
+    options: SubstitutionOptions,
+) -> Result<Vec<Substitution>> {
+    let definitions = match format {
+        RulesFormat::Toml => toml::from_str::<SubstitutionsFile>(contents)?.rules,
+        RulesFormat::Yaml => {
+            let mut value: serde_yaml::Value = serde_yaml::from_str(contents)?;
+            // `<<` merge keys extend mappings with anchored mappings
+            value.apply_merge()?;
+            match value {
+                // a top-level sequence is shorthand for the `rule` list
+                serde_yaml::Value::Sequence(_) => serde_yaml::from_value(value)?,
+                serde_yaml::Value::Null => Vec::new(),
+                value => serde_yaml::from_value::<SubstitutionsFile>(value)?.rules,
+            }
+        }
+        RulesFormat::Csv => csv::Reader::from_reader(contents.as_bytes())
+            .deserialize::<CsvSubstitutionDefinition>()
+            .map(|record| record.map(SubstitutionDefinition::from))
+            .collect::<Result<_, _>>()?,
+    };
+    if definitions.is_empty() {
+        return Err(anyhow!("at least one rule definition is required"));
+    }
+    definitions
+        .into_iter()
+        .enumerate()
+        .map(|(index, definition)| {
+            let substitution = Substitution::new(&definition.find, definition.replace, options)
+                .with_context(|| format!("invalid rule {}", index + 1))?;
+            let include = if definition.include.is_empty() {
+                None
+            } else {
+                Some(build_globset(&definition.include)?)
+            };
+            Ok(substitution.with_scope(
+                include,
+                build_globset(&definition.exclude)?,
+                definition.extension,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::scope::{LineScope, Occurrences};
+    use crate::ops::substitution::apply_all;
+    use crate::ops::transform::TemplateContext;
+    use std::cell::Cell;
+
+    fn from_str(contents: &str, format: RulesFormat) -> Result<Vec<Substitution>> {
+        substitutions_from_str(contents, format, SubstitutionOptions::default())
+    }
+
+    fn applied(substitutions: &[Substitution], filepath: &str, text: &str) -> (String, usize) {
+        let applicable: Vec<&Substitution> = substitutions
+            .iter()
+            .filter(|substitution| substitution.applies_to(Path::new(filepath)))
+            .collect();
+        apply_all(
+            &applicable,
+            &LineScope::default(),
+            &Occurrences::default(),
+            &TemplateContext {
+                path: Path::new(filepath),
+                counter: &Cell::new(1),
+            },
+            text,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_substitutions_from_toml() {
+        let substitutions = from_str(
+            r#"
+[[rule]]
+find = "colour"
+replace = "color"
+
+[[rule]]
+find = "color"
+replace = "hue"
+include = ["docs/**"]
+exclude = ["docs/legacy/**"]
+
+[[rule]]
+find = "(\\w+)_id"
+replace = "${1}Id"
+extension = "rs"
+"#,
+            RulesFormat::Toml,
+        )
+        .unwrap();
+        assert_eq!(substitutions.len(), 3);
+        assert_eq!(
+            applied(&substitutions, "docs/a.md", "colour color"),
+            ("hue hue".to_string(), 3)
+        );
+        assert_eq!(
+            applied(&substitutions, "docs/legacy/a.md", "colour"),
+            ("color".to_string(), 1)
+        );
+        assert_eq!(
+            applied(&substitutions, "src/a.rs", "user_id colour"),
+            ("userId color".to_string(), 2)
+        );
+        assert_eq!(
+            applied(&substitutions, "src/a.py", "user_id"),
+            ("user_id".to_string(), 0)
+        );
+    }
+
+    #[test]
+    fn test_substitutions_from_yaml() {
+        let substitutions = from_str(
+            r#"
+# spelling
+rule:
+  - find: colou?r   # regex
+    replace: 'hue'
+    include: ["*.md", 'docs/**']
+  - find: "a\tb"
+    replace: "it's # not a comment"
+    exclude:
+      - "*.txt"
+"#,
+            RulesFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(substitutions.len(), 2);
+        assert_eq!(substitutions[0].re.as_str(), "colou?r");
+        assert_eq!(substitutions[0].replace, "hue");
+        assert!(substitutions[0].applies_to(Path::new("docs/a.rs")));
+        assert!(!substitutions[0].applies_to(Path::new("src/a.rs")));
+        assert_eq!(substitutions[1].re.as_str(), "a\tb");
+        assert_eq!(substitutions[1].replace, "it's # not a comment");
+        assert!(!substitutions[1].applies_to(Path::new("a.txt")));
+
+        let sequence = from_str(
+            "- find: a\n  replace: b\n- find: c\n  replace: d\n",
+            RulesFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(applied(&sequence, "a.txt", "ac"), ("bd".to_string(), 2));
+
+        // anchors, flow mappings, block scalars, and escapes
+        let syntax = from_str(
+            r#"
+- &docs {find: '\bcolour\b', replace: "color\u0021", include: ["docs/**"]}
+- <<: *docs
+  find: 'it''s'
+- find: end
+  replace: |
+    one
+    two
+"#,
+            RulesFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(syntax[0].re.as_str(), r"\bcolour\b");
+        assert_eq!(syntax[0].replace, "color!");
+        assert_eq!(syntax[1].re.as_str(), "it's");
+        assert!(!syntax[1].applies_to(Path::new("a.md")));
+        assert_eq!(syntax[2].replace, "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_substitutions_from_csv() {
+        let substitutions = from_str(
+            "find,replace,include,extension\r\ncolour,color,,\n\"a,b\",\"say \"\"hi\"\"\",docs/**; *.txt,\n\nx,y,,md\n",
+            RulesFormat::Csv)
+        .unwrap();
+        assert_eq!(substitutions.len(), 3);
+        assert_eq!(substitutions[1].re.as_str(), "a,b");
+        assert_eq!(substitutions[1].replace, "say \"hi\"");
+        assert!(substitutions[1].applies_to(Path::new("b.txt")));
+        assert!(!substitutions[1].applies_to(Path::new("b.md")));
+        assert!(substitutions[2].applies_to(Path::new("b.md")));
+        assert!(!substitutions[2].applies_to(Path::new("b.txt")));
+
+        // quoted fields with line breaks
+        let multiline = from_str("find,replace\n\"a\nb\",\"c\r\nd\"\n", RulesFormat::Csv).unwrap();
+        assert_eq!(multiline[0].re.as_str(), "a\nb");
+        assert_eq!(multiline[0].replace, "c\r\nd");
+    }
+
+    #[test]
+    fn test_substitutions_invalid_definitions() {
+        assert!(from_str("", RulesFormat::Toml).is_err());
+        assert!(from_str("[[rule]]\nfind = \"a\"", RulesFormat::Toml).is_err());
+        assert!(from_str("[[rule]]\nfind = \"(\"\nreplace = \"a\"", RulesFormat::Toml).is_err());
+        assert!(from_str("", RulesFormat::Yaml).is_err());
+        assert!(from_str("- find: *.md\n  replace: a", RulesFormat::Yaml).is_err());
+        assert!(from_str("- find: a\n  replace: b\n  other: c", RulesFormat::Yaml).is_err());
+        assert!(from_str("find,replace\n", RulesFormat::Csv).is_err());
+        assert!(from_str("find,with\na,b\n", RulesFormat::Csv).is_err());
+        assert!(from_str("find,replace\na\n", RulesFormat::Csv).is_err());
+        assert!(from_str("find,replace\n\"a,b\n", RulesFormat::Csv).is_err());
+        assert!(RulesFormat::from_filepath(Path::new("rules.json")).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::{Regex, RegexSet};
+use serde::Deserialize;
+
+use crate::ops::pattern::PatternSet;
+use crate::report::ReportRule;
+
+/// The inline suppression comment syntax.  Rule ids are separated
+/// by commas (e.g., `recurse:allow(no-todo, no-fixme)`).
+const ALLOW_COMMENT_PATTERN: &str = r"recurse:allow\(([^)]*)\)";
+
+/// The severity of a rule.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Severity {
+    #[default]
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// The rules file format.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<RuleDefinition>,
+}
+
+/// A rule definition in the rules file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDefinition {
+    id: String,
+    regex: String,
+    message: String,
+    #[serde(default)]
+    severity: Severity,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    allow: Vec<String>,
+}
+
+/// A compiled rule.  Matches are reported when the file path matches
+/// an `include` glob (or no `include` globs are defined), does not
+/// match an `exclude` glob, and the line does not match an `allow`
+/// exception pattern.
+#[derive(Debug)]
+pub(crate) struct Rule {
+    pub(crate) id: String,
+    pub(crate) message: String,
+    pub(crate) severity: Severity,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    allow: RegexSet,
+}
+
+impl Rule {
+    /// Returns a boolean that indicates whether the rule is checked in
+    /// the `filepath` file.  The path is relative to the traversal
+    /// start path.
+    pub(crate) fn applies_to(&self, filepath: &Path) -> bool {
+        let included = match &self.include {
+            Some(include) => include.is_match(filepath),
+            None => true,
+        };
+        included && !self.exclude.is_match(filepath)
+    }
+
+    /// Returns a boolean that indicates whether the `line` is an allowed
+    /// exception to the rule.
+    pub(crate) fn is_allowed(&self, line: &str) -> bool {
+        self.allow.is_match(line)
+    }
+}
+
+/// The rules of a rules file.  All rule patterns are searched in a
+/// single `PatternSet` where the pattern index is the rule index.
+pub(crate) struct RuleSet {
+    rules: Vec<Rule>,
+    pattern_set: PatternSet,
+    allow_comment: Regex,
+}
+
+impl RuleSet {
+    /// Returns the `RuleSet` defined in the `filepath` TOML rules file.
+    pub(crate) fn from_file<P>(filepath: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let filepath = filepath.as_ref();
+        let contents = read_to_string(filepath)
+            .with_context(|| format!("unable to read rules file '{}'", filepath.display()))?;
+        Self::from_toml(&contents)
+            .with_context(|| format!("invalid rules file '{}'", filepath.display()))
+    }
+
+    /// Returns the `RuleSet` defined in the `contents` TOML string.
+    pub(crate) fn from_toml(contents: &str) -> Result<Self> {
+        let file: RulesFile = toml::from_str(contents)?;
+        if file.rules.is_empty() {
+            return Err(anyhow!("at least one [[rule]] definition is required"));
+        }
+        let mut rules = Vec::with_capacity(file.rules.len());
+        let mut patterns = Vec::with_capacity(file.rules.len());
+        for definition in file.rules {
+            if rules.iter().any(|rule: &Rule| rule.id == definition.id) {
+                return Err(anyhow!("duplicate rule id '{}'", definition.id));
+            }
+            Regex::new(&definition.regex)
+                .with_context(|| format!("invalid regex in rule '{}'", definition.id))?;
+            let include = if definition.include.is_empty() {
+                None
+            } else {
+                Some(build_globset(&definition.include)?)
+            };
+            rules.push(Rule {
+                exclude: build_globset(&definition.exclude)?,
+                allow: RegexSet::new(&definition.allow).with_context(|| {
+                    format!("invalid allow pattern in rule '{}'", definition.id)
+                })?,
+                include,
+                id: definition.id,
+                message: definition.message,
+                severity: definition.severity,
+            });
+            patterns.push(definition.regex);
+        }
+        Ok(Self {
+            rules,
+            pattern_set: PatternSet::new(patterns, false)?,
+            allow_comment: Regex::new(ALLOW_COMMENT_PATTERN)?,
+        })
+    }
+
+    pub(crate) fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub(crate) fn rule(&self, index: usize) -> &Rule {
+        &self.rules[index]
+    }
+
+    pub(crate) fn pattern_set(&self) -> &PatternSet {
+        &self.pattern_set
+    }
+
+    /// Returns the reporting rules in rule order.
+    pub(crate) fn report_rules(&self) -> Vec<ReportRule> {
+        self.rules
+            .iter()
+            .map(|rule| ReportRule {
+                id: rule.id.clone(),
+                message: Some(rule.message.clone()),
+                level: rule.severity.to_string(),
+            })
+            .collect()
+    }
+
+    /// Returns a boolean that indicates whether the `rule` is suppressed
+    /// with an inline `recurse:allow(rule-id)` comment in the `line`.
+    pub(crate) fn is_suppressed(&self, rule: &Rule, line: &str) -> bool {
+        self.allow_comment.captures_iter(line).any(|caps| {
+            caps[1]
+                .split(',')
+                .any(|id| id.trim() == rule.id || id.trim() == "*")
+        })
+    }
+}
+
+/// Returns the `GlobSet` of the `globs` glob patterns.
+pub(crate) fn build_globset(globs: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(Glob::new(glob).with_context(|| format!("invalid glob '{}'", glob))?);
+    }
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+[[rule]]
+id = "no-todo"
+regex = "TODO"
+message = "Resolve TODO notes"
+severity = "warning"
+include = ["*.rs"]
+exclude = ["tests/**"]
+allow = ["TODO\\(#\\d+\\)"]
+
+[[rule]]
+id = "no-dbg"
+regex = "dbg!\\("
+message = "Remove dbg! macros"
+"#;
+
+    #[test]
+    fn test_ruleset_from_toml() {
+        let ruleset = RuleSet::from_toml(RULES).unwrap();
+        assert_eq!(ruleset.rules().len(), 2);
+        assert_eq!(ruleset.pattern_set().len(), 2);
+        let todo = ruleset.rule(0);
+        assert_eq!(todo.id, "no-todo");
+        assert_eq!(todo.severity, Severity::Warning);
+        assert_eq!(ruleset.rule(1).severity, Severity::Error);
+        assert!(todo.applies_to(Path::new("src/lib.rs")));
+        assert!(!todo.applies_to(Path::new("src/lib.py")));
+        assert!(!todo.applies_to(Path::new("tests/cli.rs")));
+        assert!(ruleset.rule(1).applies_to(Path::new("tests/cli.py")));
+        assert!(todo.is_allowed("// TODO(#12): tracked"));
+        assert!(!todo.is_allowed("// TODO: untracked"));
+    }
+
+    #[test]
+    fn test_ruleset_inline_suppression() {
+        let ruleset = RuleSet::from_toml(RULES).unwrap();
+        let todo = ruleset.rule(0);
+        assert!(ruleset.is_suppressed(todo, "// TODO later recurse:allow(no-todo)"));
+        assert!(ruleset.is_suppressed(todo, "// TODO recurse:allow(no-dbg, no-todo)"));
+        assert!(ruleset.is_suppressed(todo, "// TODO recurse:allow(*)"));
+        assert!(!ruleset.is_suppressed(todo, "// TODO recurse:allow(no-dbg)"));
+        assert!(!ruleset.is_suppressed(todo, "// TODO"));
+    }
+
+    #[test]
+    fn test_ruleset_invalid_definitions() {
+        assert!(RuleSet::from_toml("").is_err());
+        assert!(
+            RuleSet::from_toml("[[rule]]\nid = \"a\"\nregex = \"(\"\nmessage = \"m\"").is_err()
+        );
+        assert!(RuleSet::from_toml("[[rule]]\nid = \"a\"\nregex = \"a\"").is_err());
+        assert!(RuleSet::from_toml(
+            "[[rule]]\nid = \"a\"\nregex = \"a\"\nmessage = \"m\"\nseverity = \"fatal\""
+        )
+        .is_err());
+        assert!(RuleSet::from_toml(
+            "[[rule]]\nid = \"a\"\nregex = \"a\"\nmessage = \"m\"\n[[rule]]\nid = \"a\"\nregex = \"b\"\nmessage = \"m\""
+        )
+        .is_err());
+    }
+}
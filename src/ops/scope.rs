@@ -0,0 +1,324 @@
+use std::ops::Range;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+
+/// A 1-based inclusive line number range.  The range is open-ended
+/// when the `end` line is not defined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct LineRange {
+    start: usize,
+    end: Option<usize>,
+}
+
+impl LineRange {
+    fn contains(&self, line_number: usize) -> bool {
+        line_number >= self.start && self.end.is_none_or(|end| line_number <= end)
+    }
+}
+
+impl FromStr for LineRange {
+    type Err = anyhow::Error;
+
+    /// Parses the `A:B`, `A:`, and `:B` line range formats.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid line range '{}', expected A:B, A:, or :B", s);
+        let (start, end) = s.split_once(':').ok_or_else(invalid)?;
+        let parse = |number: &str| match number.trim() {
+            "" => Ok(None),
+            number => match number.parse::<usize>() {
+                Ok(0) | Err(_) => Err(invalid()),
+                Ok(number) => Ok(Some(number)),
+            },
+        };
+        let (start, end) = (parse(start)?, parse(end)?);
+        if start.is_none() && end.is_none() {
+            return Err(invalid());
+        }
+        let start = start.unwrap_or(1);
+        if end.is_some_and(|end| end < start) {
+            return Err(anyhow!(
+                "invalid line range '{}', the start line is after the end line",
+                s
+            ));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+/// The lines of a file where matches are reported or replaced.  A line
+/// is in scope when it matches the `in_lines` pattern, is in the
+/// `line_range` range, and is between a start marker line and an end
+/// marker line of a `between` marker pair.  The marker lines are not in
+/// scope and lines after a start marker without an end marker are not
+/// in scope.  All lines are in scope when no criteria are defined.
+#[derive(Debug, Default)]
+pub(crate) struct LineScope {
+    in_lines: Option<Regex>,
+    line_range: Option<LineRange>,
+    between: Vec<(Regex, Regex)>,
+}
+
+impl LineScope {
+    /// Returns the `LineScope` of the `in_lines` regular expression
+    /// pattern, the `line_range` range, and the `between` start and end
+    /// marker regular expression pattern pairs.
+    pub(crate) fn new(
+        in_lines: Option<&str>,
+        line_range: Option<LineRange>,
+        between: &[String],
+    ) -> Result<Self> {
+        let in_lines = match in_lines {
+            Some(pattern) => Some(Regex::new(pattern).context("invalid --in-lines pattern")?),
+            None => None,
+        };
+        let between = between
+            .chunks(2)
+            .map(|markers| match markers {
+                [start, end] => Ok((
+                    Regex::new(start).context("invalid --between start pattern")?,
+                    Regex::new(end).context("invalid --between end pattern")?,
+                )),
+                _ => Err(anyhow!("--between requires a start and an end pattern")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            in_lines,
+            line_range,
+            between,
+        })
+    }
+
+    pub(crate) fn is_defined(&self) -> bool {
+        self.in_lines.is_some() || self.line_range.is_some() || !self.between.is_empty()
+    }
+
+    /// Returns a boolean that indicates whether the scope of a line is
+    /// defined by the line alone.  The `between` marker pairs require the
+    /// lines that follow a line.
+    pub(crate) fn is_line_local(&self) -> bool {
+        self.between.is_empty()
+    }
+
+    /// Returns a boolean that indicates whether the 1-based `line_number`
+    /// line is in the `in_lines` and `line_range` scope.  The `between`
+    /// marker pairs are not evaluated.
+    pub(crate) fn includes_line(&self, line_number: usize, line: &str) -> bool {
+        self.line_range
+            .is_none_or(|line_range| line_range.contains(line_number))
+            && self.in_lines.as_ref().is_none_or(|re| re.is_match(line))
+    }
+
+    /// Returns a boolean for each of the `lines` that indicates whether
+    /// the line is in scope.
+    pub(crate) fn included_lines(&self, lines: &[&str]) -> Vec<bool> {
+        let mut included: Vec<bool> = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| self.includes_line(index + 1, line))
+            .collect();
+        if !self.between.is_empty() {
+            let mut between = vec![false; lines.len()];
+            for (start, end) in &self.between {
+                let mut block: Option<usize> = None;
+                for (index, line) in lines.iter().enumerate() {
+                    match block {
+                        None if start.is_match(line) => block = Some(index + 1),
+                        Some(first) if end.is_match(line) => {
+                            for flag in &mut between[first..index] {
+                                *flag = true;
+                            }
+                            block = None;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            for (flag, between) in included.iter_mut().zip(between) {
+                *flag &= between;
+            }
+        }
+        included
+    }
+
+    /// Returns the byte offset ranges of the in scope lines of the `text`
+    /// string.  The ranges include the line endings.
+    pub(crate) fn ranges(&self, text: &str) -> ScopeRanges {
+        let mut lines = Vec::new();
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            offsets.push(offset..offset + line.len());
+            offset += line.len();
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            lines.push(line.strip_suffix('\r').unwrap_or(line));
+        }
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for (range, included) in offsets.into_iter().zip(self.included_lines(&lines)) {
+            if !included {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ranges.push(range),
+            }
+        }
+        ScopeRanges {
+            ranges,
+            len: text.len(),
+        }
+    }
+}
+
+/// The byte offset ranges of the in scope lines of a string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ScopeRanges {
+    ranges: Vec<Range<usize>>,
+    len: usize,
+}
+
+impl ScopeRanges {
+    /// Returns a boolean that indicates whether the `start` to `end` byte
+    /// offset match is in scope.  Matches that span more than one line
+    /// are in scope when all of the lines are in scope.
+    pub(crate) fn contains(&self, start: usize, end: usize) -> bool {
+        let index = self.ranges.partition_point(|range| range.end <= start);
+        match self.ranges.get(index) {
+            Some(range) => range.start <= start && end <= range.end,
+            // empty matches at the end of a string without a final
+            // line ending
+            None => {
+                start == end
+                    && start == self.len
+                    && self
+                        .ranges
+                        .last()
+                        .is_some_and(|range| range.end == self.len)
+            }
+        }
+    }
+}
+
+/// The selected occurrences of the in scope matches of a find pattern
+/// in a file.  All occurrences are selected when no limits are defined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Occurrences {
+    /// The maximum number of selected occurrences
+    pub(crate) max: Option<usize>,
+    /// The 1-based number of the only selected occurrence
+    pub(crate) nth: Option<usize>,
+}
+
+impl Occurrences {
+    /// Returns a boolean that indicates whether the 1-based `occurrence`
+    /// number is selected.
+    pub(crate) fn is_selected(&self, occurrence: usize) -> bool {
+        self.max.is_none_or(|max| occurrence <= max) && self.nth.is_none_or(|nth| occurrence == nth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "one\n# BEGIN\ntwo x\nthree\n# END\nfour x\n";
+
+    #[test]
+    fn test_line_range_from_str() {
+        assert_eq!(
+            "2:4".parse::<LineRange>().unwrap(),
+            LineRange {
+                start: 2,
+                end: Some(4)
+            }
+        );
+        assert_eq!(
+            ":4".parse::<LineRange>().unwrap(),
+            LineRange {
+                start: 1,
+                end: Some(4)
+            }
+        );
+        assert_eq!(
+            "2:".parse::<LineRange>().unwrap(),
+            LineRange {
+                start: 2,
+                end: None
+            }
+        );
+        for invalid in &["", ":", "2", "0:4", "4:2", "a:b", "-1:2"] {
+            assert!(invalid.parse::<LineRange>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_line_scope_included_lines() {
+        let lines: Vec<&str> = TEXT.lines().collect();
+        let scope = LineScope::default();
+        assert!(!scope.is_defined());
+        assert!(scope.included_lines(&lines).iter().all(|flag| *flag));
+
+        let scope = LineScope::new(Some("x"), None, &[]).unwrap();
+        assert_eq!(
+            scope.included_lines(&lines),
+            vec![false, false, true, false, false, true]
+        );
+        let scope = LineScope::new(None, Some("2:3".parse().unwrap()), &[]).unwrap();
+        assert_eq!(
+            scope.included_lines(&lines),
+            vec![false, true, true, false, false, false]
+        );
+        let between = ["BEGIN".to_string(), "END".to_string()];
+        let scope = LineScope::new(None, None, &between).unwrap();
+        assert_eq!(
+            scope.included_lines(&lines),
+            vec![false, false, true, true, false, false]
+        );
+        let scope = LineScope::new(Some("x"), None, &between).unwrap();
+        assert_eq!(
+            scope.included_lines(&lines),
+            vec![false, false, true, false, false, false]
+        );
+        // an unterminated block is not in scope
+        let unterminated: Vec<&str> = "# BEGIN\na\nb".lines().collect();
+        assert!(scope.included_lines(&unterminated).iter().all(|flag| !flag));
+        assert!(LineScope::new(Some("("), None, &[]).is_err());
+    }
+
+    #[test]
+    fn test_scope_ranges_contains() {
+        let between = ["BEGIN".to_string(), "END".to_string()];
+        let ranges = LineScope::new(None, None, &between).unwrap().ranges(TEXT);
+        let two = TEXT.find("two").unwrap();
+        let four = TEXT.find("four").unwrap();
+        assert!(ranges.contains(two, two + 3));
+        // a match that spans the in scope lines
+        assert!(ranges.contains(two, TEXT.find("three").unwrap() + 5));
+        assert!(!ranges.contains(four, four + 4));
+        assert!(!ranges.contains(0, 3));
+        // a match that spans the end marker line
+        assert!(!ranges.contains(two, four));
+
+        let ranges = LineScope::new(Some("c"), None, &[])
+            .unwrap()
+            .ranges("a\nbc");
+        assert!(ranges.contains(4, 4));
+        assert!(!ranges.contains(0, 0));
+    }
+
+    #[test]
+    fn test_occurrences_is_selected() {
+        assert!(Occurrences::default().is_selected(10));
+        let max = Occurrences {
+            max: Some(2),
+            nth: None,
+        };
+        assert!(max.is_selected(1) && max.is_selected(2) && !max.is_selected(3));
+        let nth = Occurrences {
+            max: None,
+            nth: Some(2),
+        };
+        assert!(!nth.is_selected(1) && nth.is_selected(2) && !nth.is_selected(3));
+    }
+}
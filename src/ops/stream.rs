@@ -0,0 +1,366 @@
+use std::borrow::Cow;
+use std::io::{self, BufRead, ErrorKind, Read, Write};
+
+use crate::ops::newline::{LineEnding, LineEndingChoice, TextFormat};
+use crate::ops::scope::{LineScope, Occurrences};
+use crate::ops::substitution::Substitution;
+use crate::ops::transform::TemplateContext;
+
+const BOM: &str = "\u{feff}";
+
+/// The maximum length in bytes of streamed lines, including the line
+/// ending.
+pub(crate) const MAX_LINE_LEN: usize = 1024 * 1024;
+
+/// A streaming replacement failure.
+#[derive(Debug)]
+pub(crate) enum StreamError {
+    /// A read or write failure.  Streams that are not valid UTF-8 fail
+    /// with the `InvalidData` error kind.
+    Io(io::Error),
+    /// A line that is longer than the maximum line length
+    LineTooLong,
+    /// A replacement failure
+    Replacement(anyhow::Error),
+}
+
+impl From<io::Error> for StreamError {
+    fn from(error: io::Error) -> Self {
+        StreamError::Io(error)
+    }
+}
+
+/// The line scope, occurrence selection, output line endings, and
+/// maximum line length of a streaming replacement.
+pub(crate) struct StreamOptions<'a> {
+    pub(crate) scope: &'a LineScope,
+    pub(crate) occurrences: &'a Occurrences,
+    pub(crate) line_endings: LineEndingChoice,
+    pub(crate) max_line_len: usize,
+}
+
+/// Writes the lines of the `reader` stream to the `writer` stream with
+/// the line local `substitutions` applied in order to each line and
+/// returns the number of replacements.  One line is in memory at a
+/// time.  The `format` of the stream is detected before the stream is
+/// read, and the lines are matched and written like the normalized
+/// contents of files that are read into memory.  Streams with lines
+/// that are longer than the maximum line length fail with a
+/// `LineTooLong` error.
+pub(crate) fn stream_substitutions(
+    mut reader: impl BufRead,
+    writer: impl Write,
+    format: TextFormat,
+    substitutions: &[&Substitution],
+    options: &StreamOptions,
+    context: &TemplateContext,
+) -> Result<usize, StreamError> {
+    let mut writer = FormatWriter::new(writer, format, options.line_endings)?;
+    let mut buffer = Vec::new();
+    let mut occurrences = vec![0; substitutions.len()];
+    let mut replacements = 0;
+    let mut line_number = 0;
+    let mut included = true;
+    loop {
+        buffer.clear();
+        (&mut reader)
+            .take(options.max_line_len as u64 + 1)
+            .read_until(b'\n', &mut buffer)?;
+        if buffer.len() > options.max_line_len {
+            return Err(StreamError::LineTooLong);
+        }
+        let line = std::str::from_utf8(&buffer)
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "stream is not valid UTF-8"))?;
+        line_number += 1;
+        let (mut line, ending) = match line.strip_suffix('\n') {
+            // `\r` characters are part of the lines of files with mixed
+            // line endings
+            Some(line) if format.line_ending == LineEnding::Crlf => {
+                (line.strip_suffix('\r').unwrap_or(line), true)
+            }
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line_number == 1 && format.bom {
+            line = line.strip_prefix(BOM).unwrap_or(line);
+        }
+        // the text after the final line ending is in the scope of the
+        // last line
+        if ending || !line.is_empty() || line_number == 1 {
+            included = options
+                .scope
+                .includes_line(line_number, line.strip_suffix('\r').unwrap_or(line));
+        }
+        let mut text = Cow::Borrowed(line);
+        if included {
+            for (substitution, occurrence) in substitutions.iter().zip(occurrences.iter_mut()) {
+                let (replaced, count) = substitution
+                    .apply_line(&text, options.occurrences, occurrence, context)
+                    .map_err(StreamError::Replacement)?;
+                if let Cow::Owned(replaced) = replaced {
+                    text = Cow::Owned(replaced);
+                }
+                replacements += count;
+            }
+        }
+        writer.write_line(&text, ending)?;
+        if !ending {
+            break;
+        }
+    }
+    writer.finish()?;
+    Ok(replacements)
+}
+
+/// A writer of normalized lines in the format of the original stream
+/// or in the defined line ending style.  The last line is held until
+/// the end of the stream so that the final newline state of the stream
+/// is preserved.
+struct FormatWriter<W> {
+    writer: W,
+    format: TextFormat,
+    line_endings: LineEndingChoice,
+    /// The last non-empty normalized line, including the line ending
+    last: String,
+}
+
+impl<W: Write> FormatWriter<W> {
+    fn new(mut writer: W, format: TextFormat, line_endings: LineEndingChoice) -> io::Result<Self> {
+        if format.bom {
+            writer.write_all(BOM.as_bytes())?;
+        }
+        Ok(Self {
+            writer,
+            format,
+            line_endings,
+            last: String::new(),
+        })
+    }
+
+    /// Writes the previous line and holds the `text` line with a `\n`
+    /// line ending when `line_ending` is set.
+    fn write_line(&mut self, text: &str, line_ending: bool) -> io::Result<()> {
+        if text.is_empty() && !line_ending {
+            return Ok(());
+        }
+        let last = self
+            .format
+            .convert_line_endings(&self.last, self.line_endings);
+        self.writer.write_all(last.as_bytes())?;
+        self.last.clear();
+        self.last.push_str(text);
+        if line_ending {
+            self.last.push('\n');
+        }
+        Ok(())
+    }
+
+    /// Writes the last line with the final newline state of the original
+    /// stream.  Only the final line ending is removed from streams
+    /// without a final newline.
+    fn finish(mut self) -> io::Result<()> {
+        if self.format.final_newline && !self.last.is_empty() && !self.last.ends_with('\n') {
+            self.last.push('\n');
+        } else if !self.format.final_newline && self.last.ends_with('\n') {
+            self.last.pop();
+            if self.last.ends_with('\r') {
+                self.last.pop();
+            }
+        }
+        let last = self
+            .format
+            .convert_line_endings(&self.last, self.line_endings);
+        self.writer.write_all(last.as_bytes())?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::substitution::SubstitutionOptions;
+    use std::cell::Cell;
+    use std::path::Path;
+
+    fn streamed(
+        text: &str,
+        find: &str,
+        replace: &str,
+        scope: &LineScope,
+        occurrences: &Occurrences,
+        line_endings: LineEndingChoice,
+    ) -> (String, usize) {
+        let substitution =
+            Substitution::new(find, replace.to_string(), SubstitutionOptions::default()).unwrap();
+        let mut output = Vec::new();
+        let replacements = stream_substitutions(
+            text.as_bytes(),
+            &mut output,
+            TextFormat::detect_reader(text.as_bytes()).unwrap(),
+            &[&substitution],
+            &StreamOptions {
+                scope,
+                occurrences,
+                line_endings,
+                max_line_len: MAX_LINE_LEN,
+            },
+            &TemplateContext {
+                path: Path::new("a.txt"),
+                counter: &Cell::new(1),
+            },
+        )
+        .unwrap();
+        (String::from_utf8(output).unwrap(), replacements)
+    }
+
+    fn in_memory(
+        text: &str,
+        find: &str,
+        replace: &str,
+        scope: &LineScope,
+        occurrences: &Occurrences,
+        line_endings: LineEndingChoice,
+    ) -> (String, usize) {
+        use crate::ops::newline::FinalNewline;
+        let substitution =
+            Substitution::new(find, replace.to_string(), SubstitutionOptions::default()).unwrap();
+        let format = TextFormat::detect(text);
+        let (replaced, replacements) = substitution
+            .apply(
+                &format.normalize(text),
+                scope,
+                occurrences,
+                &TemplateContext {
+                    path: Path::new("a.txt"),
+                    counter: &Cell::new(1),
+                },
+            )
+            .unwrap();
+        (
+            format.restore(&replaced, line_endings, FinalNewline::Preserve),
+            replacements,
+        )
+    }
+
+    #[test]
+    fn test_stream_substitutions_matches_in_memory_replace() {
+        let texts = [
+            "a test\ntest\n",
+            "\u{feff}a test\r\ntest x\r\nend",
+            "",
+            "test",
+            "one\n\ntwo test\n",
+            "a\ntest",
+            "a\r\nb\n",
+            "test\r\n\ntest x\r\n",
+            "\u{feff}a\n\r\ntest",
+        ];
+        let cases = [
+            ("test", "check"),
+            ("(?m)^", "> "),
+            ("(?m)$", ";"),
+            (r"\btest\b", "check\nmore"),
+            ("x?", "-"),
+            ("test", "test\n\n\n"),
+            ("x", "\r\n"),
+            ("t.*", "[$0]"),
+            ("(?m)[^e\n]$", "!"),
+        ];
+        let scopes = [
+            LineScope::default(),
+            LineScope::new(Some("test"), None, &[]).unwrap(),
+            LineScope::new(None, Some("2:".parse().unwrap()), &[]).unwrap(),
+        ];
+        let limits = [
+            Occurrences::default(),
+            Occurrences {
+                max: None,
+                nth: Some(2),
+            },
+        ];
+        let line_endings = [
+            LineEndingChoice::Preserve,
+            LineEndingChoice::Lf,
+            LineEndingChoice::Crlf,
+        ];
+        for text in &texts {
+            for (find, replace) in &cases {
+                for scope in &scopes {
+                    for occurrences in &limits {
+                        for line_ending in &line_endings {
+                            assert_eq!(
+                                streamed(text, find, replace, scope, occurrences, *line_ending),
+                                in_memory(text, find, replace, scope, occurrences, *line_ending),
+                                "{:?} {:?} {:?} {:?} {:?} {:?}",
+                                text,
+                                find,
+                                replace,
+                                scope,
+                                occurrences,
+                                line_ending
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_substitutions_invalid_utf8() {
+        let substitution =
+            Substitution::new("a", "b".to_string(), SubstitutionOptions::default()).unwrap();
+        let result = stream_substitutions(
+            &b"a\n\xff\n"[..],
+            io::sink(),
+            TextFormat::detect_reader(&b"a\n\xff\n"[..]).unwrap(),
+            &[&substitution],
+            &StreamOptions {
+                scope: &LineScope::default(),
+                occurrences: &Occurrences::default(),
+                line_endings: LineEndingChoice::Preserve,
+                max_line_len: MAX_LINE_LEN,
+            },
+            &TemplateContext {
+                path: Path::new("a.txt"),
+                counter: &Cell::new(1),
+            },
+        );
+        assert!(
+            matches!(result, Err(StreamError::Io(error)) if error.kind() == ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn test_stream_substitutions_line_too_long() {
+        let substitution =
+            Substitution::new("a", "b".to_string(), SubstitutionOptions::default()).unwrap();
+        let stream = |text: &str, max_line_len| {
+            let mut output = Vec::new();
+            stream_substitutions(
+                text.as_bytes(),
+                &mut output,
+                TextFormat::detect(text),
+                &[&substitution],
+                &StreamOptions {
+                    scope: &LineScope::default(),
+                    occurrences: &Occurrences::default(),
+                    line_endings: LineEndingChoice::Preserve,
+                    max_line_len,
+                },
+                &TemplateContext {
+                    path: Path::new("a.txt"),
+                    counter: &Cell::new(1),
+                },
+            )
+            .map(|replacements| (String::from_utf8(output).unwrap(), replacements))
+        };
+        // the maximum line length includes the line ending
+        assert_eq!(stream("aa\na", 3).unwrap(), ("bb\nb".to_string(), 3));
+        assert!(matches!(stream("aaa\na", 3), Err(StreamError::LineTooLong)));
+        assert!(matches!(
+            stream("a\naaaa", 3),
+            Err(StreamError::LineTooLong)
+        ));
+    }
+}
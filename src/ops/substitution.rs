@@ -0,0 +1,566 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use globset::GlobSet;
+use regex::{Captures, Regex};
+use regex_syntax::hir::{Anchor, Class, Hir, HirKind, Literal};
+use regex_syntax::Parser;
+
+use crate::ops::case::{case_insensitive_pattern, preserve_case};
+use crate::ops::external::ExternalCommand;
+use crate::ops::path::path_has_extension;
+use crate::ops::pattern::GroupRef;
+use crate::ops::scope::{LineScope, Occurrences};
+use crate::ops::transform::{ReplaceTemplate, TemplateContext};
+
+/// The options that modify the matches and replacements of all
+/// substitutions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct SubstitutionOptions {
+    /// Match case-insensitively and reproduce the letter case pattern of
+    /// each match in the replacement
+    pub(crate) preserve_case: bool,
+    /// Insert the replacement string without capture group expansion
+    pub(crate) literal: bool,
+    /// Evaluate the replacement string as a template with case
+    /// conversions and variables
+    pub(crate) template: bool,
+}
+
+/// A compiled find and replace pair.  The pair is applied to a file
+/// when the file path matches an `include` glob (or no `include` globs
+/// are defined), does not match an `exclude` glob, and has the
+/// `extension` file extension when one is defined.
+#[derive(Debug)]
+pub(crate) struct Substitution {
+    pub(crate) re: Regex,
+    pub(crate) replace: String,
+    options: SubstitutionOptions,
+    template: Option<ReplaceTemplate>,
+    command: Option<ExternalCommand>,
+    line_local: bool,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    extension: Option<String>,
+}
+
+impl Substitution {
+    /// Returns an unscoped `Substitution` of the `find` regular
+    /// expression pattern that applies to all files.  The capture group
+    /// references in the `replace` string are validated unless the
+    /// replacement is literal, and template replacements are parsed.
+    pub(crate) fn new(find: &str, replace: String, options: SubstitutionOptions) -> Result<Self> {
+        let re = match options.preserve_case {
+            true => Regex::new(&case_insensitive_pattern(find))?,
+            false => Regex::new(find)?,
+        };
+        let template = match options.template {
+            true => Some(ReplaceTemplate::parse(&replace, &re)?),
+            false => None,
+        };
+        if !options.literal && !options.template {
+            validate_group_refs(&re, &replace)?;
+        }
+        Ok(Self {
+            replace,
+            options,
+            template,
+            command: None,
+            line_local: is_line_local(re.as_str()),
+            re,
+            include: None,
+            exclude: GlobSet::empty(),
+            extension: None,
+        })
+    }
+
+    /// Returns the substitution with replacements from the `command`
+    /// external command.  The command input capture group must be
+    /// defined in the find pattern.
+    pub(crate) fn with_command(self, command: ExternalCommand) -> Result<Self> {
+        if !has_group(&self.re, &command.group.to_string()) {
+            return Err(anyhow!(
+                "command input group '{}' is not a capture group in the find pattern",
+                command.group
+            ));
+        }
+        Ok(Self {
+            command: Some(command),
+            ..self
+        })
+    }
+
+    /// Returns the substitution limited to the files that match an
+    /// `include` glob (or all files when `include` is `None`), do not
+    /// match an `exclude` glob, and have the `extension` file extension
+    /// when one is defined.
+    pub(crate) fn with_scope(
+        self,
+        include: Option<GlobSet>,
+        exclude: GlobSet,
+        extension: Option<String>,
+    ) -> Self {
+        Self {
+            include,
+            exclude,
+            extension,
+            ..self
+        }
+    }
+
+    /// Returns a boolean that indicates whether the find pattern matches
+    /// are limited to a single line.  Patterns that cannot match line
+    /// ending characters or the start and end of the text match the same
+    /// text in a file and in each line of the file.
+    pub(crate) fn is_line_local(&self) -> bool {
+        self.line_local
+    }
+
+    /// Returns a boolean that indicates whether the substitution applies
+    /// to the `filepath` file.  The path is relative to the traversal
+    /// start path.
+    pub(crate) fn applies_to(&self, filepath: &Path) -> bool {
+        let included = match &self.include {
+            Some(include) => include.is_match(filepath),
+            None => true,
+        };
+        let has_extension = match &self.extension {
+            Some(extension) => path_has_extension(filepath, extension),
+            None => true,
+        };
+        included && has_extension && !self.exclude.is_match(filepath)
+    }
+
+    /// Returns the replacement string of the `caps` match with the
+    /// capture group references expanded.  Template replacements are
+    /// evaluated in the `context` file and external command replacements
+    /// fail when the command fails.
+    pub(crate) fn replacement(&self, caps: &Captures, context: &TemplateContext) -> Result<String> {
+        let replacement = match (&self.command, &self.template, self.options.literal) {
+            (Some(command), _, _) => command.replacement(caps)?,
+            (None, Some(template), _) => template.render(caps, context),
+            (None, None, true) => self.replace.clone(),
+            (None, None, false) => {
+                let mut replacement = String::new();
+                caps.expand(&self.replace, &mut replacement);
+                replacement
+            }
+        };
+        Ok(match self.options.preserve_case {
+            true => preserve_case(&caps[0], &replacement),
+            false => replacement,
+        })
+    }
+
+    /// Returns the `text` with the selected `occurrences` of the matches
+    /// in the `scope` lines replaced and the number of replacements.  The
+    /// `context` counter is incremented for each replacement.  The first
+    /// replacement failure is returned as an error.
+    pub(crate) fn apply(
+        &self,
+        text: &str,
+        scope: &LineScope,
+        occurrences: &Occurrences,
+        context: &TemplateContext,
+    ) -> Result<(String, usize)> {
+        if !self.re.is_match(text) {
+            return Ok((text.to_string(), 0));
+        }
+        let ranges = match scope.is_defined() {
+            true => Some(scope.ranges(text)),
+            false => None,
+        };
+        let mut occurrence = 0;
+        self.replace_matches(
+            text,
+            |start, end| {
+                ranges
+                    .as_ref()
+                    .is_none_or(|ranges| ranges.contains(start, end))
+            },
+            occurrences,
+            &mut occurrence,
+            context,
+        )
+        .map(|(replaced, replacements)| (replaced.into_owned(), replacements))
+    }
+
+    /// Returns the `line` with the selected `occurrences` of the matches
+    /// replaced and the number of replacements.  The `occurrence` number
+    /// of the last match in the previous lines of the file is updated.
+    pub(crate) fn apply_line<'t>(
+        &self,
+        line: &'t str,
+        occurrences: &Occurrences,
+        occurrence: &mut usize,
+        context: &TemplateContext,
+    ) -> Result<(Cow<'t, str>, usize)> {
+        self.replace_matches(line, |_, _| true, occurrences, occurrence, context)
+    }
+
+    /// Returns the `text` with the selected `occurrences` of the matches
+    /// that are `in_scope` replaced and the number of replacements.  The
+    /// first replacement failure is returned as an error.
+    fn replace_matches<'t>(
+        &self,
+        text: &'t str,
+        in_scope: impl Fn(usize, usize) -> bool,
+        occurrences: &Occurrences,
+        occurrence: &mut usize,
+        context: &TemplateContext,
+    ) -> Result<(Cow<'t, str>, usize)> {
+        let mut replacements = 0;
+        let mut failure = None;
+        let replaced = self.re.replace_all(text, |caps: &Captures| {
+            let mat = caps.get(0).unwrap();
+            if failure.is_some() || !in_scope(mat.start(), mat.end()) {
+                return mat.as_str().to_string();
+            }
+            *occurrence += 1;
+            if !occurrences.is_selected(*occurrence) {
+                return mat.as_str().to_string();
+            }
+            match self.replacement(caps, context) {
+                Ok(replacement) => {
+                    replacements += 1;
+                    context.counter.set(context.counter.get() + 1);
+                    replacement
+                }
+                Err(error) => {
+                    failure = Some(error);
+                    mat.as_str().to_string()
+                }
+            }
+        });
+        match failure {
+            Some(error) => Err(error),
+            None => Ok((replaced, replacements)),
+        }
+    }
+}
+
+/// Returns the `text` with the `substitutions` applied in order and the
+/// total number of replacements.  Each substitution is applied to the
+/// result of the previous substitution and the `scope` lines are
+/// evaluated on the result of the previous substitution.  The
+/// `occurrences` are selected for each substitution.  The first
+/// replacement failure is returned as an error.
+pub(crate) fn apply_all(
+    substitutions: &[&Substitution],
+    scope: &LineScope,
+    occurrences: &Occurrences,
+    context: &TemplateContext,
+    text: &str,
+) -> Result<(String, usize)> {
+    let mut replaced = text.to_string();
+    let mut replacements = 0;
+    for substitution in substitutions {
+        let (next, count) = substitution.apply(&replaced, scope, occurrences, context)?;
+        replaced = next;
+        replacements += count;
+    }
+    Ok((replaced, replacements))
+}
+
+/// Returns a boolean that indicates whether the matches of the `pattern`
+/// regular expression are limited to a single line.  The pattern must
+/// not match `\n` characters and must not contain the `^` and `$` start
+/// and end of text anchors that are not line anchors.  `\r` characters
+/// are part of the lines of files with mixed line endings and are not
+/// in the lines of CRLF files.  Patterns that cannot be parsed are not
+/// line local.
+fn is_line_local(pattern: &str) -> bool {
+    match Parser::new().parse(pattern) {
+        Ok(hir) => is_line_local_hir(&hir),
+        Err(_) => false,
+    }
+}
+
+fn is_line_local_hir(hir: &Hir) -> bool {
+    let newline = u32::from('\n');
+    match hir.kind() {
+        HirKind::Empty | HirKind::WordBoundary(_) => true,
+        HirKind::Literal(Literal::Unicode(c)) => u32::from(*c) != newline,
+        HirKind::Literal(Literal::Byte(b)) => u32::from(*b) != newline,
+        HirKind::Class(Class::Unicode(class)) => !class
+            .iter()
+            .any(|range| (u32::from(range.start())..=u32::from(range.end())).contains(&newline)),
+        HirKind::Class(Class::Bytes(class)) => !class
+            .iter()
+            .any(|range| (u32::from(range.start())..=u32::from(range.end())).contains(&newline)),
+        HirKind::Anchor(Anchor::StartLine) | HirKind::Anchor(Anchor::EndLine) => true,
+        HirKind::Anchor(_) => false,
+        HirKind::Repetition(repetition) => is_line_local_hir(&repetition.hir),
+        HirKind::Group(group) => is_line_local_hir(&group.hir),
+        HirKind::Concat(hirs) | HirKind::Alternation(hirs) => hirs.iter().all(is_line_local_hir),
+    }
+}
+
+/// A capture group reference in a replacement string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ReplacementRef<'a> {
+    /// The reference name between the `$` or `${` and `}` delimiters
+    name: &'a str,
+    braced: bool,
+}
+
+/// Returns the capture group references of the `replace` replacement
+/// string with the `regex` crate expansion syntax.  `$$` is a literal
+/// `$`, `${name}` references the group between the braces, and `$name`
+/// references the group with the longest name of letters, digits, and
+/// underscores.  A `$` that is not followed by a name is literal.
+fn parse_group_refs(replace: &str) -> Result<Vec<ReplacementRef<'_>>> {
+    let mut refs = Vec::new();
+    let bytes = replace.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b'$' {
+            index += 1;
+            continue;
+        }
+        match bytes.get(index + 1) {
+            Some(b'$') => index += 2,
+            Some(b'{') => {
+                let end = replace[index + 2..].find('}').ok_or_else(|| {
+                    anyhow!(
+                        "unterminated capture group reference '{}' in replacement, use $$ for a literal $",
+                        &replace[index..]
+                    )
+                })?;
+                refs.push(ReplacementRef {
+                    name: &replace[index + 2..index + 2 + end],
+                    braced: true,
+                });
+                index += end + 3;
+            }
+            _ => {
+                let length = bytes[index + 1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                    .count();
+                if length > 0 {
+                    refs.push(ReplacementRef {
+                        name: &replace[index + 1..index + 1 + length],
+                        braced: false,
+                    });
+                }
+                index += length + 1;
+            }
+        }
+    }
+    Ok(refs)
+}
+
+/// Returns a boolean that indicates whether the `name` capture group
+/// index or name is defined in the `re` pattern.
+pub(crate) fn has_group(re: &Regex, name: &str) -> bool {
+    match name.parse::<GroupRef>() {
+        Ok(GroupRef::Index(index)) => index < re.captures_len(),
+        Ok(GroupRef::Name(name)) => re.capture_names().any(|group| group == Some(&name)),
+        Err(_) => false,
+    }
+}
+
+/// Returns an error when a capture group reference in the `replace`
+/// replacement string is not defined in the `re` pattern.  Unbraced
+/// references that begin with a defined group (e.g., `$1abc`) are
+/// reported with the braced `${1}abc` syntax that separates the group
+/// from the text that follows.
+fn validate_group_refs(re: &Regex, replace: &str) -> Result<()> {
+    for group_ref in parse_group_refs(replace)? {
+        if has_group(re, group_ref.name) {
+            continue;
+        }
+        let reference = match group_ref.braced {
+            true => format!("${{{}}}", group_ref.name),
+            false => format!("${}", group_ref.name),
+        };
+        let prefix = (1..group_ref.name.len())
+            .rev()
+            .map(|end| &group_ref.name[..end])
+            .find(|prefix| has_group(re, prefix));
+        return Err(match (group_ref.braced, prefix) {
+            (false, Some(prefix)) => anyhow!(
+                "replacement reference '{}' is the undefined capture group '{}', use '${{{}}}{}' to reference group '{}' followed by text",
+                reference,
+                group_ref.name,
+                prefix,
+                &group_ref.name[prefix.len()..],
+                prefix
+            ),
+            _ => anyhow!(
+                "replacement reference '{}' is not a capture group in the find pattern, use $$ for a literal $ or the --literal-replacement option",
+                reference
+            ),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_substitution_group_refs() {
+        let options = SubstitutionOptions::default();
+        let valid = ["${1}abc", "$1 $name ${name}", "$$1 costs $", "$ 5", "${0}"];
+        for replace in &valid {
+            assert!(
+                Substitution::new(r"(?P<name>\w+)", replace.to_string(), options).is_ok(),
+                "{}",
+                replace
+            );
+        }
+        let error = Substitution::new(r"(\w+)", "$1abc".to_string(), options).unwrap_err();
+        assert!(error.to_string().contains("use '${1}abc'"));
+        let error =
+            Substitution::new(r"(?P<year>\d+)", "$year_x".to_string(), options).unwrap_err();
+        assert!(error.to_string().contains("use '${year}_x'"));
+        for invalid in &["$2", "${2}", "$name", "${1", "${}"] {
+            assert!(
+                Substitution::new(r"(\w+)", invalid.to_string(), options).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_substitution_literal_replacement() {
+        let options = SubstitutionOptions {
+            literal: true,
+            ..SubstitutionOptions::default()
+        };
+        let substitution =
+            Substitution::new(r"(\w+)", "$1abc ${x} $$".to_string(), options).unwrap();
+        let context = TemplateContext {
+            path: Path::new("a.txt"),
+            counter: &Cell::new(1),
+        };
+        assert_eq!(
+            substitution
+                .apply(
+                    "price",
+                    &LineScope::default(),
+                    &Occurrences::default(),
+                    &context
+                )
+                .unwrap(),
+            ("$1abc ${x} $$".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn test_substitution_template_replacement() {
+        let options = SubstitutionOptions {
+            template: true,
+            ..SubstitutionOptions::default()
+        };
+        let substitution =
+            Substitution::new(r"item_(\w+)", r"\u${1}${n}".to_string(), options).unwrap();
+        let counter = Cell::new(1);
+        let context = TemplateContext {
+            path: Path::new("a.txt"),
+            counter: &counter,
+        };
+        assert_eq!(
+            substitution
+                .apply(
+                    "item_a item_b item_c",
+                    &LineScope::default(),
+                    &Occurrences {
+                        max: Some(2),
+                        nth: None
+                    },
+                    &context
+                )
+                .unwrap(),
+            ("A1 B2 item_c".to_string(), 2)
+        );
+        assert_eq!(counter.get(), 3);
+        assert!(Substitution::new(r"(\w+)", r"\q".to_string(), options).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_substitution_command_replacement() {
+        use crate::ops::external::ExternalCommand;
+        use std::time::Duration;
+
+        let command = |group: &str| {
+            ExternalCommand::new(
+                "tr a-z A-Z".to_string(),
+                group.parse().unwrap(),
+                Duration::from_secs(10),
+            )
+        };
+        let options = SubstitutionOptions::default();
+        let substitution = Substitution::new(r"id=(\w+)", String::new(), options)
+            .unwrap()
+            .with_command(command("1"))
+            .unwrap();
+        let context = TemplateContext {
+            path: Path::new("a.txt"),
+            counter: &Cell::new(1),
+        };
+        assert_eq!(
+            apply_all(
+                &[&substitution],
+                &LineScope::default(),
+                &Occurrences::default(),
+                &context,
+                "id=ab id=c"
+            )
+            .unwrap(),
+            ("AB C".to_string(), 2)
+        );
+        assert!(Substitution::new(r"id=(\w+)", String::new(), options)
+            .unwrap()
+            .with_command(command("2"))
+            .is_err());
+        let failing = Substitution::new(r"\w+", String::new(), options)
+            .unwrap()
+            .with_command(ExternalCommand::new(
+                "exit 1".to_string(),
+                "0".parse().unwrap(),
+                Duration::from_secs(10),
+            ))
+            .unwrap();
+        assert!(failing
+            .apply(
+                "a",
+                &LineScope::default(),
+                &Occurrences::default(),
+                &context
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_substitution_is_line_local() {
+        let options = SubstitutionOptions::default();
+        for find in &[
+            r"test",
+            r"(?m)^[ \t]*test$",
+            r"[^\s]+",
+            r"\bid=\w+",
+            r"a|b*",
+            r".",
+            r"version = .*",
+            r"[^x\n]+",
+            r"a\r",
+        ] {
+            let substitution = Substitution::new(find, String::new(), options).unwrap();
+            assert!(substitution.is_line_local(), "{}", find);
+        }
+        for find in &[
+            r"a\nb", r"\s+", r"[^x]", r"(?s)a.b", r"(?s).*", r"^a", r"a$", r"\Aa",
+        ] {
+            let substitution = Substitution::new(find, String::new(), options).unwrap();
+            assert!(!substitution.is_line_local(), "{}", find);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{anyhow, Result};
+
+use crate::ops::pattern::GroupRef;
+
+/// A template output field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Field {
+    Path,
+    Line,
+    Column,
+    Start,
+    End,
+    Match,
+    Pattern,
+    Group(GroupRef),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(Field),
+}
+
+/// A parsed output template.  Fields are defined with `{name}` syntax,
+/// the `{{` and `}}` sequences are literal braces, and the `\t`, `\n`,
+/// and `\\` escape sequences are supported in literal text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Returns a parsed `Template` for the `template` string.  The
+    /// `path`, `line`, `col`, `start`, `end`, `match`, and `pattern`
+    /// field names are reserved.  All other field names are capture
+    /// group indices or capture group names.
+    pub(crate) fn parse(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => literal.push('\t'),
+                    Some('n') => literal.push('\n'),
+                    Some('\\') => literal.push('\\'),
+                    Some(other) => {
+                        literal.push('\\');
+                        literal.push(other);
+                    }
+                    None => literal.push('\\'),
+                },
+                '{' => {
+                    if chars.as_str().starts_with('{') {
+                        chars.next();
+                        literal.push('{');
+                        continue;
+                    }
+                    let rest = chars.as_str();
+                    let close = rest
+                        .find('}')
+                        .ok_or_else(|| anyhow!("unclosed '{{' in format template"))?;
+                    let name = &rest[..close];
+                    let field = match name {
+                        "path" => Field::Path,
+                        "line" => Field::Line,
+                        "col" => Field::Column,
+                        "start" => Field::Start,
+                        "end" => Field::End,
+                        "match" => Field::Match,
+                        "pattern" => Field::Pattern,
+                        _ => Field::Group(
+                            name.parse()
+                                .map_err(|_| anyhow!("empty field name in format template"))?,
+                        ),
+                    };
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field(field));
+                    chars = rest[close + 1..].chars();
+                }
+                '}' => {
+                    if chars.as_str().starts_with('}') {
+                        chars.next();
+                        literal.push('}');
+                    } else {
+                        return Err(anyhow!("unmatched '}}' in format template"));
+                    }
+                }
+                _ => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Returns an iterator over the capture group references in the template.
+    pub(crate) fn groups(&self) -> impl Iterator<Item = &GroupRef> {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Field(Field::Group(group)) => Some(group),
+            _ => None,
+        })
+    }
+
+    /// Returns the rendered template string.  The `resolve` closure
+    /// returns the value of each template field.
+    pub(crate) fn render<F>(&self, mut resolve: F) -> String
+    where
+        F: FnMut(&Field) -> String,
+    {
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => rendered.push_str(text),
+                Segment::Field(field) => rendered.push_str(&resolve(field)),
+            }
+        }
+        rendered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_test_fields(field: &Field) -> String {
+        match field {
+            Field::Path => "a.txt".to_string(),
+            Field::Line => "2".to_string(),
+            Field::Column => "5".to_string(),
+            Field::Group(GroupRef::Index(i)) => format!("g{}", i),
+            Field::Group(GroupRef::Name(n)) => format!("n{}", n),
+            _ => "x".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_template_parse_and_render() {
+        let t = Template::parse(r"{path}:{line}:{col}\t{name}={1}").unwrap();
+        assert_eq!(t.render(render_test_fields), "a.txt:2:5\tnname=g1");
+        assert_eq!(
+            t.groups().collect::<Vec<&GroupRef>>(),
+            vec![&GroupRef::Name("name".to_string()), &GroupRef::Index(1)]
+        );
+    }
+
+    #[test]
+    fn test_template_escapes() {
+        let t = Template::parse(r"{{{line}}}\n\\\q").unwrap();
+        assert_eq!(t.render(render_test_fields), "{2}\n\\\\q");
+    }
+
+    #[test]
+    fn test_template_literal_only() {
+        let t = Template::parse("plain").unwrap();
+        assert_eq!(t.render(render_test_fields), "plain");
+        assert_eq!(t.groups().count(), 0);
+    }
+
+    #[test]
+    fn test_template_parse_errors() {
+        assert!(Template::parse("{path").is_err());
+        assert!(Template::parse("path}").is_err());
+        assert!(Template::parse("{}").is_err());
+    }
+}
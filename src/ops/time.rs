@@ -0,0 +1,97 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A UTC date and time with one second resolution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct UtcDateTime {
+    pub(crate) year: i64,
+    pub(crate) month: u32,
+    pub(crate) day: u32,
+    pub(crate) hour: u32,
+    pub(crate) minute: u32,
+    pub(crate) second: u32,
+}
+
+impl UtcDateTime {
+    /// Returns the current UTC date and time.
+    pub(crate) fn now() -> Self {
+        Self::from_system_time(SystemTime::now())
+    }
+
+    /// Returns the UTC date and time of the `time` system time.  Times
+    /// before the Unix epoch are clamped to the epoch.
+    pub(crate) fn from_system_time(time: SystemTime) -> Self {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        Self::from_unix_secs(secs)
+    }
+
+    /// Returns the UTC date and time of the `secs` seconds since the
+    /// Unix epoch.
+    pub(crate) fn from_unix_secs(secs: u64) -> Self {
+        let days = (secs / 86_400) as i64;
+        let day_secs = (secs % 86_400) as u32;
+        // civil date from days since the epoch in the proleptic
+        // Gregorian calendar (H. Hinnant, chrono-Compatible Low-Level
+        // Date Algorithms)
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Self {
+            year,
+            month,
+            day,
+            hour: day_secs / 3_600,
+            minute: day_secs % 3_600 / 60,
+            second: day_secs % 60,
+        }
+    }
+
+    /// Returns the ISO 8601 basic format representation that is safe
+    /// to use in file names (e.g., `20211018T153012Z`).
+    pub(crate) fn compact(&self) -> String {
+        format!(
+            "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// Formats the date and time in the RFC 3339 format (e.g.,
+/// `2021-10-18T15:30:12Z`).
+impl fmt::Display for UtcDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utc_date_time_from_unix_secs() {
+        let epoch = UtcDateTime::from_unix_secs(0);
+        assert_eq!(epoch.to_string(), "1970-01-01T00:00:00Z");
+        let leap_day = UtcDateTime::from_unix_secs(951_825_600);
+        assert_eq!(leap_day.to_string(), "2000-02-29T12:00:00Z");
+        let time = UtcDateTime::from_unix_secs(1_634_571_012);
+        assert_eq!(time.to_string(), "2021-10-18T15:30:12Z");
+        assert_eq!(time.compact(), "20211018T153012Z");
+        assert_eq!(
+            UtcDateTime::from_unix_secs(4_107_542_399).to_string(),
+            "2100-02-28T23:59:59Z"
+        );
+    }
+}
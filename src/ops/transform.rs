@@ -0,0 +1,331 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::env;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use regex::{Captures, Regex};
+
+use crate::ops::pattern::GroupRef;
+use crate::ops::substitution::has_group;
+use crate::ops::time::UtcDateTime;
+
+/// The replacement template counter scope.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CounterScope {
+    /// One counter for all files in a run
+    Run,
+    /// A counter that restarts in each file
+    File,
+}
+
+impl FromStr for CounterScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "run" => Ok(CounterScope::Run),
+            "file" => Ok(CounterScope::File),
+            _ => Err(anyhow!("invalid counter scope '{}'", s)),
+        }
+    }
+}
+
+/// The file state of the replacement template evaluation.
+#[derive(Debug)]
+pub(crate) struct TemplateContext<'a> {
+    /// The file path
+    pub(crate) path: &'a Path,
+    /// The `${n}` counter value of the next replacement
+    pub(crate) counter: &'a Cell<usize>,
+}
+
+/// A letter case conversion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Case {
+    Upper,
+    Lower,
+}
+
+impl Case {
+    fn convert(&self, c: char, converted: &mut String) {
+        match self {
+            Case::Upper => converted.extend(c.to_uppercase()),
+            Case::Lower => converted.extend(c.to_lowercase()),
+        }
+    }
+}
+
+/// A replacement template segment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Group(GroupRef),
+    Counter,
+    Path,
+    Stem,
+    /// The case conversion of the text that follows, `None` ends the
+    /// conversion
+    CaseSpan(Option<Case>),
+    /// The case conversion of the next character
+    CaseNext(Case),
+}
+
+/// A parsed replacement template.  Templates extend the replacement
+/// capture group references with `\U`, `\L`, `\u`, `\l`, and `\E` case
+/// conversions and the `${n}`, `${path}`, `${stem}`, `${date}`, and
+/// `${env:NAME}` variables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ReplaceTemplate {
+    segments: Vec<Segment>,
+}
+
+impl ReplaceTemplate {
+    /// Returns the parsed `ReplaceTemplate` of the `template` string for
+    /// matches of the `re` pattern.  Capture group references must be
+    /// defined in the pattern and the variable names must not be capture
+    /// group names.  The `${date}` and `${env:NAME}` variables are
+    /// evaluated once.
+    pub(crate) fn parse(template: &str, re: &Regex) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            let segment = match c {
+                '\\' => match chars.next() {
+                    Some('U') => Segment::CaseSpan(Some(Case::Upper)),
+                    Some('L') => Segment::CaseSpan(Some(Case::Lower)),
+                    Some('E') => Segment::CaseSpan(None),
+                    Some('u') => Segment::CaseNext(Case::Upper),
+                    Some('l') => Segment::CaseNext(Case::Lower),
+                    Some('t') => Segment::Literal("\t".to_string()),
+                    Some('n') => Segment::Literal("\n".to_string()),
+                    Some('\\') => Segment::Literal("\\".to_string()),
+                    Some(other) => {
+                        return Err(anyhow!(
+                            "unsupported escape '\\{}' in replacement template, use \\\\ for a literal \\",
+                            other
+                        ))
+                    }
+                    None => {
+                        return Err(anyhow!(
+                            "trailing '\\' in replacement template, use \\\\ for a literal \\"
+                        ))
+                    }
+                },
+                '$' => {
+                    let rest = chars.as_str();
+                    if let Some(after) = rest.strip_prefix('$') {
+                        chars = after.chars();
+                        Segment::Literal("$".to_string())
+                    } else if let Some(after) = rest.strip_prefix('{') {
+                        let close = after.find('}').ok_or_else(|| {
+                            anyhow!(
+                                "unterminated reference '${}' in replacement template, use $$ for a literal $",
+                                rest
+                            )
+                        })?;
+                        chars = after[close + 1..].chars();
+                        variable(&after[..close], re)?
+                    } else {
+                        let length = rest
+                            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                            .unwrap_or(rest.len());
+                        if length == 0 {
+                            // a `$` that is not followed by a name is literal
+                            Segment::Literal("$".to_string())
+                        } else {
+                            let name = &rest[..length];
+                            chars = rest[length..].chars();
+                            if !has_group(re, name) {
+                                return Err(anyhow!(
+                                    "replacement template reference '${}' is not a capture group in the find pattern, use braces to separate a reference from the text that follows (e.g., '${{1}}abc')",
+                                    name
+                                ));
+                            }
+                            Segment::Group(name.parse()?)
+                        }
+                    }
+                }
+                _ => {
+                    literal.push(c);
+                    continue;
+                }
+            };
+            match segment {
+                Segment::Literal(text) => literal.push_str(&text),
+                segment => {
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(segment);
+                }
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Returns the replacement string of the `caps` match in the
+    /// `context` file.  A case conversion applies until the next `\E` or
+    /// case conversion, and a next character conversion takes precedence
+    /// for the first character that follows it.
+    pub(crate) fn render(&self, caps: &Captures, context: &TemplateContext) -> String {
+        let mut rendered = String::new();
+        let mut span: Option<Case> = None;
+        let mut next: Option<Case> = None;
+        for segment in &self.segments {
+            let text: Cow<str> = match segment {
+                Segment::Literal(text) => Cow::Borrowed(text),
+                Segment::Group(GroupRef::Index(index)) => {
+                    Cow::Borrowed(caps.get(*index).map_or("", |mat| mat.as_str()))
+                }
+                Segment::Group(GroupRef::Name(name)) => {
+                    Cow::Borrowed(caps.name(name).map_or("", |mat| mat.as_str()))
+                }
+                Segment::Counter => Cow::Owned(context.counter.get().to_string()),
+                Segment::Path => context.path.to_string_lossy(),
+                Segment::Stem => context
+                    .path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy())
+                    .unwrap_or_default(),
+                Segment::CaseSpan(case) => {
+                    span = *case;
+                    continue;
+                }
+                Segment::CaseNext(case) => {
+                    next = Some(*case);
+                    continue;
+                }
+            };
+            for c in text.chars() {
+                match next.take().or(span) {
+                    Some(case) => case.convert(c, &mut rendered),
+                    None => rendered.push(c),
+                }
+            }
+        }
+        rendered
+    }
+}
+
+/// Returns the segment of the `name` braced reference.  Names that are
+/// not variables are capture group references of the `re` pattern, and
+/// a variable name that is also a capture group name is an error.
+fn variable(name: &str, re: &Regex) -> Result<Segment> {
+    let segment = match name {
+        "n" => Segment::Counter,
+        "path" => Segment::Path,
+        "stem" => Segment::Stem,
+        "date" => {
+            let now = UtcDateTime::now();
+            Segment::Literal(format!("{:04}-{:02}-{:02}", now.year, now.month, now.day))
+        }
+        _ => {
+            if let Some(key) = name.strip_prefix("env:") {
+                return env::var(key).map(Segment::Literal).map_err(|_| {
+                    anyhow!(
+                        "environment variable '{}' in replacement template is not defined",
+                        key
+                    )
+                });
+            }
+            if !has_group(re, name) {
+                return Err(anyhow!(
+                    "replacement template reference '${{{}}}' is not a capture group in the find pattern or a template variable",
+                    name
+                ));
+            }
+            return Ok(Segment::Group(name.parse()?));
+        }
+    };
+    if has_group(re, name) {
+        return Err(anyhow!(
+            "replacement template variable '${{{}}}' is ambiguous with the '{}' capture group name",
+            name,
+            name
+        ));
+    }
+    Ok(segment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(find: &str, template: &str, text: &str, path: &str, counter: usize) -> String {
+        let re = Regex::new(find).unwrap();
+        let template = ReplaceTemplate::parse(template, &re).unwrap();
+        let caps = re.captures(text).unwrap();
+        let counter = Cell::new(counter);
+        template.render(
+            &caps,
+            &TemplateContext {
+                path: Path::new(path),
+                counter: &counter,
+            },
+        )
+    }
+
+    #[test]
+    fn test_replace_template_case_conversions() {
+        let find = r"(\w+)_(\w+)";
+        assert_eq!(render(find, r"\U$1\E_$2", "get_name", "a", 1), "GET_name");
+        assert_eq!(render(find, r"\L$1 \U$2", "GET_NAME", "a", 1), "get NAME");
+        assert_eq!(render(find, r"$1\u$2", "get_name", "a", 1), "getName");
+        assert_eq!(render(find, r"\u\L$1", "GET_NAME", "a", 1), "Get");
+        assert_eq!(render(find, r"\l$1", "GET_NAME", "a", 1), "gET");
+        assert_eq!(render(find, r"\Ux\tb\\", "a_b", "a", 1), "X\tB\\");
+    }
+
+    #[test]
+    fn test_replace_template_variables() {
+        let find = r"(?P<key>v)(\d)";
+        assert_eq!(
+            render(find, "${key}-${n}-$$-${2}", "v2", "docs/a.md", 7),
+            "v-7-$-2"
+        );
+        assert_eq!(
+            render(find, r"${path} ${stem} \U${stem}", "v2", "docs/a.md", 1),
+            "docs/a.md a A"
+        );
+        let date = render(find, "${date}", "v2", "a", 1);
+        assert_eq!(date.len(), 10);
+        assert_eq!(&date[4..5], "-");
+        let key = env::vars().next().unwrap().0;
+        assert_eq!(
+            render(find, &format!("${{env:{}}}", key), "v2", "a", 1),
+            env::var(&key).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_replace_template_invalid() {
+        let re = Regex::new(r"(\w+)").unwrap();
+        for invalid in &[
+            "$2",
+            "${x}",
+            "${1",
+            r"\q",
+            "a\\",
+            "${env:RECURSE_UNDEFINED_TEMPLATE_VARIABLE}",
+        ] {
+            assert!(ReplaceTemplate::parse(invalid, &re).is_err(), "{}", invalid);
+        }
+        let re = Regex::new(r"(?P<n>\d)").unwrap();
+        let error = ReplaceTemplate::parse("${n}", &re).unwrap_err();
+        assert!(error.to_string().contains("ambiguous"));
+        assert!(ReplaceTemplate::parse("$n", &re).is_ok());
+    }
+
+    #[test]
+    fn test_counter_scope_from_str() {
+        assert_eq!("run".parse::<CounterScope>().unwrap(), CounterScope::Run);
+        assert_eq!("file".parse::<CounterScope>().unwrap(), CounterScope::File);
+        assert!("all".parse::<CounterScope>().is_err());
+    }
+}
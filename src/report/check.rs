@@ -0,0 +1,69 @@
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::ops::rules::{Rule, Severity};
+use crate::report::{ContextRecord, MatchRecord, Reporter, Summary};
+
+/// The default `check` report format:
+///
+/// ```text
+/// [FILEPATH]:[LINE]:[COLUMN]: [SEVERITY][[RULE ID]]: [MESSAGE]
+/// ```
+///
+/// A totals line is written after the traversal.  Columns are 1-based
+/// character columns.
+pub(crate) struct CheckReporter<'a, W: Write> {
+    writer: W,
+    rules: &'a [Rule],
+    errors: usize,
+    warnings: usize,
+    notes: usize,
+}
+
+impl<'a, W: Write> CheckReporter<'a, W> {
+    pub(crate) fn new(writer: W, rules: &'a [Rule]) -> Self {
+        Self {
+            writer,
+            rules,
+            errors: 0,
+            warnings: 0,
+            notes: 0,
+        }
+    }
+}
+
+impl<'a, W: Write> Reporter for CheckReporter<'a, W> {
+    fn matched(&mut self, record: &MatchRecord) -> Result<()> {
+        let rule = &self.rules[record.pattern_match.pattern];
+        match rule.severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+            Severity::Note => self.notes += 1,
+        }
+        writeln!(
+            self.writer,
+            "{}:{}:{}: {}[{}]: {}",
+            record.path.display(),
+            record.line_number,
+            record.column(),
+            rule.severity,
+            rule.id,
+            rule.message
+        )?;
+        Ok(())
+    }
+
+    fn context(&mut self, _record: &ContextRecord) -> Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self, summary: &Summary) -> Result<()> {
+        writeln!(
+            self.writer,
+            "{} error(s), {} warning(s), {} note(s) in {} of {} file(s)",
+            self.errors, self.warnings, self.notes, summary.files_matched, summary.files_searched
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::report::{
+    xml_escape, ContextRecord, FileStats, MatchRecord, ReportRule, Reporter, Summary,
+};
+
+/// Checkstyle XML report format.  Each file with a match is a `file`
+/// element and each match is an `error` element with the rule id as the
+/// `recurse.RULE ID` source.  Context lines are not reported in this
+/// format.
+pub(crate) struct CheckstyleReporter<W: Write> {
+    writer: W,
+    rules: Vec<ReportRule>,
+    has_header: bool,
+}
+
+impl<W: Write> CheckstyleReporter<W> {
+    pub(crate) fn new(writer: W, rules: Vec<ReportRule>) -> Self {
+        Self {
+            writer,
+            rules,
+            has_header: false,
+        }
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        if !self.has_header {
+            writeln!(self.writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+            writeln!(self.writer, r#"<checkstyle version="4.3">"#)?;
+            self.has_header = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Reporter for CheckstyleReporter<W> {
+    fn begin(&mut self, path: &Path) -> Result<()> {
+        self.write_header()?;
+        writeln!(
+            self.writer,
+            r#"  <file name="{}">"#,
+            xml_escape(&path.display().to_string())
+        )?;
+        Ok(())
+    }
+
+    fn matched(&mut self, record: &MatchRecord) -> Result<()> {
+        let rule = &self.rules[record.pattern_match.pattern];
+        // Checkstyle severities are error, warning, info, and ignore
+        let severity = match rule.level.as_str() {
+            "note" => "info",
+            level => level,
+        };
+        writeln!(
+            self.writer,
+            r#"    <error line="{}" column="{}" severity="{}" message="{}" source="recurse.{}"/>"#,
+            record.line_number,
+            record.column(),
+            severity,
+            xml_escape(&rule.result_message(record)),
+            xml_escape(&rule.id)
+        )?;
+        Ok(())
+    }
+
+    fn context(&mut self, _record: &ContextRecord) -> Result<()> {
+        Ok(())
+    }
+
+    fn end(&mut self, _path: &Path, _stats: &FileStats) -> Result<()> {
+        writeln!(self.writer, "  </file>")?;
+        Ok(())
+    }
+
+    fn finish(&mut self, _summary: &Summary) -> Result<()> {
+        self.write_header()?;
+        writeln!(self.writer, "</checkstyle>")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::pattern::{PatternMatch, PatternSet};
+
+    #[test]
+    fn test_checkstyle_report() {
+        let pattern_set = PatternSet::new(vec!["TODO".to_string()], false).unwrap();
+        let rules = vec![ReportRule {
+            id: "no-todo".to_string(),
+            message: Some("Resolve \"TODO\" notes".to_string()),
+            level: "note".to_string(),
+        }];
+        let mut output = Vec::new();
+        let mut reporter = CheckstyleReporter::new(&mut output, rules);
+        let path = Path::new("a&b.txt");
+        let pattern_match = PatternMatch {
+            pattern: 0,
+            start: 4,
+            end: 8,
+        };
+        reporter.begin(path).unwrap();
+        reporter
+            .matched(&MatchRecord {
+                path,
+                line_number: 3,
+                line: "ऄ - TODO",
+                start: 6,
+                end: 10,
+                pattern_set: &pattern_set,
+                pattern_match: &pattern_match,
+            })
+            .unwrap();
+        reporter.end(path, &FileStats::default()).unwrap();
+        reporter.finish(&Summary::default()).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&output).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <checkstyle version=\"4.3\">\n\
+             \x20 <file name=\"a&amp;b.txt\">\n\
+             \x20   <error line=\"3\" column=\"5\" severity=\"info\" message=\"Resolve &quot;TODO&quot; notes\" source=\"recurse.no-todo\"/>\n\
+             \x20 </file>\n\
+             </checkstyle>\n"
+        );
+    }
+
+    #[test]
+    fn test_checkstyle_report_without_matches() {
+        let mut output = Vec::new();
+        let mut reporter = CheckstyleReporter::new(&mut output, vec![]);
+        reporter.finish(&Summary::default()).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&output).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n</checkstyle>\n"
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::report::{ContextRecord, MatchRecord, Reporter};
+
+/// Editor jump list report formats.  All columns are 1-based
+/// character columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EditorFormat {
+    /// Vim `:grep` and quickfix format `path:line:col:text`
+    Vimgrep,
+    /// Emacs compilation mode format `path:line:col: text`
+    Emacs,
+    /// VS Code `$msCompile` problem matcher format
+    /// `path(line,col,line,end col): info: text`
+    Vscode,
+}
+
+/// Editor jump list match report format.  Context lines are not
+/// reported in these formats.
+pub(crate) struct EditorReporter<W: Write> {
+    writer: W,
+    format: EditorFormat,
+}
+
+impl<W: Write> EditorReporter<W> {
+    pub(crate) fn new(writer: W, format: EditorFormat) -> Self {
+        Self { writer, format }
+    }
+}
+
+impl<W: Write> Reporter for EditorReporter<W> {
+    fn matched(&mut self, record: &MatchRecord) -> Result<()> {
+        let path = record.path.display();
+        match self.format {
+            EditorFormat::Vimgrep => writeln!(
+                self.writer,
+                "{}:{}:{}:{}",
+                path,
+                record.line_number,
+                record.column(),
+                record.line
+            )?,
+            EditorFormat::Emacs => writeln!(
+                self.writer,
+                "{}:{}:{}: {}",
+                path,
+                record.line_number,
+                record.column(),
+                record.text()
+            )?,
+            EditorFormat::Vscode => writeln!(
+                self.writer,
+                "{}({},{},{},{}): info: {}",
+                path,
+                record.line_number,
+                record.column(),
+                record.line_number,
+                record.end_column(),
+                record.text()
+            )?,
+        }
+        Ok(())
+    }
+
+    fn context(&mut self, _record: &ContextRecord) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::pattern::{PatternMatch, PatternSet};
+    use std::path::Path;
+
+    fn render(format: EditorFormat) -> String {
+        let pattern_set = PatternSet::new(vec!["वि".to_string()], false).unwrap();
+        let line = "a विकि";
+        let pattern_match = PatternMatch {
+            pattern: 0,
+            start: 2,
+            end: 8,
+        };
+        let record = MatchRecord {
+            path: Path::new("a.txt"),
+            line_number: 3,
+            line,
+            start: 2,
+            end: 8,
+            pattern_set: &pattern_set,
+            pattern_match: &pattern_match,
+        };
+        let mut output = Vec::new();
+        EditorReporter::new(&mut output, format)
+            .matched(&record)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_editor_formats() {
+        assert_eq!(render(EditorFormat::Vimgrep), "a.txt:3:3:a विकि\n");
+        assert_eq!(render(EditorFormat::Emacs), "a.txt:3:3: वि\n");
+        assert_eq!(render(EditorFormat::Vscode), "a.txt(3,3,3,5): info: वि\n");
+    }
+}
@@ -0,0 +1,188 @@
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::report::{ContextRecord, FileStats, MatchRecord, Reporter, Summary};
+
+/// The JSON Lines output schema version.  The version is incremented
+/// when record fields are removed or change meaning.
+pub(crate) const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Writes a single JSON Lines record with the `record_type` type
+/// and the `data` record data.
+pub(crate) fn write_record(writer: &mut impl Write, record_type: &str, data: Value) -> Result<()> {
+    let record = json!({
+        "version": JSON_SCHEMA_VERSION,
+        "type": record_type,
+        "data": data,
+    });
+    writeln!(writer, "{}", record)?;
+    Ok(())
+}
+
+/// Returns the JSON representation of a file path.  Valid UTF-8 paths
+/// are defined as `{"text": PATH}`.  Paths that are not valid UTF-8
+/// are defined as `{"bytes": BASE64}` with the standard base64 encoding
+/// of the raw path bytes on Unix platforms.
+pub(crate) fn path_value(path: &Path) -> Value {
+    match path.to_str() {
+        Some(text) => json!({ "text": text }),
+        None => non_utf8_path_value(path),
+    }
+}
+
+#[cfg(unix)]
+fn non_utf8_path_value(path: &Path) -> Value {
+    use std::os::unix::ffi::OsStrExt;
+    json!({ "bytes": base64_encode(path.as_os_str().as_bytes()) })
+}
+
+#[cfg(not(unix))]
+fn non_utf8_path_value(path: &Path) -> Value {
+    json!({ "text": path.to_string_lossy() })
+}
+
+/// Returns the standard base64 encoding (with padding) of `bytes`.
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// JSON Lines `find` report format with `begin`, `match`, `context`,
+/// `end`, and `summary` records.
+pub(crate) struct JsonReporter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonReporter<W> {
+    pub(crate) fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> Reporter for JsonReporter<W> {
+    fn begin(&mut self, path: &Path) -> Result<()> {
+        write_record(
+            &mut self.writer,
+            "begin",
+            json!({ "path": path_value(path) }),
+        )
+    }
+
+    fn matched(&mut self, record: &MatchRecord) -> Result<()> {
+        write_record(
+            &mut self.writer,
+            "match",
+            json!({
+                "path": path_value(record.path),
+                "line_number": record.line_number,
+                "line": record.line,
+                "text": record.text(),
+                "start": record.start,
+                "end": record.end,
+                "column": record.column(),
+                "pattern": record.pattern(),
+                "pattern_index": record.pattern_match.pattern,
+            }),
+        )
+    }
+
+    fn context(&mut self, record: &ContextRecord) -> Result<()> {
+        write_record(
+            &mut self.writer,
+            "context",
+            json!({
+                "path": path_value(record.path),
+                "line_number": record.line_number,
+                "line": record.line,
+            }),
+        )
+    }
+
+    fn end(&mut self, path: &Path, stats: &FileStats) -> Result<()> {
+        write_record(
+            &mut self.writer,
+            "end",
+            json!({
+                "path": path_value(path),
+                "stats": {
+                    "matches": stats.matches,
+                    "matched_lines": stats.matched_lines,
+                },
+            }),
+        )
+    }
+
+    fn finish(&mut self, summary: &Summary) -> Result<()> {
+        write_record(
+            &mut self.writer,
+            "summary",
+            json!({
+                "files_searched": summary.files_searched,
+                "files_matched": summary.files_matched,
+                "matches": summary.matches,
+                "matched_lines": summary.matched_lines,
+            }),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+        assert_eq!(base64_encode(&[0xff, 0xfe, 0x00]), "//4A");
+    }
+
+    #[test]
+    fn test_path_value_utf8() {
+        let path = PathBuf::from("a/b.txt");
+        assert_eq!(path_value(&path), json!({"text": "a/b.txt"}));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_value_non_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(OsStr::from_bytes(b"a/\xff.txt"));
+        assert_eq!(path_value(path), json!({"bytes": "YS//LnR4dA=="}));
+    }
+
+    #[test]
+    fn test_write_record() {
+        let mut output = Vec::new();
+        write_record(&mut output, "entry", json!({"a": "\"quoted\"\n"})).unwrap();
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        assert_eq!(
+            output_slice,
+            "{\"version\":1,\"type\":\"entry\",\"data\":{\"a\":\"\\\"quoted\\\"\\n\"}}\n"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::report::{xml_escape, ContextRecord, MatchRecord, ReportRule, Reporter, Summary};
+
+/// The rule failures in a single file.  Failure descriptions are
+/// grouped by rule index.
+struct FileFailures {
+    path: String,
+    failures: BTreeMap<usize, Vec<String>>,
+}
+
+/// JUnit XML report format.  Each file with a match is a test suite
+/// and each rule with a match in the file is a failed test case.  Rules
+/// without matches are passed test cases in a final `recurse` test
+/// suite.  The report is written after the traversal.  Context lines
+/// are not reported in this format.
+pub(crate) struct JunitReporter<W: Write> {
+    writer: W,
+    rules: Vec<ReportRule>,
+    files: Vec<FileFailures>,
+}
+
+impl<W: Write> JunitReporter<W> {
+    pub(crate) fn new(writer: W, rules: Vec<ReportRule>) -> Self {
+        Self {
+            writer,
+            rules,
+            files: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Reporter for JunitReporter<W> {
+    fn begin(&mut self, path: &Path) -> Result<()> {
+        self.files.push(FileFailures {
+            path: path.display().to_string(),
+            failures: BTreeMap::new(),
+        });
+        Ok(())
+    }
+
+    fn matched(&mut self, record: &MatchRecord) -> Result<()> {
+        let rule = &self.rules[record.pattern_match.pattern];
+        let description = format!(
+            "{}:{}:{}: {}",
+            record.path.display(),
+            record.line_number,
+            record.column(),
+            rule.result_message(record)
+        );
+        if let Some(file) = self.files.last_mut() {
+            file.failures
+                .entry(record.pattern_match.pattern)
+                .or_insert_with(Vec::new)
+                .push(description);
+        }
+        Ok(())
+    }
+
+    fn context(&mut self, _record: &ContextRecord) -> Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self, _summary: &Summary) -> Result<()> {
+        let mut matched_rules = vec![false; self.rules.len()];
+        for file in &self.files {
+            for rule_index in file.failures.keys() {
+                matched_rules[*rule_index] = true;
+            }
+        }
+        let passed = matched_rules.iter().filter(|matched| !**matched).count();
+        let failures: usize = self.files.iter().map(|file| file.failures.len()).sum();
+
+        writeln!(self.writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            self.writer,
+            r#"<testsuites name="recurse" tests="{}" failures="{}">"#,
+            failures + passed,
+            failures
+        )?;
+        for file in &self.files {
+            let path = xml_escape(&file.path);
+            writeln!(
+                self.writer,
+                r#"  <testsuite name="{}" tests="{}" failures="{}">"#,
+                path,
+                file.failures.len(),
+                file.failures.len()
+            )?;
+            for (rule_index, descriptions) in &file.failures {
+                let rule = &self.rules[*rule_index];
+                writeln!(
+                    self.writer,
+                    r#"    <testcase name="{}" classname="{}">"#,
+                    xml_escape(&rule.id),
+                    path
+                )?;
+                writeln!(
+                    self.writer,
+                    r#"      <failure message="{}" type="{}">{}</failure>"#,
+                    xml_escape(&rule.description()),
+                    rule.level,
+                    xml_escape(&descriptions.join("\n"))
+                )?;
+                writeln!(self.writer, "    </testcase>")?;
+            }
+            writeln!(self.writer, "  </testsuite>")?;
+        }
+        if passed > 0 {
+            writeln!(
+                self.writer,
+                r#"  <testsuite name="recurse" tests="{}" failures="0">"#,
+                passed
+            )?;
+            for (rule, _) in self
+                .rules
+                .iter()
+                .zip(&matched_rules)
+                .filter(|(_, matched)| !**matched)
+            {
+                writeln!(
+                    self.writer,
+                    r#"    <testcase name="{}" classname="recurse"/>"#,
+                    xml_escape(&rule.id)
+                )?;
+            }
+            writeln!(self.writer, "  </testsuite>")?;
+        }
+        writeln!(self.writer, "</testsuites>")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::pattern::{PatternMatch, PatternSet};
+    use crate::report::{pattern_rules, FileStats};
+
+    #[test]
+    fn test_junit_report() {
+        let patterns = vec!["TODO".to_string(), "a<b".to_string(), "unused".to_string()];
+        let pattern_set = PatternSet::new(patterns, false).unwrap();
+        let rules = pattern_rules(&pattern_set);
+        let mut output = Vec::new();
+        let mut reporter = JunitReporter::new(&mut output, rules);
+        let path = Path::new("a.txt");
+        let line = "TODO a<b TODO";
+        reporter.begin(path).unwrap();
+        for (pattern, start, end) in &[(0, 0, 4), (1, 5, 8), (0, 9, 13)] {
+            let pattern_match = PatternMatch {
+                pattern: *pattern,
+                start: *start,
+                end: *end,
+            };
+            reporter
+                .matched(&MatchRecord {
+                    path,
+                    line_number: 1,
+                    line,
+                    start: *start,
+                    end: *end,
+                    pattern_set: &pattern_set,
+                    pattern_match: &pattern_match,
+                })
+                .unwrap();
+        }
+        reporter.end(path, &FileStats::default()).unwrap();
+        reporter.finish(&Summary::default()).unwrap();
+        let output_slice = std::str::from_utf8(&output).unwrap();
+        assert!(output_slice.starts_with("<?xml"));
+        assert!(output_slice.contains(r#"<testsuites name="recurse" tests="3" failures="2">"#));
+        assert!(output_slice.contains(r#"<testsuite name="a.txt" tests="2" failures="2">"#));
+        assert!(output_slice.contains(r#"<testcase name="a&lt;b" classname="a.txt">"#));
+        assert!(output_slice.contains(
+            "a.txt:1:1: &apos;TODO&apos; matches the &apos;TODO&apos; pattern\n\
+             a.txt:1:10: &apos;TODO&apos; matches the &apos;TODO&apos; pattern</failure>"
+        ));
+        assert!(output_slice.contains(r#"<testcase name="unused" classname="recurse"/>"#));
+        assert!(output_slice.trim_end().ends_with("</testsuites>"));
+    }
+}
@@ -0,0 +1,185 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::ops::pattern::{char_column, PatternMatch, PatternSet};
+
+pub(crate) mod check;
+pub(crate) mod checkstyle;
+pub(crate) mod editor;
+pub(crate) mod json;
+pub(crate) mod junit;
+pub(crate) mod sarif;
+pub(crate) mod standard;
+pub(crate) mod template;
+
+/// A pattern match in a line of a file.  The `start` and `end` fields
+/// are the reported byte offsets in the line and differ from the
+/// `pattern_match` offsets when a capture group is reported.
+pub(crate) struct MatchRecord<'a> {
+    pub(crate) path: &'a Path,
+    pub(crate) line_number: usize,
+    pub(crate) line: &'a str,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) pattern_set: &'a PatternSet,
+    pub(crate) pattern_match: &'a PatternMatch,
+}
+
+impl<'a> MatchRecord<'a> {
+    /// Returns the reported match string.
+    pub(crate) fn text(&self) -> &'a str {
+        &self.line[self.start..self.end]
+    }
+
+    /// Returns the 1-based character column of the match start.
+    pub(crate) fn column(&self) -> usize {
+        char_column(self.line, self.start)
+    }
+
+    /// Returns the 1-based character column that follows the match end.
+    pub(crate) fn end_column(&self) -> usize {
+        char_column(self.line, self.end)
+    }
+
+    /// Returns the pattern that produced the match.
+    pub(crate) fn pattern(&self) -> &'a str {
+        self.pattern_set.pattern(self.pattern_match.pattern)
+    }
+}
+
+/// A reporting rule.  Rules are indexed by the pattern index of the
+/// reported matches.  The `level` is one of `error`, `warning`, or
+/// `note`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ReportRule {
+    pub(crate) id: String,
+    pub(crate) message: Option<String>,
+    pub(crate) level: String,
+}
+
+impl ReportRule {
+    /// Returns a `warning` level rule with the `pattern` string as the
+    /// rule id.
+    pub(crate) fn from_pattern(pattern: &str) -> Self {
+        Self {
+            id: pattern.to_string(),
+            message: None,
+            level: "warning".to_string(),
+        }
+    }
+
+    /// Returns the rule description.
+    pub(crate) fn description(&self) -> String {
+        match &self.message {
+            Some(message) => message.clone(),
+            None => format!("Matches of the '{}' pattern", self.id),
+        }
+    }
+
+    /// Returns the report message of the `record` match.
+    pub(crate) fn result_message(&self, record: &MatchRecord) -> String {
+        match &self.message {
+            Some(message) => message.clone(),
+            None => format!(
+                "'{}' matches the '{}' pattern",
+                record.text(),
+                record.pattern()
+            ),
+        }
+    }
+}
+
+/// Returns the reporting rules of the `pattern_set` find patterns.
+pub(crate) fn pattern_rules(pattern_set: &PatternSet) -> Vec<ReportRule> {
+    (0..pattern_set.len())
+        .map(|i| ReportRule::from_pattern(pattern_set.pattern(i)))
+        .collect()
+}
+
+/// Returns the `text` string with the XML markup characters escaped.
+/// Characters that are not valid in XML 1.0 documents are removed.
+pub(crate) fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' || c == '\u{FFFE}' || c == '\u{FFFF}' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A line of context before or after a line with a match.
+pub(crate) struct ContextRecord<'a> {
+    pub(crate) path: &'a Path,
+    pub(crate) line_number: usize,
+    pub(crate) line: &'a str,
+}
+
+/// Match statistics for a single file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct FileStats {
+    pub(crate) matches: usize,
+    pub(crate) matched_lines: usize,
+}
+
+/// Match statistics for a complete traversal.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Summary {
+    pub(crate) files_searched: usize,
+    pub(crate) files_matched: usize,
+    pub(crate) matches: usize,
+    pub(crate) matched_lines: usize,
+}
+
+impl Summary {
+    /// Adds the `stats` statistics of a searched file to the summary.
+    pub(crate) fn add(&mut self, stats: &FileStats) {
+        self.files_searched += 1;
+        if stats.matches > 0 {
+            self.files_matched += 1;
+        }
+        self.matches += stats.matches;
+        self.matched_lines += stats.matched_lines;
+    }
+}
+
+/// Match report output implementation.  The `begin` and `end` methods
+/// are only called for files with at least one match.
+pub(crate) trait Reporter {
+    fn begin(&mut self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    fn matched(&mut self, record: &MatchRecord) -> Result<()>;
+
+    fn context(&mut self, record: &ContextRecord) -> Result<()>;
+
+    fn end(&mut self, _path: &Path, _stats: &FileStats) -> Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self, _summary: &Summary) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(
+            xml_escape("<a href=\"x\">'&'</a>\t\u{1B}"),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;\t"
+        );
+    }
+}
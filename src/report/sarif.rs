@@ -0,0 +1,212 @@
+use std::io::Write;
+use std::path::{Component, Path};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::report::{ContextRecord, MatchRecord, ReportRule, Reporter, Summary};
+
+/// The SARIF specification version of the report.
+pub(crate) const SARIF_VERSION: &str = "2.1.0";
+
+/// The SARIF 2.1.0 JSON schema URI.
+pub(crate) const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Returns the SARIF reporting descriptor of the `rule`.
+fn rule_descriptor(rule: &ReportRule) -> Value {
+    json!({
+        "id": rule.id,
+        "shortDescription": { "text": rule.description() },
+        "defaultConfiguration": { "level": rule.level },
+    })
+}
+
+/// SARIF 2.1.0 report format.  Results are written in a single log
+/// document after the traversal.  Context lines are not reported in
+/// this format.
+pub(crate) struct SarifReporter<W: Write> {
+    writer: W,
+    rules: Vec<ReportRule>,
+    results: Vec<Value>,
+}
+
+impl<W: Write> SarifReporter<W> {
+    pub(crate) fn new(writer: W, rules: Vec<ReportRule>) -> Self {
+        Self {
+            writer,
+            rules,
+            results: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Reporter for SarifReporter<W> {
+    fn matched(&mut self, record: &MatchRecord) -> Result<()> {
+        let rule_index = record.pattern_match.pattern;
+        let rule = &self.rules[rule_index];
+        let message = rule.result_message(record);
+        self.results.push(json!({
+            "ruleId": rule.id,
+            "ruleIndex": rule_index,
+            "level": rule.level,
+            "message": { "text": message },
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": path_uri(record.path) },
+                    "region": {
+                        "startLine": record.line_number,
+                        "startColumn": record.column(),
+                        "endLine": record.line_number,
+                        "endColumn": record.end_column(),
+                        "snippet": { "text": record.line },
+                    },
+                },
+            }],
+        }));
+        Ok(())
+    }
+
+    fn context(&mut self, _record: &ContextRecord) -> Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self, _summary: &Summary) -> Result<()> {
+        let rules: Vec<Value> = self.rules.iter().map(rule_descriptor).collect();
+        let log = json!({
+            "$schema": SARIF_SCHEMA,
+            "version": SARIF_VERSION,
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": "https://github.com/chrissimpkins/recurse",
+                        "rules": rules,
+                    },
+                },
+                // columns are reported as Unicode scalar value counts, not
+                // the SARIF default UTF-16 code unit counts
+                "columnKind": "unicodeCodePoints",
+                "results": std::mem::take(&mut self.results),
+            }],
+        });
+        writeln!(self.writer, "{}", serde_json::to_string_pretty(&log)?)?;
+        Ok(())
+    }
+}
+
+/// Returns the SARIF artifact location URI of `path`.  Relative paths
+/// are relative URI references with `/` separators and absolute paths
+/// are `file` scheme URIs.
+pub(crate) fn path_uri(path: &Path) -> String {
+    let mut segments = Vec::new();
+    let mut prefix = String::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(p) => prefix = p.as_os_str().to_string_lossy().to_string(),
+            Component::RootDir => segments.push(String::new()),
+            Component::CurDir => {}
+            Component::ParentDir => segments.push("..".to_string()),
+            Component::Normal(segment) => segments.push(percent_encode(&segment.to_string_lossy())),
+        }
+    }
+    let uri = segments.join("/");
+    if path.is_absolute() {
+        if prefix.is_empty() {
+            format!("file://{}", uri)
+        } else {
+            format!("file:///{}{}", prefix, uri)
+        }
+    } else {
+        uri
+    }
+}
+
+/// Returns the `text` string with all bytes other than the RFC 3986
+/// unreserved characters percent-encoded.
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::pattern::{PatternMatch, PatternSet};
+
+    #[test]
+    fn test_path_uri() {
+        assert_eq!(path_uri(Path::new("./src/a b.rs")), "src/a%20b.rs");
+        assert_eq!(
+            path_uri(Path::new("../वि.txt")),
+            "../%E0%A4%B5%E0%A4%BF.txt"
+        );
+        #[cfg(unix)]
+        assert_eq!(path_uri(Path::new("/tmp/a.txt")), "file:///tmp/a.txt");
+    }
+
+    #[test]
+    fn test_sarif_log_structure() {
+        let pattern_set =
+            PatternSet::new(vec!["TODO".to_string(), "FIXME".to_string()], false).unwrap();
+        let rules = vec![
+            ReportRule::from_pattern("TODO"),
+            ReportRule {
+                id: "no-fixme".to_string(),
+                message: Some("Resolve FIXME notes".to_string()),
+                level: "error".to_string(),
+            },
+        ];
+        let line = "ऄ FIXME";
+        let pattern_match = PatternMatch {
+            pattern: 1,
+            start: 4,
+            end: 9,
+        };
+        let mut output = Vec::new();
+        let mut reporter = SarifReporter::new(&mut output, rules);
+        reporter
+            .matched(&MatchRecord {
+                path: Path::new("./src/main.rs"),
+                line_number: 7,
+                line,
+                start: 4,
+                end: 9,
+                pattern_set: &pattern_set,
+                pattern_match: &pattern_match,
+            })
+            .unwrap();
+        reporter.finish(&Summary::default()).unwrap();
+
+        let log: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(log["$schema"], SARIF_SCHEMA);
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "recurse");
+        assert_eq!(run["columnKind"], "unicodeCodePoints");
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0]["id"], "TODO");
+        assert_eq!(rules[1]["defaultConfiguration"]["level"], "error");
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "no-fixme");
+        assert_eq!(result["ruleIndex"], 1);
+        assert_eq!(result["level"], "error");
+        assert_eq!(result["message"]["text"], "Resolve FIXME notes");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "src/main.rs");
+        let region = &location["region"];
+        assert_eq!(region["startLine"], 7);
+        assert_eq!(region["startColumn"], 3);
+        assert_eq!(region["endLine"], 7);
+        assert_eq!(region["endColumn"], 8);
+    }
+}
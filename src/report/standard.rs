@@ -0,0 +1,62 @@
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::ops::color::ColorStyles;
+use crate::report::{ContextRecord, MatchRecord, Reporter};
+
+/// The default match report format:
+///
+/// ```text
+/// [FILEPATH] [LINE NUMBER]:[START]-[END] [ MATCHED STRING ]
+/// ```
+///
+/// Matches are tagged with the pattern that produced them when more
+/// than one pattern is searched.
+pub(crate) struct StandardReporter<W: Write> {
+    writer: W,
+    styles: ColorStyles,
+}
+
+impl<W: Write> StandardReporter<W> {
+    pub(crate) fn new(writer: W, styles: ColorStyles) -> Self {
+        Self { writer, styles }
+    }
+}
+
+impl<W: Write> Reporter for StandardReporter<W> {
+    fn matched(&mut self, record: &MatchRecord) -> Result<()> {
+        let styles = &self.styles;
+        let mut report = format!(
+            "{} {} {} {} {}",
+            styles.path(&record.path.display().to_string()),
+            styles.line(&format!(
+                "{}:{}-{}",
+                record.line_number, record.start, record.end
+            )),
+            styles.separator("["),
+            styles.matched(record.text()),
+            styles.separator("]"),
+        );
+        if record.pattern_set.len() > 1 {
+            report = format!(
+                "{} {}",
+                report,
+                styles.separator(&format!("({})", record.pattern()))
+            );
+        }
+        writeln!(self.writer, "{}", report)?;
+        Ok(())
+    }
+
+    fn context(&mut self, record: &ContextRecord) -> Result<()> {
+        writeln!(
+            self.writer,
+            "{} {} {}",
+            self.styles.path(&record.path.display().to_string()),
+            self.styles.line(&format!("{}-", record.line_number)),
+            record.line
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::ops::template::{Field, Template};
+use crate::report::{ContextRecord, MatchRecord, Reporter};
+
+/// User-defined match report template format.  Context lines are
+/// not reported in this format.
+pub(crate) struct TemplateReporter<W: Write> {
+    writer: W,
+    template: Template,
+}
+
+impl<W: Write> TemplateReporter<W> {
+    pub(crate) fn new(writer: W, template: Template) -> Self {
+        Self { writer, template }
+    }
+}
+
+impl<W: Write> Reporter for TemplateReporter<W> {
+    fn matched(&mut self, record: &MatchRecord) -> Result<()> {
+        let report = self.template.render(|field| match field {
+            Field::Path => record.path.display().to_string(),
+            Field::Line => record.line_number.to_string(),
+            Field::Column => record.column().to_string(),
+            Field::Start => record.start.to_string(),
+            Field::End => record.end.to_string(),
+            Field::Match => record.text().to_string(),
+            Field::Pattern => record.pattern().to_string(),
+            Field::Group(group) => {
+                match record
+                    .pattern_set
+                    .group_offsets(record.line, record.pattern_match, group)
+                {
+                    Some((start, end)) => record.line[start..end].to_string(),
+                    None => String::new(),
+                }
+            }
+        });
+        writeln!(self.writer, "{}", report)?;
+        Ok(())
+    }
+
+    fn context(&mut self, _record: &ContextRecord) -> Result<()> {
+        Ok(())
+    }
+}
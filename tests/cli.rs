@@ -18,3 +18,508 @@ fn integration_walk_inpath_doesnt_exist() -> Result<(), Box<dyn std::error::Erro
 
     Ok(())
 }
+
+// ==================================
+// Check subcommand integration tests
+// ==================================
+
+#[test]
+fn integration_check_error_violations_exit_status() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("recurse")?;
+
+    cmd.arg("check")
+        .arg("--rules")
+        .arg("tests/testfiles/check/recurse.toml")
+        .arg("tests/testfiles/check");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "error[no-dbg]: Remove dbg! macros",
+        ))
+        .stdout(predicate::str::contains("recurse.toml").not())
+        .stderr(predicate::str::is_empty())
+        .code(1);
+
+    Ok(())
+}
+
+#[test]
+fn integration_check_warning_violations_exit_status() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("recurse")?;
+
+    cmd.arg("check")
+        .arg("-r")
+        .arg("tests/testfiles/check/warnings.toml")
+        .arg("tests/testfiles/check/src");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("warning[no-todo]"));
+
+    Ok(())
+}
+
+#[test]
+fn integration_check_checkstyle() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("recurse")?;
+
+    cmd.arg("check")
+        .arg("--checkstyle")
+        .arg("-r")
+        .arg("tests/testfiles/check/recurse.toml")
+        .arg("tests/testfiles/check/src");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(r#"<checkstyle version="4.3">"#))
+        .stdout(predicate::str::contains(
+            r#"<error line="7" column="5" severity="error" message="Remove dbg! macros" source="recurse.no-dbg"/>"#,
+        ))
+        .code(1);
+
+    Ok(())
+}
+
+#[test]
+fn integration_check_junit() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("recurse")?;
+
+    cmd.arg("check")
+        .arg("--junit")
+        .arg("-r")
+        .arg("tests/testfiles/check/warnings.toml")
+        .arg("tests/testfiles/check/src");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#"<testsuites name="recurse" tests="2" failures="2">"#,
+        ))
+        .stdout(predicate::str::contains(r#"<testcase name="no-todo""#));
+
+    Ok(())
+}
+
+// =================================
+// Find subcommand integration tests
+// =================================
+
+#[test]
+fn integration_find_pattern_options_with_single_positional_inpath(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("recurse")?;
+
+    cmd.env("NO_COLOR", "1")
+        .arg("find")
+        .arg("-p")
+        .arg("1010")
+        .arg("--ext")
+        .arg("md")
+        .arg("tests/testfiles/find/dir1");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("2:0-4 [ 1010 ]"));
+
+    Ok(())
+}
+
+#[test]
+fn integration_find_missing_pattern() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("recurse")?;
+
+    cmd.arg("find");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn integration_find_color_auto_not_a_terminal() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("recurse")?;
+
+    // standard output is a pipe in this test
+    cmd.env_remove("NO_COLOR")
+        .env_remove("CLICOLOR_FORCE")
+        .arg("find")
+        .arg("1010")
+        .arg("tests/testfiles/find/dir1/dir2");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\x1B").not());
+
+    Ok(())
+}
+
+#[test]
+fn integration_find_color_clicolor_force() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("recurse")?;
+
+    cmd.env_remove("NO_COLOR")
+        .env("CLICOLOR_FORCE", "1")
+        .arg("find")
+        .arg("1010")
+        .arg("tests/testfiles/find/dir1/dir2");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\x1B[31m1010\x1B[0m"));
+
+    Ok(())
+}
+
+#[test]
+fn integration_find_color_no_color_overrides_force() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("recurse")?;
+
+    cmd.env("NO_COLOR", "1")
+        .env("CLICOLOR_FORCE", "1")
+        .arg("find")
+        .arg("1010")
+        .arg("tests/testfiles/find/dir1/dir2");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\x1B").not());
+
+    Ok(())
+}
+
+/// Validates the `log` against the SARIF 2.1.0 JSON schema and checks the
+/// constraints that the schema cannot express.  The language code
+/// patterns of the vendored schema are corrected because the published
+/// patterns are not valid ECMA 262 regular expressions.
+fn validate_sarif_log(log: &serde_json::Value) {
+    let schema: serde_json::Value =
+        serde_json::from_str(include_str!("sarif-schema-2.1.0.json")).unwrap();
+    let validator = jsonschema::validator_for(&schema).unwrap();
+    let errors: Vec<String> = validator
+        .iter_errors(log)
+        .map(|error| format!("{} at {}", error, error.instance_path()))
+        .collect();
+    assert!(errors.is_empty(), "invalid SARIF log: {:#?}", errors);
+    assert!(log["$schema"]
+        .as_str()
+        .unwrap()
+        .ends_with("sarif-2.1.0.json"));
+    for run in log["runs"].as_array().unwrap() {
+        // rule indexes refer to the rule with the result rule id
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        for result in run["results"].as_array().unwrap() {
+            let rule_index = result["ruleIndex"].as_u64().unwrap() as usize;
+            assert_eq!(rules[rule_index]["id"], result["ruleId"]);
+            for location in result["locations"].as_array().unwrap() {
+                let region = &location["physicalLocation"]["region"];
+                assert!(region["endLine"].as_u64() >= region["startLine"].as_u64());
+            }
+        }
+    }
+}
+
+#[test]
+fn integration_find_sarif() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("recurse")?;
+
+    cmd.arg("find")
+        .arg("--sarif")
+        .arg("-p")
+        .arg("1010")
+        .arg("-p")
+        .arg("This")
+        .arg("tests/testfiles/find/dir1/dir2/test2.txt");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let log: serde_json::Value = serde_json::from_slice(&output)?;
+    validate_sarif_log(&log);
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[1]["ruleId"], "1010");
+    assert_eq!(
+        results[1]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+        "tests/testfiles/find/dir1/dir2/test2.txt"
+    );
+    assert_eq!(
+        results[1]["locations"][0]["physicalLocation"]["region"]["startLine"],
+        2
+    );
+
+    Ok(())
+}
+
+// =====================================
+// Replace subcommand integration tests
+// =====================================
+
+#[test]
+fn integration_replace_history_undo() -> Result<(), Box<dyn std::error::Error>> {
+    // default temporary directory names are hidden paths
+    let workdir = tempfile::Builder::new().prefix("recurse-").tempdir()?;
+    let journal_dir = tempfile::tempdir()?;
+    let filepath = workdir.path().join("test.txt");
+    std::fs::write(&filepath, "a test\n")?;
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.env("RECURSE_JOURNAL_DIR", journal_dir.path())
+        .arg("replace")
+        .arg("--nobu")
+        .arg("-f")
+        .arg("test")
+        .arg("-r")
+        .arg("check")
+        .arg(workdir.path());
+    cmd.assert().success();
+    assert_eq!(std::fs::read_to_string(&filepath)?, "a check\n");
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.env("RECURSE_JOURNAL_DIR", journal_dir.path())
+        .arg("history");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "1 file(s)  recurse replace --nobu -f test -r check",
+    ));
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.env("RECURSE_JOURNAL_DIR", journal_dir.path())
+        .arg("undo");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("test.txt restored"));
+    assert_eq!(std::fs::read_to_string(&filepath)?, "a test\n");
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.env("RECURSE_JOURNAL_DIR", journal_dir.path())
+        .arg("history");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1 file(s), undone"));
+
+    Ok(())
+}
+
+#[test]
+fn integration_replace_numbered_backups_excluded() -> Result<(), Box<dyn std::error::Error>> {
+    // default temporary directory names are hidden paths
+    let workdir = tempfile::Builder::new().prefix("recurse-").tempdir()?;
+    let filepath = workdir.path().join("test.txt");
+    std::fs::write(&filepath, "a test\n")?;
+
+    for (find, replace) in [("test", "check"), ("check", "test")] {
+        let mut cmd = Command::cargo_bin("recurse")?;
+        cmd.arg("replace")
+            .arg("--no-journal")
+            .arg("--backup-naming")
+            .arg("numbered")
+            .arg("-f")
+            .arg(find)
+            .arg("-r")
+            .arg(replace)
+            .arg(workdir.path());
+        cmd.assert().success();
+    }
+    assert_eq!(
+        std::fs::read_to_string(workdir.path().join("test.txt.1.bu"))?,
+        "a test\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(workdir.path().join("test.txt.2.bu"))?,
+        "a check\n"
+    );
+
+    // backups are not searched
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("contains").arg("test").arg(workdir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("test.txt\n"))
+        .stdout(predicate::str::contains(".bu").not());
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("backups")
+        .arg("list")
+        .arg("--backup-naming")
+        .arg("numbered")
+        .arg(workdir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("test.txt.2.bu -> "));
+
+    Ok(())
+}
+
+#[test]
+fn integration_find_backup_dir_excluded() -> Result<(), Box<dyn std::error::Error>> {
+    let workdir = tempfile::Builder::new().prefix("recurse-").tempdir()?;
+    let backup_dir = workdir.path().join("backups");
+    std::fs::write(workdir.path().join("test.txt"), "a test\n")?;
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("replace")
+        .arg("--no-journal")
+        .arg("--backup-dir")
+        .arg(&backup_dir)
+        .arg("--backup-suffix")
+        .arg(".orig")
+        .arg("-f")
+        .arg("test")
+        .arg("-r")
+        .arg("test check")
+        .arg(workdir.path());
+    cmd.assert().success();
+    assert!(backup_dir.join("test.txt.orig").is_file());
+
+    // the backup directory is searched unless it is excluded
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("find").arg("test").arg(workdir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("test.txt.orig"));
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("find")
+        .arg("--backup-dir")
+        .arg(&backup_dir)
+        .arg("test")
+        .arg(workdir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("test.txt"))
+        .stdout(predicate::str::contains("backups").not());
+
+    // backups with another suffix next to the original files
+    std::fs::write(workdir.path().join("test.txt.orig"), "a test\n")?;
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.env("RECURSE_BACKUP_SUFFIX", ".orig")
+        .arg("find")
+        .arg("--backup-dir")
+        .arg(&backup_dir)
+        .arg("test")
+        .arg(workdir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(".orig").not());
+
+    Ok(())
+}
+
+#[test]
+fn integration_replace_exit_status() -> Result<(), Box<dyn std::error::Error>> {
+    // default temporary directory names are hidden paths
+    let workdir = tempfile::Builder::new().prefix("recurse-").tempdir()?;
+    std::fs::write(workdir.path().join("test.txt"), "a test\n")?;
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("replace")
+        .arg("--no-journal")
+        .arg("-f")
+        .arg("bogus")
+        .arg("-r")
+        .arg("check")
+        .arg(workdir.path());
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "0 file(s) updated with 0 replacement(s)",
+        ))
+        .stderr(predicate::str::is_empty())
+        .code(1);
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("replace")
+        .arg("--no-journal")
+        .arg("--expect")
+        .arg("2")
+        .arg("-f")
+        .arg("test")
+        .arg("-r")
+        .arg("check")
+        .arg(workdir.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Error: expected 2 replacement(s), found 1",
+        ))
+        .code(2);
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("replace")
+        .arg("--no-journal")
+        .arg("-f")
+        .arg("test")
+        .arg("-r")
+        .arg("check")
+        .arg(workdir.path().join("bogus"));
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("no such file or directory"))
+        .code(2);
+
+    Ok(())
+}
+
+#[test]
+fn integration_replace_rules_file() -> Result<(), Box<dyn std::error::Error>> {
+    // default temporary directory names are hidden paths
+    let workdir = tempfile::Builder::new().prefix("recurse-").tempdir()?;
+    let rules = workdir.path().join("rules.csv");
+    std::fs::write(
+        &rules,
+        "find,replace,extension\ncolour,color,\ncolor,hue,md\n",
+    )?;
+    let srcdir = workdir.path().join("src");
+    std::fs::create_dir_all(&srcdir)?;
+    std::fs::write(srcdir.join("test.txt"), "colour\n")?;
+    std::fs::write(srcdir.join("test.md"), "colour\n")?;
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("replace")
+        .arg("--no-journal")
+        .arg("--rules")
+        .arg(&rules)
+        .arg(&srcdir);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "2 file(s) updated with 3 replacement(s)",
+    ));
+    assert_eq!(std::fs::read_to_string(srcdir.join("test.txt"))?, "color\n");
+    assert_eq!(std::fs::read_to_string(srcdir.join("test.md"))?, "hue\n");
+    assert_eq!(
+        std::fs::read_to_string(srcdir.join("test.md.bu"))?,
+        "colour\n"
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn integration_replace_with_cmd() -> Result<(), Box<dyn std::error::Error>> {
+    // default temporary directory names are hidden paths
+    let workdir = tempfile::Builder::new().prefix("recurse-").tempdir()?;
+    let testpath = workdir.path().join("test.txt");
+    std::fs::write(&testpath, "id=ab id=cd\n")?;
+
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("replace")
+        .arg("--no-journal")
+        .arg("--nobu")
+        .arg("--find")
+        .arg(r"id=(\w+)")
+        .arg("--with-cmd")
+        .arg("tr a-z A-Z")
+        .arg("--cmd-group")
+        .arg("1")
+        .arg(workdir.path());
+    cmd.assert().success().stdout(predicate::str::contains(
+        "1 file(s) updated with 2 replacement(s)",
+    ));
+    assert_eq!(std::fs::read_to_string(&testpath)?, "AB CD\n");
+
+    // a failed command leaves the file untouched
+    let mut cmd = Command::cargo_bin("recurse")?;
+    cmd.arg("replace")
+        .arg("--no-journal")
+        .arg("--nobu")
+        .arg("--find")
+        .arg("AB")
+        .arg("--with-cmd")
+        .arg("echo broken >&2; exit 1")
+        .arg(workdir.path());
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::str::contains("broken, not updated"))
+        .stdout(predicate::str::contains(
+            "1 file(s) not updated due to replacement command failures:",
+        ));
+    assert_eq!(std::fs::read_to_string(&testpath)?, "AB CD\n");
+    Ok(())
+}
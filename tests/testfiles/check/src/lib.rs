@@ -0,0 +1,8 @@
+fn main() {
+    // TODO: untracked
+    // TODO(#12): tracked
+    // TODO: suppressed recurse:allow(no-todo)
+    // recurse:allow(no-dbg)
+    dbg!(1);
+    dbg!(2);
+}